@@ -0,0 +1,162 @@
+use nalgebra::{Matrix3, Matrix3x6, Matrix6, Vector2, Vector3, Vector6, UnitQuaternion, RealField, U3};
+use crate::{AhrsError, Ahrs};
+
+/// Multiplicative extended Kalman filter, estimating orientation and gyroscope bias. The error
+/// state is made of the attitude error (rad, body frame), applied to the orientation quaternion
+/// after each correction, and of the gyroscope bias error (rad/s).
+#[derive(Debug)]
+pub struct Ekf<N: RealField> {
+	/// Gyroscope noise density (rad/s/√Hz).
+	gyr_noise: N,
+	/// Gyroscope bias random walk (rad/s²/√Hz).
+	gyr_bias_noise: N,
+	/// Standard deviation of normalized accelerometer measurements.
+	acc_noise: N,
+	/// Standard deviation of normalized magnetometer measurements.
+	mag_noise: N,
+	/// Filter state quaternion.
+	state: UnitQuaternion<N>,
+	gyr_bias: Vector3<N>,
+	covariance: Matrix6<N>,
+}
+
+impl<N: RealField> Ekf<N> {
+	pub fn new(gyr_noise: N, gyr_bias_noise: N, acc_noise: N, mag_noise: N) -> Self {
+		// Orientation is unknown, bias is expected within a few degrees per second
+		let attitude_variance = N::one();
+		let gyr_bias_variance: N = nalgebra::convert(0.05 * 0.05);
+
+		Ekf {
+			gyr_noise,
+			gyr_bias_noise,
+			acc_noise,
+			mag_noise,
+			state: UnitQuaternion::identity(),
+			gyr_bias: Vector3::zeros(),
+			covariance: Matrix6::from_diagonal(&Vector6::new(
+				attitude_variance, attitude_variance, attitude_variance,
+				gyr_bias_variance, gyr_bias_variance, gyr_bias_variance,
+			)),
+		}
+	}
+
+	/// Estimated gyroscope bias (rad/s), already removed from orientation updates.
+	pub fn gyr_bias(&self) -> Vector3<N> {
+		self.gyr_bias
+	}
+
+	/// Covariance of the error state: attitude error (rad, body frame) followed by gyroscope
+	/// bias error (rad/s).
+	pub fn covariance(&self) -> &Matrix6<N> {
+		&self.covariance
+	}
+
+	/// Standard deviation of the attitude error (rad) around each body axis.
+	pub fn attitude_std(&self) -> Vector3<N> {
+		Vector3::new(self.covariance[(0, 0)].sqrt(),
+					 self.covariance[(1, 1)].sqrt(),
+					 self.covariance[(2, 2)].sqrt())
+	}
+
+	fn predict(&mut self, gyroscope: &Vector3<N>, dt: N) {
+		let rate = gyroscope - self.gyr_bias;
+
+		self.state = UnitQuaternion::new_normalize(
+			(self.state * UnitQuaternion::from_scaled_axis(rate * dt)).into_inner());
+
+		let mut transition = Matrix6::identity();
+		transition.fixed_slice_mut::<U3, U3>(0, 0)
+			.copy_from(&(Matrix3::identity() - rate.cross_matrix() * dt));
+		transition.fixed_slice_mut::<U3, U3>(0, 3)
+			.copy_from(&(Matrix3::identity() * -dt));
+
+		let attitude_noise = self.gyr_noise * self.gyr_noise * dt;
+		let gyr_bias_noise = self.gyr_bias_noise * self.gyr_bias_noise * dt;
+
+		self.covariance = transition * self.covariance * transition.transpose()
+			+ Matrix6::from_diagonal(&Vector6::new(
+			attitude_noise, attitude_noise, attitude_noise,
+			gyr_bias_noise, gyr_bias_noise, gyr_bias_noise,
+		));
+	}
+
+	/// Corrects the state from a normalized measurement of a reference direction, `predicted`
+	/// being this direction in body frame according to the current orientation.
+	fn correct(&mut self,
+			   measured: &Vector3<N>,
+			   predicted: &Vector3<N>,
+			   noise: N) -> Result<(), AhrsError> {
+		let mut h = Matrix3x6::zeros();
+		h.fixed_slice_mut::<U3, U3>(0, 0).copy_from(&predicted.cross_matrix());
+
+		let r = Matrix3::identity() * (noise * noise);
+
+		let innovation_covariance = h * self.covariance * h.transpose() + r;
+		let innovation_covariance_inverse = innovation_covariance
+			.try_inverse()
+			.ok_or(AhrsError::SingularCovariance)?;
+
+		let gain = self.covariance * h.transpose() * innovation_covariance_inverse;
+		let correction = gain * (measured - predicted);
+
+		self.state = UnitQuaternion::new_normalize(
+			(self.state * UnitQuaternion::from_scaled_axis(correction.fixed_rows::<U3>(0).into_owned()))
+				.into_inner());
+		self.gyr_bias += correction.fixed_rows::<U3>(3);
+
+		// Joseph form, keeping the covariance symmetric and positive
+		let i_kh = Matrix6::identity() - gain * h;
+		self.covariance = i_kh * self.covariance * i_kh.transpose() + gain * r * gain.transpose();
+
+		Ok(())
+	}
+}
+
+impl<N: RealField> Ahrs<N> for Ekf<N> {
+	fn update_imu(&mut self,
+				  gyroscope: &Vector3<N>,
+				  accelerometer: &Vector3<N>,
+				  dt: N) -> Result<(), AhrsError> {
+		let zero: N = nalgebra::zero();
+
+		// Normalize accelerometer measurement
+		let accel = match accelerometer.try_normalize(zero) {
+			Some(n) => n,
+			None => return Err(AhrsError::NormalizationError),
+		};
+
+		self.predict(gyroscope, dt);
+
+		// Estimated direction of gravity
+		let v = self.state.inverse_transform_vector(&Vector3::z());
+
+		self.correct(&accel, &v, self.acc_noise)
+	}
+
+	fn update_marg(&mut self,
+				   gyroscope: &Vector3<N>,
+				   accelerometer: &Vector3<N>,
+				   magnetometer: &Vector3<N>,
+				   dt: N) -> Result<(), AhrsError> {
+		let zero: N = nalgebra::zero();
+
+		// Magnetometer measurement is invalid, falling back to IMU algorithm
+		let mag = match magnetometer.try_normalize(zero) {
+			Some(n) => n,
+			None => return self.update_imu(gyroscope, accelerometer, dt),
+		};
+
+		self.update_imu(gyroscope, accelerometer, dt)?;
+
+		// Reference direction of Earth's magnetic field, and its estimated direction
+		let h = self.state.transform_vector(&mag);
+		let b = Vector3::new(Vector2::new(h[0], h[1]).norm(), zero, h[2]);
+		let w = self.state.inverse_transform_vector(&b);
+
+		self.correct(&mag, &w, self.mag_noise)
+	}
+
+	fn orientation(&self) -> UnitQuaternion<N> {
+		self.state
+	}
+}
@@ -1,13 +1,16 @@
 pub use crate::{madgwick::Madgwick};
 pub use crate::mahony::Mahony;
+pub use crate::ekf::Ekf;
 use nalgebra::{RealField, Vector3, UnitQuaternion};
 
+mod ekf;
 mod madgwick;
 mod mahony;
 
 #[derive(Debug)]
 pub enum AhrsError {
 	NormalizationError,
+	SingularCovariance,
 }
 
 pub trait Ahrs<N: RealField> where Self: Send + Sync + 'static {
@@ -15,5 +18,104 @@ pub trait Ahrs<N: RealField> where Self: Send + Sync + 'static {
 				  gyroscope: &Vector3<N>,
 				  accelerometer: &Vector3<N>,
 				  dt: N) -> Result<(), AhrsError>;
+
+	/// Updates orientation with magnetometer measurements, so that yaw is observable. Falls back
+	/// to `update_imu` if the magnetic field is null.
+	fn update_marg(&mut self,
+				   gyroscope: &Vector3<N>,
+				   accelerometer: &Vector3<N>,
+				   magnetometer: &Vector3<N>,
+				   dt: N) -> Result<(), AhrsError>;
+
 	fn orientation(&self) -> UnitQuaternion<N>;
-}
\ No newline at end of file
+}
+
+impl<N: RealField> Ahrs<N> for Box<dyn Ahrs<N>> {
+	fn update_imu(&mut self,
+				  gyroscope: &Vector3<N>,
+				  accelerometer: &Vector3<N>,
+				  dt: N) -> Result<(), AhrsError> {
+		(**self).update_imu(gyroscope, accelerometer, dt)
+	}
+
+	fn update_marg(&mut self,
+				   gyroscope: &Vector3<N>,
+				   accelerometer: &Vector3<N>,
+				   magnetometer: &Vector3<N>,
+				   dt: N) -> Result<(), AhrsError> {
+		(**self).update_marg(gyroscope, accelerometer, magnetometer, dt)
+	}
+
+	fn orientation(&self) -> UnitQuaternion<N> {
+		(**self).orientation()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{Ahrs, Ekf, Madgwick, Mahony};
+	use nalgebra::{UnitQuaternion, Vector3};
+
+	const DT: f64 = 0.002;
+
+	/// Feeds a static attitude with a non zero yaw and returns the estimated yaw.
+	fn converged_yaw<A: Ahrs<f64>>(ahrs: &mut A, iterations: usize) -> f64 {
+		let attitude = UnitQuaternion::from_euler_angles(0., 0., 1.);
+
+		let acc = attitude.inverse_transform_vector(&Vector3::new(0., 0., 9.81));
+		let mag = attitude.inverse_transform_vector(&Vector3::new(0.22, 0., -0.42));
+
+		for _ in 0..iterations {
+			ahrs.update_marg(&Vector3::zeros(), &acc, &mag, DT).unwrap();
+		}
+
+		ahrs.orientation().euler_angles().2
+	}
+
+	#[test]
+	fn madgwick_marg_test() {
+		assert!((converged_yaw(&mut Madgwick::new(0.1), 20_000) - 1.).abs() < 0.01);
+	}
+
+	#[test]
+	fn mahony_marg_test() {
+		assert!((converged_yaw(&mut Mahony::new(2., 0.), 20_000) - 1.).abs() < 0.01);
+	}
+
+	#[test]
+	fn ekf_marg_test() {
+		assert!((converged_yaw(&mut Ekf::new(0.01, 0.0001, 0.05, 0.05), 20_000) - 1.).abs() < 0.01);
+	}
+
+	#[test]
+	fn ekf_gyr_bias_test() {
+		let attitude = UnitQuaternion::from_euler_angles(0.1, -0.2, 0.5);
+		let gyr_bias = Vector3::new(0.01, -0.02, 0.015);
+
+		let acc = attitude.inverse_transform_vector(&Vector3::new(0., 0., 9.81));
+		let mag = attitude.inverse_transform_vector(&Vector3::new(0.22, 0., -0.42));
+
+		let mut ekf = Ekf::new(0.01, 0.0001, 0.05, 0.05);
+		let initial_attitude_std = ekf.attitude_std();
+
+		for _ in 0..50_000 {
+			ekf.update_marg(&gyr_bias, &acc, &mag, DT).unwrap();
+		}
+
+		assert!((ekf.gyr_bias() - gyr_bias).norm() < 1e-3);
+		assert!(ekf.orientation().angle_to(&attitude) < 0.01);
+		assert!(ekf.attitude_std().iter().zip(initial_attitude_std.iter()).all(|(std, initial)| std < initial));
+	}
+
+	#[test]
+	fn null_magnetometer_test() {
+		let mut imu = Madgwick::new(0.1);
+		let mut marg = Madgwick::new(0.1);
+		let acc = Vector3::new(0.5, 0., 9.8);
+
+		imu.update_imu(&Vector3::zeros(), &acc, DT).unwrap();
+		marg.update_marg(&Vector3::zeros(), &acc, &Vector3::zeros(), DT).unwrap();
+
+		assert_eq!(imu.orientation(), marg.orientation());
+	}
+}
@@ -0,0 +1,223 @@
+use crate::input::{BarometerData, GnssData, ImuData, Input, NavioAdcData, RcChannels};
+use crate::traits::{InputController, OutputController};
+use std::{error::Error, time::Duration};
+
+/// Board-agnostic inertial measurement unit.
+pub trait ImuSource
+	where
+		Self: Send + 'static,
+{
+	/// Reads a raw sample: acceleration (m/s²), angular rate (rad/s) and magnetic field (gauss),
+	/// all expressed in the body frame.
+	fn read_imu(&mut self) -> Result<ImuData<f64>, Box<dyn Error>>;
+
+	/// Reads every sample acquired since the previous call, oldest first, waiting for at least
+	/// one sample if none is available. Each sample carries the instant it was acquired, so that
+	/// sample periods are exact. By default, reads a single sample.
+	fn read_imu_batch(&mut self) -> Result<Vec<ImuData<f64>>, Box<dyn Error>> {
+		Ok(vec![self.read_imu()?])
+	}
+}
+
+/// Board-agnostic barometer.
+pub trait BarometerSource
+	where
+		Self: Send + 'static,
+{
+	/// Reads a compensated sample. Reading may block for the duration of a conversion.
+	fn read_barometer(&mut self) -> Result<BarometerData<f64>, Box<dyn Error>>;
+}
+
+/// Board-agnostic GNSS receiver.
+pub trait GnssSource
+	where
+		Self: Send + 'static,
+{
+	/// Reads the next navigation solution, blocking until the receiver outputs it.
+	fn read_gnss(&mut self) -> Result<GnssData<f64>, Box<dyn Error>>;
+}
+
+/// Board-agnostic RC receiver.
+pub trait RcSource
+	where
+		Self: Send + 'static,
+{
+	/// Reads channels normalized within (0., 1.), or `None` if the transmitter is disconnected.
+	fn read_rc(&mut self) -> Result<RcChannels<f64>, Box<dyn Error>>;
+}
+
+/// Board-agnostic power module.
+pub trait PowerSource
+	where
+		Self: Send + 'static,
+{
+	fn read_power(&mut self) -> Result<NavioAdcData<f64>, Box<dyn Error>>;
+}
+
+/// Board-agnostic motor outputs.
+pub trait MotorSink
+	where
+		Self: Send + 'static,
+{
+	/// Writes motor commands, expected to be within (0., 1.).
+	fn write_motors(&mut self, outputs: &[f64]) -> Result<(), Box<dyn Error>>;
+}
+
+impl<S: ImuSource + ?Sized> ImuSource for Box<S> {
+	fn read_imu(&mut self) -> Result<ImuData<f64>, Box<dyn Error>> {
+		(**self).read_imu()
+	}
+
+	fn read_imu_batch(&mut self) -> Result<Vec<ImuData<f64>>, Box<dyn Error>> {
+		(**self).read_imu_batch()
+	}
+}
+
+impl<S: BarometerSource + ?Sized> BarometerSource for Box<S> {
+	fn read_barometer(&mut self) -> Result<BarometerData<f64>, Box<dyn Error>> {
+		(**self).read_barometer()
+	}
+}
+
+impl<S: GnssSource + ?Sized> GnssSource for Box<S> {
+	fn read_gnss(&mut self) -> Result<GnssData<f64>, Box<dyn Error>> {
+		(**self).read_gnss()
+	}
+}
+
+impl<S: RcSource + ?Sized> RcSource for Box<S> {
+	fn read_rc(&mut self) -> Result<RcChannels<f64>, Box<dyn Error>> {
+		(**self).read_rc()
+	}
+}
+
+impl<S: PowerSource + ?Sized> PowerSource for Box<S> {
+	fn read_power(&mut self) -> Result<NavioAdcData<f64>, Box<dyn Error>> {
+		(**self).read_power()
+	}
+}
+
+impl<S: MotorSink + ?Sized> MotorSink for Box<S> {
+	fn write_motors(&mut self, outputs: &[f64]) -> Result<(), Box<dyn Error>> {
+		(**self).write_motors(outputs)
+	}
+}
+
+/// Input controller polling an RC receiver.
+pub struct RcInputController<S: RcSource> {
+	rc: S,
+}
+
+impl<S: RcSource> RcInputController<S> {
+	pub fn new(rc: S) -> Self {
+		Self { rc }
+	}
+}
+
+impl<S: RcSource> InputController for RcInputController<S> {
+	const DELAY: Option<Duration> = Some(Duration::from_millis(20));
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+		self.rc.read_rc().map(Input::RcChannels)
+	}
+}
+
+/// Input controller polling a barometer.
+pub struct BarometerInputController<S: BarometerSource> {
+	barometer: S,
+}
+
+impl<S: BarometerSource> BarometerInputController<S> {
+	pub fn new(barometer: S) -> Self {
+		Self { barometer }
+	}
+}
+
+impl<S: BarometerSource> InputController for BarometerInputController<S> {
+	// Barometers are paced by their conversion time
+	const DELAY: Option<Duration> = None;
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+		self.barometer
+			.read_barometer()
+			.map(|barometer| {
+				debug!(target: "barometer", "{} {}", barometer.pressure, barometer.temperature);
+				barometer
+			})
+			.map(Input::Barometer)
+	}
+}
+
+/// Input controller polling a GNSS receiver.
+pub struct GnssInputController<S: GnssSource> {
+	gnss: S,
+}
+
+impl<S: GnssSource> GnssInputController<S> {
+	pub fn new(gnss: S) -> Self {
+		Self { gnss }
+	}
+}
+
+impl<S: GnssSource> InputController for GnssInputController<S> {
+	// Receivers are paced by their navigation rate
+	const DELAY: Option<Duration> = None;
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+		self.gnss
+			.read_gnss()
+			.map(|gnss| {
+				debug!(target: "gnss", "{:?} {} {} {} {} {} {} {} {} {} {}",
+					   gnss.fix,
+					   gnss.latitude, gnss.longitude, gnss.altitude,
+					   gnss.velocity.x, gnss.velocity.y, gnss.velocity.z,
+					   gnss.horizontal_accuracy, gnss.vertical_accuracy, gnss.speed_accuracy,
+					   gnss.satellites);
+				gnss
+			})
+			.map(Input::Gnss)
+	}
+}
+
+/// Input controller polling a power module.
+pub struct PowerInputController<S: PowerSource> {
+	power: S,
+}
+
+impl<S: PowerSource> PowerInputController<S> {
+	pub fn new(power: S) -> Self {
+		Self { power }
+	}
+}
+
+impl<S: PowerSource> InputController for PowerInputController<S> {
+	// Fast enough to integrate current and react to voltage sag
+	const DELAY: Option<Duration> = Some(Duration::from_millis(100));
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+		self.power
+			.read_power()
+			.map(|power| {
+				debug!(target: "adc", "{}", power);
+				power
+			})
+			.map(Input::NavioAdc)
+	}
+}
+
+/// Output controller forwarding motor commands to a motor sink.
+pub struct MotorOutputController<S: MotorSink> {
+	motors: S,
+}
+
+impl<S: MotorSink> MotorOutputController<S> {
+	pub fn new(motors: S) -> Self {
+		Self { motors }
+	}
+}
+
+impl<S: MotorSink, T: AsRef<[f64]> + Send + 'static> OutputController<T> for MotorOutputController<S> {
+	fn write_output(&mut self, output: T) -> Result<(), Box<dyn Error>> {
+		self.motors.write_motors(output.as_ref())
+	}
+}
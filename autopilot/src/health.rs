@@ -0,0 +1,101 @@
+//! Registry of controller health, updated by controller loops and read by monitors.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+	static ref REGISTRY: Mutex<BTreeMap<&'static str, ControllerHealth>> = Mutex::new(BTreeMap::new());
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+	Healthy,
+	/// No success within the maximum age.
+	Stale,
+	/// Last call failed.
+	Failing,
+}
+
+/// Health of a controller, as reported by its loop.
+#[derive(Debug, Clone, Default)]
+pub struct ControllerHealth {
+	/// Age above which the controller is stale, if it is expected to succeed periodically.
+	pub max_age: Option<Duration>,
+	/// Instant of the last successful read or write.
+	pub last_success: Option<Instant>,
+	/// Last error, and its instant.
+	pub last_error: Option<(Instant, String)>,
+	/// Number of errors since the last success.
+	pub consecutive_errors: u32,
+	/// Instant the controller was registered.
+	pub registered: Option<Instant>,
+}
+
+impl ControllerHealth {
+	pub fn status(&self, instant: Instant) -> HealthStatus {
+		if self.consecutive_errors > 0 {
+			return HealthStatus::Failing;
+		}
+
+		let last = match self.last_success.or(self.registered) {
+			Some(last) => last,
+			None => return HealthStatus::Healthy,
+		};
+
+		match self.max_age {
+			Some(max_age) if instant.saturating_duration_since(last) > max_age => HealthStatus::Stale,
+			_ => HealthStatus::Healthy,
+		}
+	}
+}
+
+fn registry() -> MutexGuard<'static, BTreeMap<&'static str, ControllerHealth>> {
+	// Health is still reported after a panic in another controller
+	REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Name of a controller type, without module path nor generic parameters.
+pub fn controller_name<T: ?Sized>() -> &'static str {
+	let name = std::any::type_name::<T>();
+	let name = &name[..name.find('<').unwrap_or(name.len())];
+
+	&name[name.rfind("::").map_or(0, |i| i + 2)..]
+}
+
+/// Registers a controller, keeping the maximum age of a previous registration if none is given.
+pub fn register(controller: &'static str, max_age: Option<Duration>) {
+	let mut registry = registry();
+	let health = registry.entry(controller).or_default();
+
+	health.registered = Some(Instant::now());
+
+	if max_age.is_some() {
+		health.max_age = max_age;
+	}
+}
+
+pub fn report_success(controller: &'static str) {
+	let mut registry = registry();
+	let health = registry.entry(controller).or_default();
+
+	health.last_success = Some(Instant::now());
+	health.consecutive_errors = 0;
+}
+
+pub fn report_error(controller: &'static str, error: &dyn Error) {
+	let mut registry = registry();
+	let health = registry.entry(controller).or_default();
+
+	health.last_error = Some((Instant::now(), error.to_string()));
+	health.consecutive_errors = health.consecutive_errors.saturating_add(1);
+}
+
+/// Health of every registered controller, by name.
+pub fn health() -> Vec<(&'static str, ControllerHealth)> {
+	registry()
+		.iter()
+		.map(|(&controller, health)| (controller, health.clone()))
+		.collect()
+}
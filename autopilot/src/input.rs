@@ -8,6 +8,8 @@ pub struct ImuData<N: RealField> {
 	pub acc: Vector3<N>,
 	pub gyr: Vector3<N>,
 	pub mag: Vector3<N>,
+	/// Sensor die temperature (°C).
+	pub temperature: N,
 	pub instant: Instant,
 }
 
@@ -17,12 +19,13 @@ impl<N: RealField> Default for ImuData<N> {
 			acc: zero(),
 			gyr: zero(),
 			mag: zero(),
+			temperature: zero(),
 			instant: Instant::now(),
 		}
 	}
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct NavioAdcData<N: RealField> {
 	pub board_voltage: N,
 	pub servo_voltage: N,
@@ -30,6 +33,21 @@ pub struct NavioAdcData<N: RealField> {
 	pub external_current: N,
 	pub adc_port_2: N,
 	pub adc_port_3: N,
+	pub instant: Instant,
+}
+
+impl<N: RealField> Default for NavioAdcData<N> {
+	fn default() -> Self {
+		NavioAdcData {
+			board_voltage: zero(),
+			servo_voltage: zero(),
+			external_voltage: zero(),
+			external_current: zero(),
+			adc_port_2: zero(),
+			adc_port_3: zero(),
+			instant: Instant::now(),
+		}
+	}
 }
 
 impl<N: RealField> Display for NavioAdcData<N> {
@@ -46,6 +64,46 @@ impl<N: RealField> Display for NavioAdcData<N> {
 	}
 }
 
+#[derive(Clone, Debug)]
+pub struct BarometerData<N: RealField> {
+	/// Static pressure (Pa).
+	pub pressure: N,
+	/// Sensor temperature (°C).
+	pub temperature: N,
+	pub instant: Instant,
+}
+
+/// Type of a GNSS fix, only 3D fixes providing a usable position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GnssFix {
+	NoFix,
+	DeadReckoning,
+	Fix2D,
+	Fix3D,
+}
+
+/// GNSS receiver solution. Latitude and longitude require double precision.
+#[derive(Clone, Debug)]
+pub struct GnssData<N: RealField> {
+	/// Other fields are meaningless without a fix.
+	pub fix: GnssFix,
+	/// Latitude (degrees, positive northwards).
+	pub latitude: N,
+	/// Longitude (degrees, positive eastwards).
+	pub longitude: N,
+	/// Height above the WGS84 ellipsoid (m).
+	pub altitude: N,
+	/// Velocity in the north-east-down frame (m/s).
+	pub velocity: Vector3<N>,
+	/// Estimated horizontal and vertical position accuracies (m).
+	pub horizontal_accuracy: N,
+	pub vertical_accuracy: N,
+	/// Estimated speed accuracy (m/s).
+	pub speed_accuracy: N,
+	pub satellites: u8,
+	pub instant: Instant,
+}
+
 pub type RcChannels<N> = Option<[N; 16]>;
 pub type Orientation<N> = (UnitQuaternion<N>, ImuData<N>, Instant);
 
@@ -54,5 +112,9 @@ pub enum Input {
 	RcChannels(RcChannels<f64>),
 	NavioAdc(NavioAdcData<f64>),
 	Orientation(Orientation<f64>),
+	Barometer(BarometerData<f64>),
+	Gnss(GnssData<f64>),
 	SoftArmed(bool),
+	/// CPU temperature (°C).
+	CpuTemperature(f64),
 }
@@ -1,8 +1,17 @@
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 
+pub mod health;
+mod hal;
 mod input;
+mod realtime;
+mod supervisor;
 mod traits;
 
+pub use hal::*;
 pub use input::*;
+pub use realtime::*;
+pub use supervisor::*;
 pub use traits::*;
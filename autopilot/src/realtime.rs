@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, io, mem, sync::Mutex, thread};
+
+lazy_static! {
+	static ref STAGE_OPTIONS: Mutex<BTreeMap<String, RealtimeOptions>> = Mutex::new(BTreeMap::new());
+}
+
+/// Range of `SCHED_FIFO` priorities on Linux.
+pub const MIN_FIFO_PRIORITY: i32 = 1;
+pub const MAX_FIFO_PRIORITY: i32 = 99;
+
+/// Real-time options of the thread running a stage.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct RealtimeOptions {
+	/// `SCHED_FIFO` priority, within (1, 99), or the default scheduling policy if `None`.
+	pub fifo_priority: Option<i32>,
+	/// CPUs the thread is pinned to, or any CPU if empty.
+	pub cpu_affinity: Vec<usize>,
+	/// Whether current and future memory pages are locked in RAM, preventing page faults. Memory
+	/// is locked for the whole process, once the stage starts.
+	pub lock_memory: bool,
+}
+
+impl RealtimeOptions {
+	/// Checks that options can be applied, i.e. that the priority is a `SCHED_FIFO` priority.
+	pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+		match self.fifo_priority {
+			Some(priority) if !(MIN_FIFO_PRIORITY..=MAX_FIFO_PRIORITY).contains(&priority) => {
+				Err(format!("SCHED_FIFO priority {} out of range ({}, {})",
+							priority, MIN_FIFO_PRIORITY, MAX_FIFO_PRIORITY).into())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Applies options to the calling thread. Failures, e.g. when the process lacks privileges,
+	/// are logged and the thread runs on without the failed options.
+	pub fn apply(&self, stage: &str) {
+		if let Some(priority) = self.fifo_priority {
+			if let Err(e) = set_fifo_priority(priority) {
+				warn!("Failed to set SCHED_FIFO priority {} of stage {}: {}", priority, stage, describe(&e));
+			}
+		}
+
+		if !self.cpu_affinity.is_empty() {
+			if let Err(e) = set_cpu_affinity(&self.cpu_affinity) {
+				warn!("Failed to pin stage {} to CPUs {:?}: {}", stage, self.cpu_affinity, describe(&e));
+			}
+		}
+
+		if self.lock_memory {
+			if let Err(e) = lock_memory() {
+				warn!("Failed to lock memory for stage {}: {}", stage, describe(&e));
+			}
+		}
+	}
+}
+
+/// Sets the real-time options of stages, by name, applied to the threads of stages spawned
+/// afterwards, whether supervised or not.
+pub fn set_stage_options(options: BTreeMap<String, RealtimeOptions>) {
+	*STAGE_OPTIONS.lock().unwrap_or_else(|e| e.into_inner()) = options;
+}
+
+/// Spawns a thread named after the stage, running `stage` with its real-time options if any.
+pub(crate) fn spawn_stage<F, T>(name: &'static str, stage: F) -> thread::JoinHandle<T>
+	where
+		F: FnOnce() -> T + Send + 'static,
+		T: Send + 'static,
+{
+	let options = STAGE_OPTIONS.lock().unwrap_or_else(|e| e.into_inner()).get(name).cloned();
+
+	thread::Builder::new()
+		.name(name.to_string())
+		.spawn(move || {
+			if let Some(options) = options {
+				options.apply(name);
+			}
+
+			stage()
+		})
+		.unwrap()
+}
+
+/// Error description, hinting at missing privileges.
+fn describe(error: &io::Error) -> String {
+	match error.raw_os_error() {
+		Some(libc::EPERM) => format!("{}, the process requires root or CAP_SYS_NICE and CAP_IPC_LOCK", error),
+		_ => error.to_string(),
+	}
+}
+
+fn set_fifo_priority(priority: i32) -> io::Result<()> {
+	let param = libc::sched_param { sched_priority: priority };
+
+	// Safety: `param` outlives the call
+	match unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) } {
+		0 => Ok(()),
+		error => Err(io::Error::from_raw_os_error(error)),
+	}
+}
+
+fn set_cpu_affinity(cpus: &[usize]) -> io::Result<()> {
+	if cpus.iter().any(|&cpu| cpu >= libc::CPU_SETSIZE as usize) {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "CPU out of range"));
+	}
+
+	// Safety: CPUs are within the set, which outlives the call. A zero pid is the calling thread
+	unsafe {
+		let mut set: libc::cpu_set_t = mem::zeroed();
+
+		for &cpu in cpus {
+			libc::CPU_SET(cpu, &mut set);
+		}
+
+		match libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) {
+			0 => Ok(()),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+}
+
+fn lock_memory() -> io::Result<()> {
+	// Safety: no pointer is involved
+	match unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } {
+		0 => Ok(()),
+		_ => Err(io::Error::last_os_error()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::realtime::RealtimeOptions;
+
+	#[test]
+	fn validate_test() {
+		let options = |fifo_priority| RealtimeOptions { fifo_priority, ..RealtimeOptions::default() };
+
+		assert!(options(None).validate().is_ok());
+		assert!(options(Some(1)).validate().is_ok());
+		assert!(options(Some(99)).validate().is_ok());
+		assert!(options(Some(0)).validate().is_err());
+		assert!(options(Some(100)).validate().is_err());
+	}
+}
@@ -0,0 +1,330 @@
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::{
+	any::Any,
+	error::Error,
+	panic::{self, AssertUnwindSafe},
+	sync::{Arc, RwLock},
+	thread,
+	time::{Duration, Instant},
+};
+
+use crate::health;
+use crate::realtime;
+
+/// Handling of a stage that panicked or exited.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StagePolicy {
+	/// Runs the stage again after a panic, with a delay growing with consecutive failures. A stage
+	/// that exits, i.e. whose channels are closed, is not run again.
+	Restart,
+	/// Triggers the failsafe action, other stages running on.
+	Failsafe,
+	/// Triggers the failsafe action, then shuts the autopilot down.
+	Abort,
+}
+
+enum SupervisorEvent {
+	Aborted(&'static str),
+	ShutdownRequested,
+}
+
+/// Requests an orderly shutdown of the supervisor, from any thread.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+	sender: Sender<SupervisorEvent>,
+}
+
+impl ShutdownHandle {
+	pub fn request(&self) {
+		let _ = self.sender.send(SupervisorEvent::ShutdownRequested);
+	}
+}
+
+/// Lets frames through until shutdown. Frames are forwarded under a read lock, and shutdown takes
+/// the write lock, so that no frame is forwarded after the failsafe action.
+#[derive(Clone)]
+pub(crate) struct ShutdownGate {
+	shutting_down: Arc<RwLock<bool>>,
+}
+
+impl ShutdownGate {
+	/// Runs `forward` unless shutting down, returning whether it ran.
+	pub(crate) fn pass<F: FnOnce()>(&self, forward: F) -> bool {
+		let shutting_down = self.shutting_down.read().unwrap_or_else(|e| e.into_inner());
+
+		if *shutting_down {
+			return false;
+		}
+
+		forward();
+		true
+	}
+
+	fn is_shutting_down(&self) -> bool {
+		*self.shutting_down.read().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn close(&self) {
+		*self.shutting_down.write().unwrap_or_else(|e| e.into_inner()) = true;
+	}
+}
+
+/// Delays before restarting a stage, doubled on each consecutive failure.
+const MIN_RESTART_DELAY: Duration = Duration::from_millis(10);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Duration a restarted stage must run before its failures are no longer consecutive.
+const RECOVERY_DURATION: Duration = Duration::from_secs(10);
+/// Period at which output controllers are polled during shutdown.
+const SHUTDOWN_POLL_PERIOD: Duration = Duration::from_millis(10);
+
+/// Number of outputs queued for an output controller.
+type QueuedOutputs = Box<dyn Fn() -> usize + Send>;
+
+/// Owns the threads running autopilot stages, i.e. controllers, collector, autopilot and
+/// dispatcher. Stages that panic or exit are handled according to their policy, and the
+/// autopilot is shut down in order, once the failsafe action has been written by every output
+/// controller.
+pub struct Supervisor {
+	sender: Sender<SupervisorEvent>,
+	receiver: Receiver<SupervisorEvent>,
+	failsafe: Arc<dyn Fn() + Send + Sync>,
+	gate: ShutdownGate,
+	/// Stages of output controllers, as named in the health registry, with the number of outputs
+	/// queued for them.
+	outputs: Vec<(&'static str, QueuedOutputs)>,
+}
+
+impl Default for Supervisor {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Supervisor {
+	pub fn new() -> Self {
+		let (sender, receiver) = unbounded::<SupervisorEvent>();
+
+		Self {
+			sender,
+			receiver,
+			failsafe: Arc::new(|| {}),
+			gate: ShutdownGate { shutting_down: Arc::new(RwLock::new(false)) },
+			outputs: Vec::new(),
+		}
+	}
+
+	/// Action triggered when a stage with the failsafe or abort policy fails, and on shutdown,
+	/// e.g. stopping motors. It must be set before spawning stages, and write to every output
+	/// controller, as shutdown waits for their writes.
+	pub fn with_failsafe<F: Fn() + Send + Sync + 'static>(mut self, failsafe: F) -> Self {
+		self.failsafe = Arc::new(failsafe);
+		self
+	}
+
+	pub fn shutdown_handle(&self) -> ShutdownHandle {
+		ShutdownHandle { sender: self.sender.clone() }
+	}
+
+	/// Gate of stages forwarding frames to output controllers, closed on shutdown.
+	pub(crate) fn shutdown_gate(&self) -> ShutdownGate {
+		self.gate.clone()
+	}
+
+	/// Spawns a thread named after the stage, with its real-time options if any, running `stage`
+	/// until it panics or returns, which is then handled according to `policy`.
+	pub fn spawn<F>(&mut self, name: &'static str, policy: StagePolicy, mut stage: F)
+		where
+			F: FnMut() + Send + 'static,
+	{
+		let sender = self.sender.clone();
+		let failsafe = self.failsafe.clone();
+		let gate = self.gate.clone();
+
+		realtime::spawn_stage(name, move || {
+			let mut consecutive_failures = 0;
+
+			loop {
+				let start_instant = Instant::now();
+				let result = panic::catch_unwind(AssertUnwindSafe(&mut stage));
+
+				if gate.is_shutting_down() {
+					break;
+				}
+
+				match result {
+					Ok(()) => {
+						error!("Stage {} exited", name);
+
+						if policy == StagePolicy::Restart {
+							break;
+						}
+					}
+					Err(payload) => error!("Stage {} panicked: {}", name, panic_message(&*payload)),
+				}
+
+				match policy {
+					StagePolicy::Restart => {
+						if start_instant.elapsed() >= RECOVERY_DURATION {
+							consecutive_failures = 0;
+						}
+
+						let delay = (MIN_RESTART_DELAY * 2u32.pow(consecutive_failures.min(7)))
+							.min(MAX_RESTART_DELAY);
+						consecutive_failures += 1;
+
+						warn!("Restarting stage {} in {} ms", name, delay.as_millis());
+						thread::sleep(delay);
+					}
+					StagePolicy::Failsafe => {
+						failsafe();
+						break;
+					}
+					StagePolicy::Abort => {
+						failsafe();
+						let _ = sender.send(SupervisorEvent::Aborted(name));
+						break;
+					}
+				}
+			}
+		});
+	}
+
+	/// Spawns the stage of an output controller, whose writes are awaited on shutdown until no
+	/// output is `queued`.
+	pub(crate) fn spawn_output<F, Q>(&mut self, name: &'static str, policy: StagePolicy, queued: Q, stage: F)
+		where
+			F: FnMut() + Send + 'static,
+			Q: Fn() -> usize + Send + 'static,
+	{
+		self.outputs.push((name, Box::new(queued)));
+		self.spawn(name, policy, stage);
+	}
+
+	/// Blocks until a shutdown is requested, or a stage with the abort policy failed.
+	pub fn wait(&self) -> Result<(), Box<dyn Error>> {
+		match self.receiver.recv()? {
+			SupervisorEvent::ShutdownRequested => Ok(()),
+			SupervisorEvent::Aborted(name) => Err(format!("Stage {} aborted the autopilot", name).into()),
+		}
+	}
+
+	/// Stops handling stage failures and forwarding frames, and triggers the failsafe action, then
+	/// waits up to `timeout` for every output controller to write its queued outputs, the failsafe
+	/// ones being last.
+	pub fn shutdown(self, timeout: Duration) {
+		info!("Shutting down");
+
+		self.gate.close();
+
+		let instant = Instant::now();
+		(self.failsafe)();
+
+		loop {
+			let health = health::health();
+
+			let pending: Vec<&str> = self.outputs
+				.iter()
+				.filter(|(output, queued)| queued() > 0 || !health.iter().any(|(controller, health)| {
+					controller == output && health.last_success.map_or(false, |last_success| last_success > instant)
+				}))
+				.map(|&(output, _)| output)
+				.collect();
+
+			if pending.is_empty() {
+				break;
+			}
+
+			if instant.elapsed() >= timeout {
+				warn!("Outputs not written before shutdown: {}", pending.join(", "));
+				break;
+			}
+
+			thread::sleep(SHUTDOWN_POLL_PERIOD);
+		}
+
+		log::logger().flush();
+	}
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		message
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message
+	} else {
+		"unknown"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::supervisor::{StagePolicy, Supervisor};
+	use crate::traits::{Dispatcher, OutputController};
+	use crossbeam_channel::{unbounded, Sender};
+	use std::{
+		error::Error,
+		thread,
+		time::{Duration, Instant},
+	};
+
+	struct RecordingOutputController {
+		written: Sender<f64>,
+	}
+
+	impl OutputController<f64> for RecordingOutputController {
+		fn write_output(&mut self, output: f64) -> Result<(), Box<dyn Error>> {
+			Ok(self.written.send(output)?)
+		}
+	}
+
+	struct ForwardingDispatcher {
+		sender: Sender<f64>,
+	}
+
+	impl Dispatcher<f64> for ForwardingDispatcher {
+		fn dispatch(&self, frame: f64) {
+			let _ = self.sender.send(frame);
+		}
+	}
+
+	#[test]
+	fn shutdown_test() {
+		let (frame_sender, frame_receiver) = unbounded::<f64>();
+		let (output_sender, output_receiver) = unbounded::<f64>();
+		let (written_sender, written_receiver) = unbounded::<f64>();
+
+		let mut supervisor = {
+			let output_sender = output_sender.clone();
+
+			Supervisor::new().with_failsafe(move || {
+				let _ = output_sender.send(0.);
+			})
+		};
+
+		RecordingOutputController { written: written_sender }
+			.supervise(&mut supervisor, StagePolicy::Restart, output_receiver);
+		ForwardingDispatcher { sender: output_sender }
+			.supervise(&mut supervisor, StagePolicy::Failsafe, frame_receiver);
+
+		// Frames keep coming during shutdown
+		thread::spawn(move || {
+			while frame_sender.send(1.).is_ok() {
+				thread::sleep(Duration::from_micros(100));
+			}
+		});
+
+		thread::sleep(Duration::from_millis(50));
+
+		let timeout = Duration::from_millis(500);
+		let instant = Instant::now();
+		supervisor.shutdown(timeout);
+
+		// Every output was written before the timeout, the failsafe output last
+		assert!(instant.elapsed() < timeout);
+
+		thread::sleep(Duration::from_millis(50));
+		let written: Vec<f64> = written_receiver.try_iter().collect();
+		assert!(written.contains(&1.));
+		assert_eq!(written.last(), Some(&0.));
+	}
+}
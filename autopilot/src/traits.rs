@@ -1,4 +1,7 @@
+use crate::health;
 use crate::input::Input;
+use crate::realtime;
+use crate::supervisor::{StagePolicy, Supervisor};
 use crossbeam_channel::{Receiver, Sender};
 use std::{
 	error::Error,
@@ -27,7 +30,9 @@ pub trait Autopilot<In, Out>
 			if receiver.is_empty()
 				|| (Instant::now() - last_output_frame_instant) >= Self::MAX_CONTROL_LOOP_PERIOD
 			{
-				sender.send(self.output_frame(input_frame)).unwrap();
+				if sender.send(self.output_frame(input_frame)).is_err() {
+					return;
+				}
 
 				last_output_frame_instant = Instant::now();
 			}
@@ -35,7 +40,14 @@ pub trait Autopilot<In, Out>
 	}
 
 	fn spawn(mut self, receiver: Receiver<In>, sender: Sender<Out>) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.control_loop(receiver, sender))
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.control_loop(receiver, sender))
+	}
+
+	/// Runs `control_loop` in a stage owned by the supervisor.
+	fn supervise(mut self, supervisor: &mut Supervisor, policy: StagePolicy, receiver: Receiver<In>, sender: Sender<Out>) {
+		supervisor.spawn(health::controller_name::<Self>(),
+						 policy,
+						 move || self.control_loop(receiver.clone(), sender.clone()));
 	}
 }
 
@@ -46,16 +58,24 @@ pub trait Collector<F: Send + 'static>
 {
 	fn collect(&mut self, input: Input) -> F;
 
-	fn collect_loop(&mut self, receiver: Receiver<Input>, sender: Sender<F>) -> ! {
+	/// Collects inputs until the input or frame channel is closed.
+	fn collect_loop(&mut self, receiver: Receiver<Input>, sender: Sender<F>) {
 		for input in receiver {
-			sender.send(self.collect(input)).unwrap();
+			if sender.send(self.collect(input)).is_err() {
+				return;
+			}
 		}
-
-		unreachable!()
 	}
 
 	fn spawn(mut self, receiver: Receiver<Input>, sender: Sender<F>) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.collect_loop(receiver, sender))
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.collect_loop(receiver, sender))
+	}
+
+	/// Runs `collect_loop` in a stage owned by the supervisor.
+	fn supervise(mut self, supervisor: &mut Supervisor, policy: StagePolicy, receiver: Receiver<Input>, sender: Sender<F>) {
+		supervisor.spawn(health::controller_name::<Self>(),
+						 policy,
+						 move || self.collect_loop(receiver.clone(), sender.clone()));
 	}
 }
 
@@ -66,34 +86,75 @@ pub trait Dispatcher<F: Send + Sized + 'static>
 {
 	fn dispatch(&self, frame: F);
 
-	fn dispatch_loop(&self, receiver: Receiver<F>) -> ! {
+	/// Dispatches frames until the frame channel is closed.
+	fn dispatch_loop(&self, receiver: Receiver<F>) {
 		for frame in receiver {
 			self.dispatch(frame);
 		}
-
-		unreachable!()
 	}
 
 	fn spawn(self, receiver: Receiver<F>) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.dispatch_loop(receiver))
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.dispatch_loop(receiver))
+	}
+
+	/// Runs `dispatch_loop` in a stage owned by the supervisor, until shutdown: frames received
+	/// afterwards are dropped, so that the failsafe outputs are the last ones.
+	fn supervise(self, supervisor: &mut Supervisor, policy: StagePolicy, receiver: Receiver<F>) {
+		let gate = supervisor.shutdown_gate();
+
+		supervisor.spawn(health::controller_name::<Self>(),
+						 policy,
+						 move || {
+							 for frame in receiver.iter() {
+								 if !gate.pass(|| self.dispatch(frame)) {
+									 return;
+								 }
+							 }
+						 });
 	}
 }
 
+/// Delays after a failed `read_input` call, doubled on each consecutive error, so that devices
+/// failing immediately do not spin the loop.
+const MIN_READ_ERROR_DELAY: Duration = Duration::from_millis(10);
+const MAX_READ_ERROR_DELAY: Duration = Duration::from_secs(1);
+
 /// Controllers that import external data.
 pub trait InputController
 	where
 		Self: Sized + Send + 'static,
 {
-	/// Minimum duration to wait between two successive `read_input` calls.
+	/// Minimum duration to wait between two successive `read_input` calls. Failed calls are also
+	/// followed by a growing delay.
 	const DELAY: Option<Duration>;
 
 	fn read_input(&mut self) -> Result<Input, Box<dyn Error>>;
 
-	fn read_loop(&mut self, input_sender: Sender<Input>) -> ! {
+	/// Reads inputs until the input channel is closed.
+	fn read_loop(&mut self, input_sender: Sender<Input>) {
+		let name = health::controller_name::<Self>();
+		health::register(name, None);
+
+		let mut consecutive_errors = 0;
+
 		loop {
 			match self.read_input() {
-				Ok(input) => input_sender.send(input).unwrap(),
-				Err(e) => error!("{}", e),
+				Ok(input) => {
+					health::report_success(name);
+					consecutive_errors = 0;
+
+					if input_sender.send(input).is_err() {
+						return;
+					}
+				}
+				Err(e) => {
+					health::report_error(name, e.as_ref());
+					error!("{}", e);
+
+					thread::sleep((MIN_READ_ERROR_DELAY * 2u32.pow(consecutive_errors.min(7)))
+						.min(MAX_READ_ERROR_DELAY));
+					consecutive_errors += 1;
+				}
 			}
 
 			if let Some(delay) = Self::DELAY {
@@ -105,7 +166,20 @@ pub trait InputController
 	/// Spawns a thread running `read_loop`. It is expected that the input controller is ready to
 	/// read when spawned, i.e. that the initialization (if any) is done.
 	fn spawn(mut self, input_sender: Sender<Input>) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.read_loop(input_sender))
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.read_loop(input_sender))
+	}
+
+	/// Runs `read_loop` in a stage owned by the supervisor, the controller being reported as stale
+	/// by the health registry when no input is read within `max_age`, if any.
+	fn supervise(mut self,
+				 supervisor: &mut Supervisor,
+				 policy: StagePolicy,
+				 input_sender: Sender<Input>,
+				 max_age: Option<Duration>) {
+		let name = health::controller_name::<Self>();
+		health::register(name, max_age);
+
+		supervisor.spawn(name, policy, move || self.read_loop(input_sender.clone()));
 	}
 }
 
@@ -133,7 +207,12 @@ pub trait Monitor<T: Display>
 	}
 
 	fn spawn(mut self) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.monitor_loop())
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.monitor_loop())
+	}
+
+	/// Runs `monitor_loop` in a stage owned by the supervisor.
+	fn supervise(mut self, supervisor: &mut Supervisor, policy: StagePolicy) {
+		supervisor.spawn(health::controller_name::<Self>(), policy, move || self.monitor_loop());
 	}
 }
 
@@ -144,19 +223,33 @@ pub trait OutputController<T: Send + 'static>
 {
 	fn write_output(&mut self, output: T) -> Result<(), Box<dyn Error>>;
 
-	fn write_loop(&mut self, output_receiver: Receiver<T>) -> ! {
-		loop {
-			for output in output_receiver.iter() {
-				self.write_output(output)
-					.map_err(|e| error!("{}", e))
-					.unwrap_or_default();
+	/// Writes outputs until the output channel is closed.
+	fn write_loop(&mut self, output_receiver: Receiver<T>) {
+		let name = health::controller_name::<Self>();
+		health::register(name, None);
+
+		for output in output_receiver.iter() {
+			match self.write_output(output) {
+				Ok(()) => health::report_success(name),
+				Err(e) => {
+					health::report_error(name, e.as_ref());
+					error!("{}", e)
+				}
 			}
-
-			unreachable!()
 		}
 	}
 
 	fn spawn(mut self, output_receiver: Receiver<T>) -> thread::JoinHandle<()> {
-		thread::spawn(move || self.write_loop(output_receiver))
+		realtime::spawn_stage(health::controller_name::<Self>(), move || self.write_loop(output_receiver))
+	}
+
+	/// Runs `write_loop` in a stage owned by the supervisor, whose writes are awaited on shutdown.
+	fn supervise(mut self, supervisor: &mut Supervisor, policy: StagePolicy, output_receiver: Receiver<T>) {
+		let queued_receiver = output_receiver.clone();
+
+		supervisor.spawn_output(health::controller_name::<Self>(),
+								policy,
+								move || queued_receiver.len(),
+								move || self.write_loop(output_receiver.clone()));
 	}
 }
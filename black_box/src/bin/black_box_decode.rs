@@ -0,0 +1,86 @@
+//! Converts a binary black box log to CSV or JSON lines.
+//!
+//! Usage: `black_box_decode <LOG_FILE> [csv|json] [FRAME_TYPE]`
+//!
+//! CSV output of a single frame type (e.g. `imu`) has a header with the field names. Without frame
+//! type, each CSV row is `time,type,values...`.
+
+use black_box::{BinaryLogReader, Frame};
+use serde::Serialize;
+use std::{
+	env,
+	error::Error,
+	fs::File,
+	io::{stdout, BufReader, BufWriter, Write},
+	process,
+};
+
+#[derive(Serialize)]
+struct TimedFrame<'a> {
+	time: f64,
+	#[serde(flatten)]
+	frame: &'a Frame,
+}
+
+fn main() {
+	let args = env::args().collect::<Vec<_>>();
+
+	if args.len() < 2 || args.len() > 4 {
+		eprintln!("Usage: {} <LOG_FILE> [csv|json] [FRAME_TYPE]", args[0]);
+		process::exit(1);
+	}
+
+	let format = args.get(2).map(String::as_str).unwrap_or("csv");
+	let frame_type = args.get(3).map(String::as_str);
+
+	if let Err(e) = decode(&args[1], format, frame_type) {
+		eprintln!("{}", e);
+		process::exit(1);
+	}
+}
+
+fn decode(path: &str, format: &str, frame_type: Option<&str>) -> Result<(), Box<dyn Error>> {
+	let tag = match frame_type {
+		Some(frame_type) => Some((0..=u8::MAX)
+			.find(|&tag| Frame::tag_name(tag) == Some(frame_type))
+			.ok_or(format!("Unknown frame type {}", frame_type))?),
+		None => None,
+	};
+
+	let reader = BinaryLogReader::new(BufReader::new(File::open(path)?))?;
+	let stdout = stdout();
+	let mut output = BufWriter::new(stdout.lock());
+
+	match (format, tag) {
+		("csv", Some(tag)) => writeln!(output, "time,{}", Frame::field_names(tag).unwrap().join(","))?,
+		("csv", None) | ("json", _) => {}
+		_ => return Err(format!("Unknown output format {}", format).into()),
+	}
+
+	for frame in reader {
+		let (time, frame) = frame?;
+
+		if tag.map_or(false, |tag| tag != frame.tag()) {
+			continue;
+		}
+
+		if format == "json" {
+			serde_json::to_writer(&mut output, &TimedFrame { time: time.as_secs_f64(), frame: &frame })?;
+			writeln!(output)?;
+		} else {
+			let values = frame.values()
+				.iter()
+				.map(|value| value.to_string())
+				.collect::<Vec<_>>()
+				.join(",");
+
+			if tag.is_some() {
+				writeln!(output, "{:.6},{}", time.as_secs_f64(), values)?;
+			} else {
+				writeln!(output, "{:.6},{},{}", time.as_secs_f64(), frame.name(), values)?;
+			}
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,222 @@
+//! Binary black box log format.
+//!
+//! A log starts with a header made of the magic bytes `BBOX` followed by the format version as a
+//! little-endian `u16`. The header is followed by frames, each one made of:
+//! - the frame tag (`u8`), see `Frame::tag`,
+//! - the time since the start of the black box in microseconds (little-endian `u64`),
+//! - the frame payload: as many little-endian `f32` as `Frame::field_names` for this tag.
+
+use std::{
+	error::Error,
+	fmt,
+	fmt::{Display, Formatter},
+	io,
+	io::{Read, Write},
+	time::Duration,
+};
+
+use crate::frame::Frame;
+
+pub const MAGIC: [u8; 4] = *b"BBOX";
+pub const VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum DecodeError {
+	Io(io::Error),
+	InvalidMagic,
+	UnsupportedVersion(u16),
+	UnknownTag(u8),
+	InvalidPayload(u8),
+	/// The log ends in the middle of a frame, which happens if the writer was interrupted.
+	TruncatedFrame,
+}
+
+impl Display for DecodeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			DecodeError::Io(e) => write!(f, "{}", e),
+			DecodeError::InvalidMagic => write!(f, "Not a binary black box log"),
+			DecodeError::UnsupportedVersion(version) => {
+				write!(f, "Unsupported log version {} (expected {})", version, VERSION)
+			}
+			DecodeError::UnknownTag(tag) => write!(f, "Unknown frame tag {}", tag),
+			DecodeError::InvalidPayload(tag) => write!(f, "Invalid payload for frame tag {}", tag),
+			DecodeError::TruncatedFrame => write!(f, "Truncated frame"),
+		}
+	}
+}
+
+impl Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+	fn from(e: io::Error) -> Self {
+		match e.kind() {
+			io::ErrorKind::UnexpectedEof => DecodeError::TruncatedFrame,
+			_ => DecodeError::Io(e),
+		}
+	}
+}
+
+pub struct BinaryLogWriter<W: Write> {
+	writer: W,
+}
+
+impl<W: Write> BinaryLogWriter<W> {
+	/// Writes the header of the log.
+	pub fn new(mut writer: W) -> io::Result<Self> {
+		writer.write_all(&MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+
+		Ok(Self { writer })
+	}
+
+	pub fn write(&mut self, time: Duration, frame: &Frame) -> io::Result<()> {
+		let values = frame.values();
+		let mut buffer = Vec::with_capacity(9 + 4 * values.len());
+
+		buffer.push(frame.tag());
+		buffer.extend_from_slice(&(time.as_micros() as u64).to_le_bytes());
+
+		for value in values {
+			buffer.extend_from_slice(&value.to_le_bytes());
+		}
+
+		self.writer.write_all(&buffer)
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+/// Iterator over the frames of a binary log.
+pub struct BinaryLogReader<R: Read> {
+	reader: R,
+}
+
+impl<R: Read> BinaryLogReader<R> {
+	/// Reads and checks the header of the log.
+	pub fn new(mut reader: R) -> Result<Self, DecodeError> {
+		let mut magic = [0u8; 4];
+		reader.read_exact(&mut magic)
+			.map_err(|_| DecodeError::InvalidMagic)?;
+
+		if magic != MAGIC {
+			return Err(DecodeError::InvalidMagic);
+		}
+
+		let mut version = [0u8; 2];
+		reader.read_exact(&mut version)?;
+		let version = u16::from_le_bytes(version);
+
+		if version != VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		Ok(Self { reader })
+	}
+
+	fn read_frame(&mut self, tag: u8) -> Result<(Duration, Frame), DecodeError> {
+		let field_count = Frame::field_names(tag)
+			.ok_or(DecodeError::UnknownTag(tag))?
+			.len();
+
+		let mut time = [0u8; 8];
+		self.reader.read_exact(&mut time)?;
+		let time = Duration::from_micros(u64::from_le_bytes(time));
+
+		let mut values = Vec::with_capacity(field_count);
+		let mut value = [0u8; 4];
+
+		for _ in 0..field_count {
+			self.reader.read_exact(&mut value)?;
+			values.push(f32::from_le_bytes(value));
+		}
+
+		let frame = Frame::from_values(tag, &values)
+			.ok_or(DecodeError::InvalidPayload(tag))?;
+
+		Ok((time, frame))
+	}
+}
+
+impl<R: Read> Iterator for BinaryLogReader<R> {
+	type Item = Result<(Duration, Frame), DecodeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut tag = [0u8; 1];
+
+		match self.reader.read(&mut tag) {
+			Ok(0) => None,
+			Ok(_) => Some(self.read_frame(tag[0])),
+			Err(e) => Some(Err(e.into())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::binary_log::{BinaryLogReader, BinaryLogWriter, DecodeError};
+	use crate::frame::{Axis, Frame};
+	use std::time::Duration;
+
+	fn frames() -> Vec<(Duration, Frame)> {
+		let mut channels = [0.5; 16];
+		channels[2] = 0.;
+
+		vec![
+			(Duration::from_micros(0), Frame::Imu {
+				acc: [0.1, -0.2, 9.81],
+				gyr: [0.01, 0.02, -0.03],
+				mag: [0.2, 0., -0.4],
+			}),
+			(Duration::from_micros(2000), Frame::Attitude { quaternion: [1., 0., 0., 0.] }),
+			(Duration::from_micros(2001), Frame::Setpoint { roll: 0.5, pitch: -0.5, yaw: 0. }),
+			(Duration::from_micros(2002), Frame::PidTerms { axis: Axis::Yaw, p: 0.1, i: 0.2, d: 0.3 }),
+			(Duration::from_micros(2003), Frame::Mixer { outputs: [0.1, 0.2, 0.3, 0.4] }),
+			(Duration::from_micros(20_000), Frame::Rc { connected: true, channels }),
+			(Duration::from_secs(3600), Frame::Power { voltage: 12.2, current: 8.5 }),
+		]
+	}
+
+	#[test]
+	fn round_trip_test() {
+		let mut writer = BinaryLogWriter::new(Vec::new()).unwrap();
+
+		for (time, frame) in frames() {
+			writer.write(time, &frame).unwrap();
+		}
+
+		let bytes = writer.into_inner();
+		let decoded = BinaryLogReader::new(bytes.as_slice())
+			.unwrap()
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+
+		assert_eq!(decoded, frames());
+	}
+
+	#[test]
+	fn invalid_header_test() {
+		assert!(matches!(BinaryLogReader::new(&b"[0.000][quadcopter]"[..]),
+						 Err(DecodeError::InvalidMagic)));
+		assert!(matches!(BinaryLogReader::new(&b"BBOX\x02\x00"[..]),
+						 Err(DecodeError::UnsupportedVersion(2))));
+	}
+
+	#[test]
+	fn truncated_frame_test() {
+		let mut writer = BinaryLogWriter::new(Vec::new()).unwrap();
+		writer.write(Duration::from_millis(1), &Frame::Power { voltage: 12., current: 1. }).unwrap();
+
+		let mut bytes = writer.into_inner();
+		bytes.pop();
+
+		let mut reader = BinaryLogReader::new(bytes.as_slice()).unwrap();
+		assert!(matches!(reader.next(), Some(Err(DecodeError::TruncatedFrame))));
+	}
+}
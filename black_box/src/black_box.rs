@@ -4,13 +4,17 @@ use log::{Level, LevelFilter, Log, Metadata, Record};
 use std::{
 	collections::VecDeque,
 	fs::{File, OpenOptions},
-	io::Write,
+	io::{BufWriter, Write},
+	sync::atomic::{AtomicBool, Ordering},
 	thread,
 	thread::JoinHandle,
 	time::{Instant, Duration},
 };
 use std::error::Error;
 
+use crate::binary_log::BinaryLogWriter;
+use crate::frame::Frame;
+
 lazy_static! {
     static ref BLACK_BOX_CHANNEL: (Sender<BlackBoxInput>, Receiver<BlackBoxInput>) = unbounded::<BlackBoxInput>();
     static ref BLACK_BOX_LOGGER: BlackBoxLogger = BlackBoxLogger {
@@ -18,25 +22,40 @@ lazy_static! {
     };
 }
 
+/// Frames are dropped until a black box is spawned, so that producers running without black box
+/// (replay, tests) do not fill the channel.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
 enum BlackBoxInput {
 	Message(String),
+	Frame(Duration, Frame),
 	Flush,
 }
 
-/// Thread that receives log messages and save them to file asynchronously
+/// Records a frame in the binary log, timestamped from the start of the black box.
+pub fn record(frame: Frame) {
+	if RECORDING.load(Ordering::Relaxed) {
+		let time = Instant::now() - BLACK_BOX_LOGGER.start_instant;
+		let _ = BLACK_BOX_CHANNEL.0.send(BlackBoxInput::Frame(time, frame));
+	}
+}
+
+/// Thread that receives log messages and frames, and save them to file asynchronously. Messages
+/// are written to a `.log` text file, frames to a `.bbx` binary file (see `binary_log`).
 pub struct BlackBox {
 	file: File,
+	binary_log: BinaryLogWriter<BufWriter<File>>,
 	buffer: VecDeque<String>,
 	last_flush_instant: Instant,
 }
 
 impl BlackBox {
 	pub fn new() -> Self {
-		let log_file_name = {
+		let log_file_stem = {
 			let now = chrono::offset::Local::now();
 
 			format!(
-				"autopilot_{}-{}-{}_{}-{}-{}.log",
+				"autopilot_{}-{}-{}_{}-{}-{}",
 				now.time().hour(),
 				now.time().minute(),
 				now.time().second(),
@@ -46,14 +65,17 @@ impl BlackBox {
 			)
 		};
 
+		let open = |extension: &str| OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(format!("{}.{}", log_file_stem, extension))
+			.unwrap();
+
 		BlackBox {
 			buffer: VecDeque::<String>::new(),
-			file: OpenOptions::new()
-				.write(true)
-				.create(true)
-				.truncate(true)
-				.open(log_file_name)
-				.unwrap(),
+			file: open("log"),
+			binary_log: BinaryLogWriter::new(BufWriter::new(open("bbx"))).unwrap(),
 			last_flush_instant: Instant::now(),
 		}
 	}
@@ -72,6 +94,7 @@ impl BlackBox {
 		while let Some(message) = self.buffer.pop_front() {
 			writeln!(self.file, "{}", message)?;
 		}
+		self.binary_log.flush()?;
 		Ok(())
 	}
 
@@ -84,6 +107,11 @@ impl BlackBox {
 					println!("{}", &message);
 					self.buffer.push_back(message)
 				}
+				BlackBoxInput::Frame(time, frame) => {
+					if let Err(e) = self.binary_log.write(time, &frame) {
+						self.buffer.push_back(format!("Failed to record frame: {}", e));
+					}
+				}
 				BlackBoxInput::Flush => self.try_flush(),
 			}
 
@@ -103,6 +131,8 @@ impl BlackBox {
 			.map(|()| log::set_max_level(level_filter))
 			.unwrap();
 
+		RECORDING.store(true, Ordering::Relaxed);
+
 		thread::spawn(move || loop { self.receive_loop() })
 	}
 }
@@ -0,0 +1,183 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+	Roll,
+	Pitch,
+	Yaw,
+}
+
+/// Typed record of the binary black box log. Vectors are expressed in body frame, in
+/// international system units, except magnetic field (gauss).
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Frame {
+	/// Filtered IMU samples as fed to the autopilot.
+	Imu {
+		acc: [f32; 3],
+		gyr: [f32; 3],
+		mag: [f32; 3],
+	},
+	/// Attitude quaternion `(w, i, j, k)` estimated by the AHRS.
+	Attitude {
+		quaternion: [f32; 4],
+	},
+	/// Target angular rates.
+	Setpoint {
+		roll: f32,
+		pitch: f32,
+		yaw: f32,
+	},
+	PidTerms {
+		axis: Axis,
+		p: f32,
+		i: f32,
+		d: f32,
+	},
+	/// ESC commands within (0., 1.).
+	Mixer {
+		outputs: [f32; 4],
+	},
+	/// Normalized RC channels. Channels are meaningless when `connected` is false.
+	Rc {
+		connected: bool,
+		channels: [f32; 16],
+	},
+	Power {
+		voltage: f32,
+		current: f32,
+	},
+}
+
+const TAG_IMU: u8 = 1;
+const TAG_ATTITUDE: u8 = 2;
+const TAG_SETPOINT: u8 = 3;
+const TAG_PID_TERMS: u8 = 4;
+const TAG_MIXER: u8 = 5;
+const TAG_RC: u8 = 6;
+const TAG_POWER: u8 = 7;
+
+impl Frame {
+	pub fn tag(&self) -> u8 {
+		match self {
+			Frame::Imu { .. } => TAG_IMU,
+			Frame::Attitude { .. } => TAG_ATTITUDE,
+			Frame::Setpoint { .. } => TAG_SETPOINT,
+			Frame::PidTerms { .. } => TAG_PID_TERMS,
+			Frame::Mixer { .. } => TAG_MIXER,
+			Frame::Rc { .. } => TAG_RC,
+			Frame::Power { .. } => TAG_POWER,
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		Self::tag_name(self.tag()).unwrap()
+	}
+
+	pub fn tag_name(tag: u8) -> Option<&'static str> {
+		Some(match tag {
+			TAG_IMU => "imu",
+			TAG_ATTITUDE => "attitude",
+			TAG_SETPOINT => "setpoint",
+			TAG_PID_TERMS => "pid_terms",
+			TAG_MIXER => "mixer",
+			TAG_RC => "rc",
+			TAG_POWER => "power",
+			_ => return None,
+		})
+	}
+
+	/// Names of the values returned by `values`, for frames of the given tag.
+	pub fn field_names(tag: u8) -> Option<&'static [&'static str]> {
+		Some(match tag {
+			TAG_IMU => &["acc_x", "acc_y", "acc_z",
+				"gyr_x", "gyr_y", "gyr_z",
+				"mag_x", "mag_y", "mag_z"],
+			TAG_ATTITUDE => &["q_w", "q_i", "q_j", "q_k"],
+			TAG_SETPOINT => &["roll", "pitch", "yaw"],
+			TAG_PID_TERMS => &["axis", "p", "i", "d"],
+			TAG_MIXER => &["esc_0", "esc_1", "esc_2", "esc_3"],
+			TAG_RC => &["connected",
+				"ch_0", "ch_1", "ch_2", "ch_3", "ch_4", "ch_5", "ch_6", "ch_7",
+				"ch_8", "ch_9", "ch_10", "ch_11", "ch_12", "ch_13", "ch_14", "ch_15"],
+			TAG_POWER => &["voltage", "current"],
+			_ => return None,
+		})
+	}
+
+	/// Flat payload of the frame. Booleans are encoded as 0 or 1 and axes as 0 (roll), 1 (pitch)
+	/// or 2 (yaw).
+	pub fn values(&self) -> Vec<f32> {
+		match self {
+			Frame::Imu { acc, gyr, mag } => acc.iter().chain(gyr).chain(mag).copied().collect(),
+			Frame::Attitude { quaternion } => quaternion.to_vec(),
+			Frame::Setpoint { roll, pitch, yaw } => vec![*roll, *pitch, *yaw],
+			Frame::PidTerms { axis, p, i, d } => {
+				let axis = match axis {
+					Axis::Roll => 0.,
+					Axis::Pitch => 1.,
+					Axis::Yaw => 2.,
+				};
+				vec![axis, *p, *i, *d]
+			}
+			Frame::Mixer { outputs } => outputs.to_vec(),
+			Frame::Rc { connected, channels } => {
+				let mut values = Vec::with_capacity(17);
+				values.push(if *connected { 1. } else { 0. });
+				values.extend_from_slice(channels);
+				values
+			}
+			Frame::Power { voltage, current } => vec![*voltage, *current],
+		}
+	}
+
+	/// Builds a frame from its tag and payload, as returned by `tag` and `values`.
+	pub fn from_values(tag: u8, values: &[f32]) -> Option<Frame> {
+		if values.len() != Self::field_names(tag)?.len() {
+			return None;
+		}
+
+		let array3 = |i: usize| [values[i], values[i + 1], values[i + 2]];
+		let array4 = |i: usize| [values[i], values[i + 1], values[i + 2], values[i + 3]];
+
+		Some(match tag {
+			TAG_IMU => Frame::Imu {
+				acc: array3(0),
+				gyr: array3(3),
+				mag: array3(6),
+			},
+			TAG_ATTITUDE => Frame::Attitude { quaternion: array4(0) },
+			TAG_SETPOINT => Frame::Setpoint {
+				roll: values[0],
+				pitch: values[1],
+				yaw: values[2],
+			},
+			TAG_PID_TERMS => Frame::PidTerms {
+				axis: match values[0] as u8 {
+					0 => Axis::Roll,
+					1 => Axis::Pitch,
+					2 => Axis::Yaw,
+					_ => return None,
+				},
+				p: values[1],
+				i: values[2],
+				d: values[3],
+			},
+			TAG_MIXER => Frame::Mixer { outputs: array4(0) },
+			TAG_RC => {
+				let mut channels = [0.; 16];
+				channels.copy_from_slice(&values[1..]);
+				Frame::Rc {
+					connected: values[0] != 0.,
+					channels,
+				}
+			}
+			TAG_POWER => Frame::Power {
+				voltage: values[0],
+				current: values[1],
+			},
+			_ => return None,
+		})
+	}
+}
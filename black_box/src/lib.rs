@@ -1,9 +1,13 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod binary_log;
 mod black_box;
+mod frame;
 
-pub use black_box::BlackBox;
+pub use binary_log::{BinaryLogReader, BinaryLogWriter, DecodeError};
+pub use black_box::{record, BlackBox};
+pub use frame::{Axis, Frame};
 
 #[cfg(test)]
 mod tests {
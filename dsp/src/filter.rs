@@ -0,0 +1,69 @@
+use nalgebra::{RealField, Vector3};
+
+use crate::{AlphaBetaGamma, Biquad, ScalarAlphaBeta};
+
+/// Filters processing samples of type `T` taken every `dt` seconds.
+pub trait Filter<N: RealField, T>
+	where
+		Self: Send,
+{
+	fn filter(&mut self, input: T, dt: N) -> T;
+}
+
+/// Filters applied one after another, in insertion order. An empty chain leaves samples unchanged.
+pub struct FilterChain<N: RealField, T> {
+	filters: Vec<Box<dyn Filter<N, T>>>,
+}
+
+impl<N: RealField, T> FilterChain<N, T> {
+	pub fn new() -> Self {
+		Self {
+			filters: Vec::new(),
+		}
+	}
+
+	pub fn push(&mut self, filter: Box<dyn Filter<N, T>>) {
+		self.filters.push(filter);
+	}
+
+	pub fn len(&self) -> usize {
+		self.filters.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.filters.is_empty()
+	}
+}
+
+impl<N: RealField, T> Default for FilterChain<N, T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<N: RealField, T> Filter<N, T> for FilterChain<N, T> {
+	fn filter(&mut self, input: T, dt: N) -> T {
+		self.filters
+			.iter_mut()
+			.fold(input, |value, filter| filter.filter(value, dt))
+	}
+}
+
+impl<N: RealField> Filter<N, Vector3<N>> for Biquad<N> {
+	/// Biquad coefficients already account for the sample frequency, therefore `dt` is ignored.
+	fn filter(&mut self, input: Vector3<N>, _dt: N) -> Vector3<N> {
+		self.update(input)
+	}
+}
+
+impl Filter<f64, Vector3<f64>> for AlphaBetaGamma<f64> {
+	fn filter(&mut self, input: Vector3<f64>, dt: f64) -> Vector3<f64> {
+		self.update(input, dt)
+	}
+}
+
+impl<N: RealField> Filter<N, N> for ScalarAlphaBeta<N> {
+	fn filter(&mut self, input: N, dt: N) -> N {
+		self.update(input, dt)
+	}
+}
@@ -1,14 +1,35 @@
 mod alpha_beta_gamma;
 pub mod biquad;
+mod filter;
 
 pub use alpha_beta_gamma::AlphaBetaGamma;
 pub use alpha_beta_gamma::ScalarAlphaBeta;
 pub use biquad::Biquad;
+pub use filter::{Filter, FilterChain};
 
 #[cfg(test)]
 mod tests {
+    use crate::{Filter, FilterChain, ScalarAlphaBeta};
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn empty_filter_chain_test() {
+        let mut chain = FilterChain::<f64, f64>::new();
+        assert_eq!(chain.filter(4.2, 0.002), 4.2);
+    }
+
+    #[test]
+    fn filter_chain_order_test() {
+        let mut chain = FilterChain::<f64, f64>::new();
+        chain.push(Box::new(ScalarAlphaBeta::new(0.5, 0.)));
+        chain.push(Box::new(ScalarAlphaBeta::new(0.5, 0.)));
+
+        // Each alpha-beta filter halves the first sample, as its state starts at zero
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.filter(4., 0.002), 1.);
+    }
 }
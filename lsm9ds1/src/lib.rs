@@ -1,6 +1,8 @@
 mod lsm9ds1;
+mod settings;
 
 pub use lsm9ds1::*;
+pub use settings::*;
 
 #[cfg(test)]
 mod tests {
@@ -6,16 +6,56 @@ use nalgebra::Vector3;
 
 use constants::*;
 use registers::*;
-use std::f64::consts::PI;
+
+use crate::settings::{AccRange, GyrRange, MagRange, Settings};
 
 /// LSM9DS1 driver using Linux `Spidev`
 pub struct LSM9DS1 {
 	acc_gyr: Spidev,
 	mag: Spidev,
+	settings: Settings,
 }
 
 pub const OUTPUT_DELAY: Duration = Duration::from_micros(4202);
 
+/// Number of samples the FIFO can hold.
+pub const FIFO_SIZE: usize = 32;
+
+/// Output changes (absolute value, per axis) measured by the self-test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTest {
+	/// Accelerometer output change (m/s²), within ±8 g.
+	pub acc: Vector3<f64>,
+	/// Gyroscope output change (rad/s), within ±2000 dps.
+	pub gyr: Vector3<f64>,
+	/// Magnetometer output change (gauss), within ±12 gauss.
+	pub mag: Vector3<f64>,
+}
+
+impl SelfTest {
+	/// Names of the sensors whose output changes are out of datasheet limits (table 3).
+	pub fn failures(&self) -> Vec<&'static str> {
+		const G: f64 = 9.80665;
+		let in_range = |change: f64, min: f64, max: f64| change >= min && change <= max;
+
+		let mut failures = Vec::new();
+
+		if !self.acc.iter().all(|&change| in_range(change, 0.06 * G, 1.7 * G)) {
+			failures.push("accelerometer");
+		}
+
+		if !self.gyr.iter().all(|&change| in_range(change.to_degrees(), 20., 250.)) {
+			failures.push("gyroscope");
+		}
+
+		if !(in_range(self.mag.x, 1., 3.) && in_range(self.mag.y, 1., 3.) && in_range(self.mag.z, 0.1, 1.)) {
+			failures.push("magnetometer");
+		}
+
+		failures
+	}
+}
+
 impl LSM9DS1 {
 	pub fn new(acc_gyr_spi_path: &str, mag_spi_path: &str) -> Result<Self, io::Error> {
 		let options = SpidevOptions::new()
@@ -33,9 +73,25 @@ impl LSM9DS1 {
 		Ok(Self {
 			acc_gyr,
 			mag,
+			settings: Settings::default(),
 		})
 	}
 
+	/// Full-scale ranges, output data rates and bandwidths, applied by `init`.
+	pub fn with_settings(mut self, settings: Settings) -> Self {
+		self.settings = settings;
+		self
+	}
+
+	pub fn settings(&self) -> &Settings {
+		&self.settings
+	}
+
+	/// Period between two successive accelerometer and gyroscope samples.
+	pub fn sample_period(&self) -> Duration {
+		self.settings.acc_gyr_odr.period()
+	}
+
 	fn identify(&mut self) -> Result<(), io::Error> {
 		const ACC_GYR_CHIP_ID: u8 = 0b01101000;
 
@@ -61,63 +117,186 @@ impl LSM9DS1 {
 		const US_200: Duration = Duration::from_millis(200);
 		thread::sleep(US_200);
 
-		self.acc_gyr.write_register(CTRL_REG1_G, ODR_GYR_952_HZ | FS_GYR_500_DPS)?;
+		self.configure()?;
+
+		Ok(self)
+	}
+
+	/// Writes ranges, output data rates and bandwidths of `settings`.
+	fn configure(&mut self) -> Result<(), io::Error> {
+		const US_200: Duration = Duration::from_millis(200);
+		let settings = self.settings;
+
+		self.acc_gyr.write_register(CTRL_REG1_G, settings.acc_gyr_odr.bits()
+			| settings.gyr_range.bits()
+			| settings.gyr_bandwidth.bits())?;
 
 		const GYR_ENABLE_3_AXIS: u8 = XEN_G | YEN_G | ZEN_G;
 		self.acc_gyr.write_register(CTRL_REG4, GYR_ENABLE_3_AXIS)?;
 		thread::sleep(US_200);
 
+		// Accelerometer ODR bits are identical to gyroscope ones
 		const ACC_ENABLE_3_AXIS: u8 = XEN_ACC | YEN_ACC | ZEN_ACC;
 		self.acc_gyr.write_register(CTRL_REG5_ACC, ACC_ENABLE_3_AXIS)?;
-		self.acc_gyr.write_register(CTRL_REG6_ACC, ODR_ACC_952_HZ | FS_ACC_4G)?;
+		self.acc_gyr.write_register(CTRL_REG6_ACC, settings.acc_gyr_odr.bits()
+			| settings.acc_range.bits()
+			| settings.acc_bandwidth.bits())?;
 		// self.acc_gyr.write_register(CTRL_REG7_ACC, ACC_HR | ACC_DCF_9 | ACC_FDS)?; // TODO: utile ?
 
 		thread::sleep(US_200);
 
-		self.mag.write_register(CTRL_REG1_M, TEMP_COMP | OM_ULTRA_HIGH | ODR_M_80_HZ)?;
-		self.mag.write_register(CTRL_REG2_M, FS_M_4G)?;
+		let (om, omz) = settings.mag_performance.bits();
+		self.mag.write_register(CTRL_REG1_M, TEMP_COMP | om | settings.mag_odr.bits())?;
+		self.mag.write_register(CTRL_REG2_M, settings.mag_range.bits())?;
 
 		self.mag.write_register(CTRL_REG3_M, MD_CONTINUOUS)?;
-		self.mag.write_register(CTRL_REG4_M, OMZ_ULTRA_HIGH)?;
+		self.mag.write_register(CTRL_REG4_M, omz)?;
 		self.mag.write_register(CTRL_REG5_M, 0)?;
 
 		thread::sleep(US_200);
 
-		Ok(self)
+		Ok(())
 	}
 
-	pub fn read_output(&mut self) -> Result<([Vector3<f64>; 3], Instant), io::Error> {
-		const G: f64 = 9.80665;
+	/// Runs the self-test of the three sensors, which must be at rest: outputs are averaged with
+	/// and without the self-test actuation, at ±8 g, ±2000 dps and ±12 gauss. Settings are
+	/// restored afterwards. Must be called before enabling the FIFO.
+	pub fn self_test(&mut self) -> Result<SelfTest, io::Error> {
+		const SETTLING_DELAY: Duration = Duration::from_millis(200);
+
+		let settings = self.settings;
+
+		self.settings = Settings {
+			acc_range: AccRange::G8,
+			gyr_range: GyrRange::Dps2000,
+			mag_range: MagRange::Gauss12,
+			..settings
+		};
+		self.configure()?;
+
+		let (acc_off, gyr_off, mag_off) = self.average_outputs()?;
+
+		self.acc_gyr.write_register(CTRL_REG10, ST_G | ST_XL)?;
+		let (om, _) = self.settings.mag_performance.bits();
+		self.mag.write_register(CTRL_REG1_M, TEMP_COMP | om | self.settings.mag_odr.bits() | ST_M)?;
+		thread::sleep(SETTLING_DELAY);
+
+		let (acc_on, gyr_on, mag_on) = self.average_outputs()?;
+
+		self.acc_gyr.write_register(CTRL_REG10, 0)?;
+		self.settings = settings;
+		self.configure()?;
 
-		const ACC_4G_SCALE: f64 = 4. * G / i16::max_value() as f64;
-		const GYR_500_DPS_SCALE: f64 = - 500. * PI / i16::max_value() as f64 / 180.;
-		const MAG_4G_SCALE: f64 = 4. / i16::max_value() as f64;
+		Ok(SelfTest {
+			acc: (acc_on - acc_off).abs(),
+			gyr: (gyr_on - gyr_off).abs(),
+			mag: (mag_on - mag_off).abs(),
+		})
+	}
+
+	fn average_outputs(&mut self) -> Result<(Vector3<f64>, Vector3<f64>, Vector3<f64>), io::Error> {
+		const SAMPLES: usize = 20;
+		// Longer than the magnetometer period at 80 Hz
+		const SAMPLE_DELAY: Duration = Duration::from_millis(15);
+
+		let mut acc = Vector3::zeros();
+		let mut gyr = Vector3::zeros();
+		let mut mag = Vector3::zeros();
+
+		for _ in 0..SAMPLES {
+			acc += self.read_acc()?;
+			gyr += self.read_gyr()?;
+			mag += self.read_mag()?;
+			thread::sleep(SAMPLE_DELAY);
+		}
+
+		Ok((acc / SAMPLES as f64, gyr / SAMPLES as f64, mag / SAMPLES as f64))
+	}
+
+	/// Reads accelerometer (m/s²), gyroscope (rad/s) and magnetometer (gauss) outputs, along with
+	/// die temperature (°C).
+	pub fn read_output(&mut self) -> Result<([Vector3<f64>; 3], f64, Instant), io::Error> {
+		let instant = Instant::now();
+
+		let acc = self.read_acc()?;
+		let gyr = self.read_gyr()?;
+		let mag = self.read_mag()?;
+		let temperature = self.read_temperature()?;
+
+		Ok(([acc, gyr, mag], temperature, instant))
+	}
+
+	/// Enables the FIFO in continuous mode: accelerometer and gyroscope samples are queued at the
+	/// output data rate, the oldest ones being overwritten once the FIFO is full.
+	pub fn enable_fifo(&mut self) -> Result<(), io::Error> {
+		self.acc_gyr.write_register(CTRL_REG9, FIFO_EN)?;
+		self.acc_gyr.write_register(FIFO_CTRL, FMODE_CONTINUOUS)?;
 
+		Ok(())
+	}
+
+	/// Reads every accelerometer and gyroscope sample queued in the FIFO, oldest first. Samples
+	/// are timestamped backwards from the read instant, one `sample_period` apart. Also returns
+	/// whether the FIFO overran, i.e. whether samples were lost since the previous read.
+	pub fn read_fifo(&mut self) -> Result<(Vec<([Vector3<f64>; 2], Instant)>, bool), io::Error> {
+		let fifo_src = self.acc_gyr.read_register(FIFO_SRC)?;
 		let instant = Instant::now();
 
+		let count = (fifo_src & FSS_MASK) as u32;
+		let overrun = fifo_src & OVRN != 0;
+
+		let sample_period = self.sample_period();
+		let mut samples = Vec::with_capacity(count as usize);
+
+		for i in 0..count {
+			// Reading the accelerometer output last pops the FIFO slot
+			let gyr = self.read_gyr()?;
+			let acc = self.read_acc()?;
+
+			samples.push(([acc, gyr], instant - sample_period * (count - 1 - i)));
+		}
+
+		Ok((samples, overrun))
+	}
+
+	pub fn read_acc(&mut self) -> Result<Vector3<f64>, io::Error> {
 		let acc_output = self.read_acc_or_gyr_output(OUT_X_L_ACC)?;
 
-		let acc_x = i16::from_le_bytes([acc_output[0], acc_output[1]]) as f64 * ACC_4G_SCALE;
-		let acc_y = i16::from_le_bytes([acc_output[2], acc_output[3]]) as f64 * ACC_4G_SCALE;
-		let acc_z = i16::from_le_bytes([acc_output[4], acc_output[5]]) as f64 * ACC_4G_SCALE;
+		Ok(Self::vector(&acc_output, self.settings.acc_range.scale()))
+	}
 
+	pub fn read_gyr(&mut self) -> Result<Vector3<f64>, io::Error> {
 		let gyr_output = self.read_acc_or_gyr_output(OUT_X_L_G)?;
 
-		let gyr_x = i16::from_le_bytes([gyr_output[0], gyr_output[1]]) as f64 * GYR_500_DPS_SCALE;
-		let gyr_y = i16::from_le_bytes([gyr_output[2], gyr_output[3]]) as f64 * GYR_500_DPS_SCALE;
-		let gyr_z = i16::from_le_bytes([gyr_output[4], gyr_output[5]]) as f64 * GYR_500_DPS_SCALE;
+		// Gyroscope axes are reversed with respect to accelerometer ones
+		Ok(Self::vector(&gyr_output, - self.settings.gyr_range.scale()))
+	}
 
+	pub fn read_mag(&mut self) -> Result<Vector3<f64>, io::Error> {
 		let mag_output = self.read_mag_output(OUT_X_L_M)?;
 
-		let mag_x = i16::from_le_bytes([mag_output[0], mag_output[1]]) as f64 * MAG_4G_SCALE;
-		let mag_y = i16::from_le_bytes([mag_output[2], mag_output[3]]) as f64 * MAG_4G_SCALE;
-		let mag_z = i16::from_le_bytes([mag_output[4], mag_output[5]]) as f64 * MAG_4G_SCALE;
+		Ok(Self::vector(&mag_output, self.settings.mag_range.scale()))
+	}
+
+	#[inline(always)]
+	fn vector(output: &[u8; 6], scale: f64) -> Vector3<f64> {
+		Vector3::new(i16::from_le_bytes([output[0], output[1]]) as f64 * scale,
+					 i16::from_le_bytes([output[2], output[3]]) as f64 * scale,
+					 i16::from_le_bytes([output[4], output[5]]) as f64 * scale)
+	}
 
-		let acc = Vector3::<f64>::new(acc_x, acc_y, acc_z);
-		let gyr = Vector3::<f64>::new(gyr_x, gyr_y, gyr_z);
-		let mag = Vector3::<f64>::new(mag_x, mag_y, mag_z);
+	/// Die temperature (°C), output being null at 25 °C with 16 LSB/°C (datasheet p. 14).
+	pub fn read_temperature(&mut self) -> Result<f64, io::Error> {
+		const TEMP_OFFSET: f64 = 25.;
+		const TEMP_SCALE: f64 = 1. / 16.;
 
-		Ok(([acc, gyr, mag], instant))
+		let mut rx_buffer = [0u8; 3];
+		let mut tx_buffer = [0u8; 3];
+		tx_buffer[0] = OUT_TEMP_L | READ_FLAG;
+
+		self.acc_gyr.transfer(&mut SpidevTransfer::read_write(&tx_buffer, &mut rx_buffer))?;
+
+		Ok(TEMP_OFFSET + i16::from_le_bytes([rx_buffer[1], rx_buffer[2]]) as f64 * TEMP_SCALE)
 	}
 
 	#[inline(always)]
@@ -243,7 +422,7 @@ mod registers {
 }
 
 #[allow(dead_code)]
-mod constants {
+pub(crate) mod constants {
 	pub const ACC_HR: u8 = 1 << 7;
 	pub const ACC_DCF_9: u8 = 1 << 6;
 	pub const ACC_FDS: u8 = 1 << 2;
@@ -281,9 +460,24 @@ mod constants {
 	pub const FS_ACC_4G: u8 = 0x10;
 	pub const FS_ACC_8G: u8 = 0x18;
 	pub const FS_ACC_16G: u8 = 0x08;
+	pub const BW_SCAL_ODR: u8 = 0x04;
+	pub const ST_G: u8 = 0x04;
+	pub const ST_XL: u8 = 0x01;
+
+	// FIFO configuration and status bits
+	pub const FIFO_EN: u8 = 0x02;
+	pub const STOP_ON_FTH: u8 = 0x01;
+	pub const FMODE_BYPASS: u8 = 0x00;
+	pub const FMODE_FIFO: u8 = 0x20;
+	pub const FMODE_CONTINUOUS: u8 = 0xC0;
+	pub const FTH_MASK: u8 = 0x1F;
+	pub const FSS_MASK: u8 = 0x3F;
+	pub const OVRN: u8 = 0x40;
+	pub const FTH: u8 = 0x80;
 
 	// Configuration bits Magnetometer
 	pub const TEMP_COMP: u8 = 0x80;
+	pub const ST_M: u8 = 0x01;
 	pub const OM_LOW: u8 = 0x00;
 	pub const OM_MEDIUM: u8 = 0x20;
 	pub const OM_HIGH: u8 = 0x40;
@@ -308,4 +502,26 @@ mod constants {
 	pub const OMZ_MEDIUM: u8 = 0x04;
 	pub const OMZ_HIGH: u8 = 0x08;
 	pub const OMZ_ULTRA_HIGH: u8 = 0x0C;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use crate::lsm9ds1::SelfTest;
+	use nalgebra::Vector3;
+
+	#[test]
+	fn self_test_failures_test() {
+		let self_test = SelfTest {
+			acc: Vector3::new(5., 6., 4.),
+			gyr: Vector3::new(1.5, 1.4, 1.6),
+			mag: Vector3::new(1.8, 1.9, 0.5),
+		};
+		assert!(self_test.failures().is_empty());
+
+		let self_test = SelfTest {
+			gyr: Vector3::new(1.5, 0., 1.6),
+			mag: Vector3::new(1.8, 1.9, 2.),
+			..self_test
+		};
+		assert_eq!(self_test.failures(), vec!["gyroscope", "magnetometer"]);
+	}
+}
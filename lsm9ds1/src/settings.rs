@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use crate::lsm9ds1::constants::*;
+
+const G: f64 = 9.80665;
+
+/// Accelerometer full-scale range.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccRange {
+	G2,
+	G4,
+	G8,
+	G16,
+}
+
+impl AccRange {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			AccRange::G2 => FS_ACC_2G,
+			AccRange::G4 => FS_ACC_4G,
+			AccRange::G8 => FS_ACC_8G,
+			AccRange::G16 => FS_ACC_16G,
+		}
+	}
+
+	/// Sensitivity (m/s² per LSB), datasheet table 3.
+	pub fn scale(&self) -> f64 {
+		let milli_g = match self {
+			AccRange::G2 => 0.061,
+			AccRange::G4 => 0.122,
+			AccRange::G8 => 0.244,
+			AccRange::G16 => 0.732,
+		};
+
+		milli_g * 1e-3 * G
+	}
+
+	/// Highest measurable acceleration (m/s²).
+	pub fn full_scale(&self) -> f64 {
+		self.scale() * i16::max_value() as f64
+	}
+}
+
+/// Gyroscope full-scale range.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GyrRange {
+	Dps245,
+	Dps500,
+	Dps2000,
+}
+
+impl GyrRange {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			GyrRange::Dps245 => FS_GYR_245_DPS,
+			GyrRange::Dps500 => FS_GYR_500_DPS,
+			GyrRange::Dps2000 => FS_GYR_2000_DPS,
+		}
+	}
+
+	/// Sensitivity (rad/s per LSB), datasheet table 3.
+	pub fn scale(&self) -> f64 {
+		let milli_dps = match self {
+			GyrRange::Dps245 => 8.75,
+			GyrRange::Dps500 => 17.5,
+			GyrRange::Dps2000 => 70.,
+		};
+
+		milli_dps * 1e-3 * PI / 180.
+	}
+
+	/// Highest measurable angular rate (rad/s).
+	pub fn full_scale(&self) -> f64 {
+		self.scale() * i16::max_value() as f64
+	}
+}
+
+/// Magnetometer full-scale range.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MagRange {
+	Gauss4,
+	Gauss8,
+	Gauss12,
+	Gauss16,
+}
+
+impl MagRange {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			MagRange::Gauss4 => FS_M_4G,
+			MagRange::Gauss8 => FS_M_8G,
+			MagRange::Gauss12 => FS_M_12G,
+			MagRange::Gauss16 => FS_M_16G,
+		}
+	}
+
+	/// Sensitivity (gauss per LSB), datasheet table 3.
+	pub fn scale(&self) -> f64 {
+		let milli_gauss = match self {
+			MagRange::Gauss4 => 0.14,
+			MagRange::Gauss8 => 0.29,
+			MagRange::Gauss12 => 0.43,
+			MagRange::Gauss16 => 0.58,
+		};
+
+		milli_gauss * 1e-3
+	}
+}
+
+/// Output data rate of the accelerometer and gyroscope, which share it while both are enabled.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccGyrOdr {
+	Hz14_9,
+	Hz59_5,
+	Hz119,
+	Hz238,
+	Hz476,
+	Hz952,
+}
+
+impl AccGyrOdr {
+	/// Gyroscope ODR bits, the accelerometer ones being identical.
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			AccGyrOdr::Hz14_9 => ODR_GYR_14_900_MHZ,
+			AccGyrOdr::Hz59_5 => ODR_GYR_59_500_MHZ,
+			AccGyrOdr::Hz119 => ODR_GYR_119_HZ,
+			AccGyrOdr::Hz238 => ODR_GYR_238_HZ,
+			AccGyrOdr::Hz476 => ODR_GYR_476_HZ,
+			AccGyrOdr::Hz952 => ODR_GYR_952_HZ,
+		}
+	}
+
+	/// Output data rate (Hz).
+	pub fn frequency(&self) -> f64 {
+		match self {
+			AccGyrOdr::Hz14_9 => 14.9,
+			AccGyrOdr::Hz59_5 => 59.5,
+			AccGyrOdr::Hz119 => 119.,
+			AccGyrOdr::Hz238 => 238.,
+			AccGyrOdr::Hz476 => 476.,
+			AccGyrOdr::Hz952 => 952.,
+		}
+	}
+
+	/// Period between two successive samples.
+	pub fn period(&self) -> Duration {
+		Duration::from_secs_f64(1. / self.frequency())
+	}
+}
+
+/// Gyroscope bandwidth selection (`BW_G`), whose cutoff frequencies depend on the output data rate
+/// (datasheet table 47), from the lowest to the highest one.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GyrBandwidth {
+	Bw0,
+	Bw1,
+	Bw2,
+	Bw3,
+}
+
+impl GyrBandwidth {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			GyrBandwidth::Bw0 => 0b00,
+			GyrBandwidth::Bw1 => 0b01,
+			GyrBandwidth::Bw2 => 0b10,
+			GyrBandwidth::Bw3 => 0b11,
+		}
+	}
+}
+
+/// Accelerometer anti-aliasing filter bandwidth.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccBandwidth {
+	/// Determined by the output data rate.
+	Auto,
+	Hz408,
+	Hz211,
+	Hz105,
+	Hz50,
+}
+
+impl AccBandwidth {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			AccBandwidth::Auto => 0,
+			AccBandwidth::Hz408 => BW_SCAL_ODR | 0b00,
+			AccBandwidth::Hz211 => BW_SCAL_ODR | 0b01,
+			AccBandwidth::Hz105 => BW_SCAL_ODR | 0b10,
+			AccBandwidth::Hz50 => BW_SCAL_ODR | 0b11,
+		}
+	}
+}
+
+/// Magnetometer output data rate.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MagOdr {
+	Hz0_625,
+	Hz1_25,
+	Hz2_5,
+	Hz5,
+	Hz10,
+	Hz20,
+	Hz40,
+	Hz80,
+}
+
+impl MagOdr {
+	pub(crate) fn bits(&self) -> u8 {
+		match self {
+			MagOdr::Hz0_625 => ODR_M_625_MHZ,
+			MagOdr::Hz1_25 => ODR_M_1250_MHZ,
+			MagOdr::Hz2_5 => ODR_M_25_MHZ,
+			MagOdr::Hz5 => ODR_M_5_HZ,
+			MagOdr::Hz10 => ODR_M_10_HZ,
+			MagOdr::Hz20 => ODR_M_20_HZ,
+			MagOdr::Hz40 => ODR_M_40_HZ,
+			MagOdr::Hz80 => ODR_M_80_HZ,
+		}
+	}
+}
+
+/// Magnetometer operating mode, trading noise against current consumption. The magnetometer has
+/// no bandwidth setting.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MagPerformance {
+	Low,
+	Medium,
+	High,
+	UltraHigh,
+}
+
+impl MagPerformance {
+	/// X and Y axes bits (`CTRL_REG1_M`) and Z axis bits (`CTRL_REG4_M`).
+	pub(crate) fn bits(&self) -> (u8, u8) {
+		match self {
+			MagPerformance::Low => (OM_LOW, OMZ_LOW),
+			MagPerformance::Medium => (OM_MEDIUM, OMZ_MEDIUM),
+			MagPerformance::High => (OM_HIGH, OMZ_HIGH),
+			MagPerformance::UltraHigh => (OM_ULTRA_HIGH, OMZ_ULTRA_HIGH),
+		}
+	}
+}
+
+/// Full-scale ranges, output data rates and bandwidths of the three sensors.
+#[serde(default)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Settings {
+	pub acc_range: AccRange,
+	pub acc_bandwidth: AccBandwidth,
+	pub gyr_range: GyrRange,
+	pub gyr_bandwidth: GyrBandwidth,
+	pub acc_gyr_odr: AccGyrOdr,
+	pub mag_range: MagRange,
+	pub mag_odr: MagOdr,
+	pub mag_performance: MagPerformance,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Settings {
+			acc_range: AccRange::G4,
+			acc_bandwidth: AccBandwidth::Auto,
+			gyr_range: GyrRange::Dps500,
+			gyr_bandwidth: GyrBandwidth::Bw0,
+			acc_gyr_odr: AccGyrOdr::Hz952,
+			mag_range: MagRange::Gauss4,
+			mag_odr: MagOdr::Hz80,
+			mag_performance: MagPerformance::UltraHigh,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::settings::{AccGyrOdr, AccRange, GyrRange, MagRange};
+	use std::time::Duration;
+
+	#[test]
+	fn scale_test() {
+		// Full scale is slightly above the nominal range
+		assert!(AccRange::G16.full_scale() > 16. * 9.80665);
+		assert!(GyrRange::Dps2000.full_scale().to_degrees() > 2000.);
+		assert!(MagRange::Gauss16.scale() * i16::max_value() as f64 > 16.);
+
+		assert!((GyrRange::Dps500.scale().to_degrees() - 0.0175).abs() < 1e-12);
+	}
+
+	#[test]
+	fn odr_test() {
+		assert_eq!(AccGyrOdr::Hz476.period(), Duration::from_nanos(2_100_840));
+	}
+}
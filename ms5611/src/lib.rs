@@ -0,0 +1,3 @@
+mod ms5611;
+
+pub use ms5611::*;
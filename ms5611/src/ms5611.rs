@@ -0,0 +1,128 @@
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use commands::*;
+
+/// MS5611 barometer driver using Linux `i2cdev`. On Navio2, the barometer is wired on I2C bus 1,
+/// at address `0x77`.
+pub struct MS5611 {
+	device: LinuxI2CDevice,
+	/// Calibration coefficients C1 to C6 read from PROM.
+	prom: [u16; 6],
+}
+
+/// Conversion time for an oversampling ratio of 4096 (datasheet p. 3).
+pub const CONVERSION_DELAY: Duration = Duration::from_micros(9040);
+
+impl MS5611 {
+	pub fn new(i2c_path: &str, address: u16) -> Result<Self, LinuxI2CError> {
+		Ok(Self {
+			device: LinuxI2CDevice::new(i2c_path, address)?,
+			prom: [0; 6],
+		})
+	}
+
+	pub fn init(mut self) -> Result<Self, LinuxI2CError> {
+		self.device.smbus_write_byte(RESET)?;
+
+		const RESET_DELAY: Duration = Duration::from_millis(3);
+		thread::sleep(RESET_DELAY);
+
+		for (i, coefficient) in self.prom.iter_mut().enumerate() {
+			let mut buffer = [0u8; 2];
+			self.device.write(&[PROM_READ + 2 * (i as u8 + 1)])?;
+			self.device.read(&mut buffer)?;
+			*coefficient = u16::from_be_bytes(buffer);
+		}
+
+		Ok(self)
+	}
+
+	/// Reads compensated pressure (Pa) and temperature (°C). Both conversions are blocking, so
+	/// reading takes twice `CONVERSION_DELAY`.
+	pub fn read_output(&mut self) -> Result<(f64, f64, Instant), LinuxI2CError> {
+		let d1 = self.convert(CONVERT_D1_OSR_4096)?;
+		let instant = Instant::now();
+		let d2 = self.convert(CONVERT_D2_OSR_4096)?;
+
+		let (temperature, pressure) = compensate(&self.prom, d1, d2);
+
+		Ok((pressure as f64, temperature as f64 / 100., instant))
+	}
+
+	fn convert(&mut self, command: u8) -> Result<u32, LinuxI2CError> {
+		self.device.smbus_write_byte(command)?;
+		thread::sleep(CONVERSION_DELAY);
+
+		let mut buffer = [0u8; 3];
+		self.device.write(&[ADC_READ])?;
+		self.device.read(&mut buffer)?;
+
+		Ok(u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]))
+	}
+}
+
+/// Computes temperature (0.01 °C) and pressure (Pa) from calibration coefficients C1 to C6 and
+/// raw digital pressure `d1` and temperature `d2`, with second order temperature compensation
+/// (datasheet p. 7-8).
+pub fn compensate(prom: &[u16; 6], d1: u32, d2: u32) -> (i32, i32) {
+	let c = |i: usize| prom[i - 1] as i64;
+	let (d1, d2) = (d1 as i64, d2 as i64);
+
+	let dt = d2 - (c(5) << 8);
+	let mut temperature = 2000 + dt * c(6) / (1 << 23);
+	let mut offset = (c(2) << 16) + c(4) * dt / (1 << 7);
+	let mut sensitivity = (c(1) << 15) + c(3) * dt / (1 << 8);
+
+	if temperature < 2000 {
+		let t2 = dt * dt / (1 << 31);
+		let mut offset2 = 5 * (temperature - 2000).pow(2) / 2;
+		let mut sensitivity2 = 5 * (temperature - 2000).pow(2) / 4;
+
+		if temperature < -1500 {
+			offset2 += 7 * (temperature + 1500).pow(2);
+			sensitivity2 += 11 * (temperature + 1500).pow(2) / 2;
+		}
+
+		temperature -= t2;
+		offset -= offset2;
+		sensitivity -= sensitivity2;
+	}
+
+	let pressure = (d1 * sensitivity / (1 << 21) - offset) / (1 << 15);
+
+	(temperature as i32, pressure as i32)
+}
+
+#[allow(dead_code)]
+mod commands {
+	pub const RESET: u8 = 0x1E;
+	pub const CONVERT_D1_OSR_256: u8 = 0x40;
+	pub const CONVERT_D1_OSR_4096: u8 = 0x48;
+	pub const CONVERT_D2_OSR_256: u8 = 0x50;
+	pub const CONVERT_D2_OSR_4096: u8 = 0x58;
+	pub const ADC_READ: u8 = 0x00;
+	pub const PROM_READ: u8 = 0xA0;
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::ms5611::compensate;
+
+	const PROM: [u16; 6] = [40127, 36924, 23317, 23282, 33464, 28312];
+
+	#[test]
+	fn datasheet_compensation_test() {
+		assert_eq!(compensate(&PROM, 9085466, 8569150), (2007, 100009));
+	}
+
+	#[test]
+	fn low_temperature_compensation_test() {
+		// dT = -100000: first order temperature is 2000 - 337, corrected by dT² / 2^31 = 4
+		let d2 = (33464 << 8) - 100_000;
+		let (temperature, _) = compensate(&PROM, 9085466, d2);
+		assert_eq!(temperature, 1663 - 4);
+	}
+}
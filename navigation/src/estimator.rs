@@ -0,0 +1,273 @@
+use std::time::Instant;
+
+use crate::geodetic::{GeodeticPosition, LocalFrame};
+
+/// Kalman filter of position and velocity along one axis, driven by acceleration.
+#[derive(Debug, Clone)]
+struct AxisFilter {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl AxisFilter {
+    fn new(position: f64, position_variance: f64, velocity: f64, velocity_variance: f64) -> Self {
+        AxisFilter {
+            position,
+            velocity,
+            covariance: [[position_variance, 0.], [0., velocity_variance]],
+        }
+    }
+
+    /// Integrates an acceleration sample, whose noise is modelled as white with the given
+    /// variance.
+    fn predict(&mut self, acceleration: f64, dt: f64, acceleration_variance: f64) {
+        self.position += self.velocity * dt + 0.5 * acceleration * dt * dt;
+        self.velocity += acceleration * dt;
+
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = acceleration_variance;
+
+        self.covariance = [
+            [p00 + dt * (p10 + p01) + dt * dt * p11 + q * dt.powi(4) / 4.,
+                p01 + dt * p11 + q * dt.powi(3) / 2.],
+            [p10 + dt * p11 + q * dt.powi(3) / 2.,
+                p11 + q * dt * dt],
+        ];
+    }
+
+    /// Corrects the state with a measurement of the position (`index` 0) or of the velocity
+    /// (`index` 1).
+    fn correct(&mut self, index: usize, measurement: f64, variance: f64) {
+        let innovation = measurement - if index == 0 { self.position } else { self.velocity };
+        let innovation_variance = self.covariance[index][index] + variance;
+
+        let gain = [
+            self.covariance[0][index] / innovation_variance,
+            self.covariance[1][index] / innovation_variance,
+        ];
+
+        self.position += gain[0] * innovation;
+        self.velocity += gain[1] * innovation;
+
+        let measured_row = self.covariance[index];
+        for (row, g) in self.covariance.iter_mut().zip(gain.iter()) {
+            for (p, m) in row.iter_mut().zip(measured_row.iter()) {
+                *p -= g * m;
+            }
+        }
+    }
+}
+
+/// Position and velocity in the local north-east-down frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationEstimate {
+    /// North, east and down coordinates (m) relative to `origin`.
+    pub position: [f64; 3],
+    /// North, east and down velocity (m/s).
+    pub velocity: [f64; 3],
+    /// Standard deviation of each position coordinate (m).
+    pub position_std: [f64; 3],
+    /// Origin of the local frame, i.e. the first GNSS fix.
+    pub origin: GeodeticPosition,
+    pub instant: Instant,
+}
+
+/// Position and velocity estimator fusing acceleration, GNSS fixes and barometric altitude in a
+/// local north-east-down frame, with one Kalman filter per axis. The first GNSS fix sets the
+/// origin of the frame, inputs being ignored until then.
+pub struct NavigationEstimator {
+    frame: Option<LocalFrame>,
+    axes: [AxisFilter; 3],
+    /// Difference between altitude above origin and barometric altitude.
+    barometric_offset: Option<f64>,
+    last_acceleration_instant: Option<Instant>,
+    last_instant: Option<Instant>,
+}
+
+impl NavigationEstimator {
+    /// Standard deviation of acceleration samples (m/s²), including attitude errors.
+    const ACCELERATION_STD: f64 = 0.5;
+    /// Standard deviation of barometric altitude (m).
+    const BAROMETRIC_ALTITUDE_STD: f64 = 0.5;
+
+    pub fn new() -> Self {
+        NavigationEstimator {
+            frame: None,
+            axes: [
+                AxisFilter::new(0., 0., 0., 0.),
+                AxisFilter::new(0., 0., 0., 0.),
+                AxisFilter::new(0., 0., 0., 0.),
+            ],
+            barometric_offset: None,
+            last_acceleration_instant: None,
+            last_instant: None,
+        }
+    }
+
+    /// Integrates an acceleration sample (m/s²) expressed in the north-east-down frame, gravity
+    /// excluded.
+    pub fn update_acceleration(&mut self, acceleration: [f64; 3], instant: Instant) {
+        if self.frame.is_none() {
+            return;
+        }
+
+        if let Some(last_instant) = self.last_acceleration_instant {
+            if instant > last_instant {
+                let dt = (instant - last_instant).as_secs_f64();
+                let variance = Self::ACCELERATION_STD * Self::ACCELERATION_STD;
+
+                for (axis, &acceleration) in self.axes.iter_mut().zip(acceleration.iter()) {
+                    axis.predict(acceleration, dt, variance);
+                }
+
+                self.last_instant = Some(instant);
+            }
+        }
+
+        self.last_acceleration_instant = Some(instant);
+    }
+
+    /// Corrects the estimate with a GNSS fix, given its north-east-down velocity (m/s) and its
+    /// horizontal, vertical (m) and speed (m/s) accuracies. The first fix sets the origin.
+    pub fn update_gnss(&mut self,
+                       position: GeodeticPosition,
+                       velocity: [f64; 3],
+                       horizontal_accuracy: f64,
+                       vertical_accuracy: f64,
+                       speed_accuracy: f64,
+                       instant: Instant) {
+        let position_variances = [
+            horizontal_accuracy * horizontal_accuracy,
+            horizontal_accuracy * horizontal_accuracy,
+            vertical_accuracy * vertical_accuracy,
+        ];
+        let velocity_variance = speed_accuracy * speed_accuracy;
+
+        match &self.frame {
+            Some(frame) => {
+                let ned = frame.to_ned(&position);
+
+                for (i, axis) in self.axes.iter_mut().enumerate() {
+                    axis.correct(0, ned[i], position_variances[i]);
+                    axis.correct(1, velocity[i], velocity_variance);
+                }
+            }
+            None => {
+                self.frame = Some(LocalFrame::new(position));
+
+                for (i, axis) in self.axes.iter_mut().enumerate() {
+                    *axis = AxisFilter::new(0., position_variances[i], velocity[i], velocity_variance);
+                }
+            }
+        }
+
+        self.last_instant = Some(self.last_instant.map_or(instant, |last| last.max(instant)));
+    }
+
+    /// Corrects the down coordinate with a barometric altitude (m), relative to any reference.
+    /// The offset between this reference and the origin is set by the first sample.
+    pub fn update_barometric_altitude(&mut self, altitude: f64, instant: Instant) {
+        if self.frame.is_none() {
+            return;
+        }
+
+        let down = &mut self.axes[2];
+        let offset = *self.barometric_offset.get_or_insert(-down.position - altitude);
+
+        down.correct(0, -(altitude + offset),
+                     Self::BAROMETRIC_ALTITUDE_STD * Self::BAROMETRIC_ALTITUDE_STD);
+
+        self.last_instant = Some(self.last_instant.map_or(instant, |last| last.max(instant)));
+    }
+
+    /// Current estimate, available once the origin is set.
+    pub fn estimate(&self) -> Option<NavigationEstimate> {
+        let frame = self.frame.as_ref()?;
+
+        let mut estimate = NavigationEstimate {
+            position: [0.; 3],
+            velocity: [0.; 3],
+            position_std: [0.; 3],
+            origin: *frame.origin(),
+            instant: self.last_instant?,
+        };
+
+        for (i, axis) in self.axes.iter().enumerate() {
+            estimate.position[i] = axis.position;
+            estimate.velocity[i] = axis.velocity;
+            estimate.position_std[i] = axis.covariance[0][0].sqrt();
+        }
+
+        Some(estimate)
+    }
+
+    /// Geodetic position of the current estimate, available once the origin is set.
+    pub fn geodetic_position(&self) -> Option<GeodeticPosition> {
+        let frame = self.frame.as_ref()?;
+
+        Some(frame.to_geodetic([self.axes[0].position, self.axes[1].position, self.axes[2].position]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::estimator::NavigationEstimator;
+    use crate::geodetic::{GeodeticPosition, LocalFrame};
+    use std::time::{Duration, Instant};
+
+    const ORIGIN: GeodeticPosition = GeodeticPosition {
+        latitude: 48.713202,
+        longitude: 2.200641,
+        altitude: 160.,
+    };
+
+    #[test]
+    fn no_origin_test() {
+        let mut estimator = NavigationEstimator::new();
+        estimator.update_acceleration([1., 0., 0.], Instant::now());
+        estimator.update_barometric_altitude(10., Instant::now());
+
+        assert!(estimator.estimate().is_none());
+    }
+
+    #[test]
+    fn constant_velocity_test() {
+        let frame = LocalFrame::new(ORIGIN);
+        let velocity = [2., -1., -0.5];
+
+        let mut estimator = NavigationEstimator::new();
+        let start = Instant::now();
+
+        // GNSS at 5 Hz, acceleration at 500 Hz, barometer at 50 Hz
+        for i in 0..5000 {
+            let time = i as f64 * 0.002;
+            let instant = start + Duration::from_secs_f64(time);
+            let ned = [velocity[0] * time, velocity[1] * time, velocity[2] * time];
+
+            if i % 100 == 0 {
+                estimator.update_gnss(frame.to_geodetic(ned), velocity, 2., 3., 0.2, instant);
+            }
+
+            if i % 10 == 0 {
+                estimator.update_barometric_altitude(42. - ned[2], instant);
+            }
+
+            estimator.update_acceleration([0., 0., 0.], instant);
+        }
+
+        let estimate = estimator.estimate().unwrap();
+        let time = 4999. * 0.002;
+
+        for (i, &v) in velocity.iter().enumerate() {
+            assert_approx_eq!(estimate.position[i], v * time, 0.05);
+            assert_approx_eq!(estimate.velocity[i], v, 0.05);
+            assert!(estimate.position_std[i] < 2.);
+        }
+
+        // The first fix went through the local frame
+        assert_approx_eq!(estimate.origin.latitude, ORIGIN.latitude, 1e-9);
+        assert_approx_eq!(estimate.origin.longitude, ORIGIN.longitude, 1e-9);
+        assert_approx_eq!(estimate.origin.altitude, ORIGIN.altitude, 1e-6);
+    }
+}
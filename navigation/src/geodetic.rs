@@ -0,0 +1,156 @@
+/// WGS84 semi-major axis (m).
+const WGS84_A: f64 = 6_378_137.;
+/// WGS84 flattening.
+const WGS84_F: f64 = 1. / 298.257_223_563;
+/// WGS84 first eccentricity squared.
+const WGS84_E2: f64 = WGS84_F * (2. - WGS84_F);
+
+/// Position on the WGS84 ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeodeticPosition {
+    /// Degrees, positive northwards.
+    pub latitude: f64,
+    /// Degrees, positive eastwards.
+    pub longitude: f64,
+    /// Height above the ellipsoid (m).
+    pub altitude: f64,
+}
+
+impl GeodeticPosition {
+    /// Earth-centered, earth-fixed coordinates (m).
+    pub fn to_ecef(&self) -> [f64; 3] {
+        let (sin_lat, cos_lat) = self.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.to_radians().sin_cos();
+
+        let n = WGS84_A / (1. - WGS84_E2 * sin_lat * sin_lat).sqrt();
+
+        [
+            (n + self.altitude) * cos_lat * cos_lon,
+            (n + self.altitude) * cos_lat * sin_lon,
+            (n * (1. - WGS84_E2) + self.altitude) * sin_lat,
+        ]
+    }
+
+    /// Converts earth-centered, earth-fixed coordinates (m) iteratively. Not defined at the poles.
+    pub fn from_ecef(ecef: [f64; 3]) -> Self {
+        let [x, y, z] = ecef;
+        let p = x.hypot(y);
+
+        let mut latitude = z.atan2(p * (1. - WGS84_E2));
+        let mut altitude = 0.;
+
+        for _ in 0..5 {
+            let sin_lat = latitude.sin();
+            let n = WGS84_A / (1. - WGS84_E2 * sin_lat * sin_lat).sqrt();
+
+            altitude = p / latitude.cos() - n;
+            latitude = z.atan2(p * (1. - WGS84_E2 * n / (n + altitude)));
+        }
+
+        GeodeticPosition {
+            latitude: latitude.to_degrees(),
+            longitude: y.atan2(x).to_degrees(),
+            altitude,
+        }
+    }
+}
+
+/// Local north-east-down frame, tangent to the ellipsoid at its origin.
+#[derive(Debug, Clone)]
+pub struct LocalFrame {
+    origin: GeodeticPosition,
+    origin_ecef: [f64; 3],
+    /// Rotation from earth-centered, earth-fixed axes to north-east-down axes, row-major.
+    rotation: [[f64; 3]; 3],
+}
+
+impl LocalFrame {
+    pub fn new(origin: GeodeticPosition) -> Self {
+        let (sin_lat, cos_lat) = origin.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = origin.longitude.to_radians().sin_cos();
+
+        LocalFrame {
+            origin,
+            origin_ecef: origin.to_ecef(),
+            rotation: [
+                [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat],
+                [-sin_lon, cos_lon, 0.],
+                [-cos_lat * cos_lon, -cos_lat * sin_lon, -sin_lat],
+            ],
+        }
+    }
+
+    pub fn origin(&self) -> &GeodeticPosition {
+        &self.origin
+    }
+
+    /// North, east and down coordinates (m) of a position.
+    pub fn to_ned(&self, position: &GeodeticPosition) -> [f64; 3] {
+        let ecef = position.to_ecef();
+        let delta = [
+            ecef[0] - self.origin_ecef[0],
+            ecef[1] - self.origin_ecef[1],
+            ecef[2] - self.origin_ecef[2],
+        ];
+
+        let mut ned = [0.; 3];
+        for (i, row) in self.rotation.iter().enumerate() {
+            ned[i] = row[0] * delta[0] + row[1] * delta[1] + row[2] * delta[2];
+        }
+
+        ned
+    }
+
+    /// Position of north, east and down coordinates (m).
+    pub fn to_geodetic(&self, ned: [f64; 3]) -> GeodeticPosition {
+        let mut ecef = self.origin_ecef;
+        for (i, row) in self.rotation.iter().enumerate() {
+            for (coordinate, r) in ecef.iter_mut().zip(row.iter()) {
+                *coordinate += r * ned[i];
+            }
+        }
+
+        GeodeticPosition::from_ecef(ecef)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geodetic::{GeodeticPosition, LocalFrame};
+
+    // Telecom Paris
+    const ORIGIN: GeodeticPosition = GeodeticPosition {
+        latitude: 48.713202,
+        longitude: 2.200641,
+        altitude: 160.,
+    };
+
+    #[test]
+    fn ecef_round_trip_test() {
+        let position = GeodeticPosition::from_ecef(ORIGIN.to_ecef());
+
+        assert_approx_eq!(position.latitude, ORIGIN.latitude, 1e-9);
+        assert_approx_eq!(position.longitude, ORIGIN.longitude, 1e-9);
+        assert_approx_eq!(position.altitude, ORIGIN.altitude, 1e-4);
+    }
+
+    #[test]
+    fn ned_test() {
+        let frame = LocalFrame::new(ORIGIN);
+
+        // About 111 m per thousandth of a degree of latitude
+        let north = frame.to_ned(&GeodeticPosition { latitude: ORIGIN.latitude + 0.001, ..ORIGIN });
+        assert_approx_eq!(north[0], 111.2, 0.1);
+        assert_approx_eq!(north[1], 0., 1e-6);
+        assert_approx_eq!(north[2], 0., 1e-2);
+
+        let up = frame.to_ned(&GeodeticPosition { altitude: ORIGIN.altitude + 10., ..ORIGIN });
+        assert_approx_eq!(up[2], -10., 1e-6);
+
+        let position = frame.to_geodetic([120., -35., -12.]);
+        let ned = frame.to_ned(&position);
+        assert_approx_eq!(ned[0], 120., 1e-4);
+        assert_approx_eq!(ned[1], -35., 1e-4);
+        assert_approx_eq!(ned[2], -12., 1e-4);
+    }
+}
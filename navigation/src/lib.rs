@@ -2,6 +2,12 @@
 #[macro_use]
 extern crate assert_approx_eq;
 
+mod estimator;
+mod geodetic;
+
+pub use estimator::{NavigationEstimate, NavigationEstimator};
+pub use geodetic::{GeodeticPosition, LocalFrame};
+
 use std::f32::consts::{PI, TAU};
 
 /// Functions use international system units unless:
@@ -3,7 +3,7 @@
 extern crate assert_approx_eq;
 
 use std::{fmt::Display, time::Instant};
-use dsp::{ScalarAlphaBeta};
+use dsp::{Filter, FilterChain};
 use nalgebra::{RealField};
 
 pub struct Pid<N: RealField> {
@@ -12,11 +12,12 @@ pub struct Pid<N: RealField> {
 	target: N,
 	last_input: Option<(N, Instant)>,
 	last_output: Option<N>,
+	last_terms: Option<(N, N, N)>,
 	limits: Option<(N, N)>,
-	d_term_filter: ScalarAlphaBeta<N>,
+	d_term_filter: FilterChain<N, N>,
 }
 
-impl<N: RealField + From<f64> + Display> Pid<N> {
+impl<N: RealField + Display> Pid<N> {
 	pub fn new(k: (N, N, N), target: N, limits: Option<(N, N)>) -> Self {
 		Self {
 			k,
@@ -24,11 +25,18 @@ impl<N: RealField + From<f64> + Display> Pid<N> {
 			target,
 			last_input: None,
 			last_output: None,
+			last_terms: None,
 			limits,
-			d_term_filter: ScalarAlphaBeta::new(N::from(0.008), N::from(0.0005)),
+			d_term_filter: FilterChain::new(),
 		}
 	}
 
+	/// Sets the filters applied to the derivative term. By default, it is not filtered.
+	pub fn with_d_term_filter(mut self, d_term_filter: FilterChain<N, N>) -> Self {
+		self.d_term_filter = d_term_filter;
+		self
+	}
+
 	pub fn setpoint(&self) -> N {
 		self.target
 	}
@@ -40,9 +48,21 @@ impl<N: RealField + From<f64> + Display> Pid<N> {
 		}
 	}
 
+	/// Proportional, integral and derivative terms of the latest output, before limits.
+	pub fn terms(&self) -> Option<(N, N, N)> {
+		self.last_terms
+	}
+
+	/// Changes the setpoint without resetting the integral term, for setpoints that move
+	/// continuously, e.g. when following sticks.
+	pub fn track_setpoint(&mut self, target: N) {
+		self.target = target;
+	}
+
 	pub fn reset(&mut self) {
 		self.error_integral = N::zero();
 		self.last_output = None;
+		self.last_terms = None;
 		self.last_input = None;
 	}
 
@@ -53,13 +73,13 @@ impl<N: RealField + From<f64> + Display> Pid<N> {
 
 		let (i, d) = {
 			if let Some((last_input, last_instant)) = self.last_input {
-				let dt: N = (input_read_instant - last_instant).as_secs_f64().into();
+				let dt: N = nalgebra::convert((input_read_instant - last_instant).as_secs_f64());
 
 				self.error_integral = self.error_integral + self.k.1 * dt * error;
 
 				let filtered_d_term = self
 					.d_term_filter
-					.update(-self.k.2 * (last_input - input), dt);
+					.filter(-self.k.2 * (input - last_input), dt);
 
 				(self.error_integral, filtered_d_term / dt) // Note: d(err)/dt = - d(input)/dt
 			} else {
@@ -77,6 +97,7 @@ impl<N: RealField + From<f64> + Display> Pid<N> {
 		};
 
 		self.last_output = Some(output);
+		self.last_terms = Some((p, i, d));
 		self.last_input = Some((input, input_read_instant));
 
 		output
@@ -100,6 +121,7 @@ impl<N: RealField + From<f64> + Display> Pid<N> {
 #[cfg(test)]
 mod tests {
 	use crate::Pid;
+	use dsp::{FilterChain, ScalarAlphaBeta};
 	use std::time::{Duration, Instant};
 
 	const D_100_MS: Duration = Duration::from_millis(100);
@@ -150,6 +172,36 @@ mod tests {
         );
 	}
 
+	#[test]
+	fn terms_test() {
+		let initial_instant = Instant::now();
+		let mut pid = Pid::<f32>::new((2., 1., 0.5), 1., Some((-1., 1.)));
+		assert_eq!(pid.terms(), None);
+
+		pid.estimate(0., initial_instant);
+		pid.estimate(0.5, initial_instant + D_100_MS);
+
+		let (p, i, d) = pid.terms().unwrap();
+		assert_approx_eq!(p, 1.);
+		assert_approx_eq!(i, 0.05);
+		assert_approx_eq!(d, -2.5);
+
+		pid.reset();
+		assert_eq!(pid.terms(), None);
+	}
+
+	#[test]
+	fn track_setpoint_test() {
+		let initial_instant = Instant::now();
+		let mut pid = Pid::<f32>::new((0., 1., 0.), 1., None);
+
+		pid.estimate(0., initial_instant);
+		pid.estimate(0., initial_instant + D_100_MS);
+		pid.track_setpoint(2.);
+
+		assert_approx_eq!(pid.estimate(0., initial_instant + 2 * D_100_MS), 0.1 + 0.2);
+	}
+
 	#[test]
 	fn kd_test() {
 		let target = 453.246;
@@ -207,5 +259,24 @@ mod tests {
 		assert_approx_eq!(pid.estimate(-0.143, initial_instant + D_100_MS), 0.5);
 		assert_approx_eq!(pid.estimate(-0.248, initial_instant + D_1000_MS), 0.04);
 	}
-}
 
+	#[test]
+	fn d_term_filter_test() {
+		let target = 453.246;
+		let initial_instant = Instant::now();
+
+		let d = 12.34;
+		let alpha = 0.5;
+		let mut d_term_filter = FilterChain::new();
+		d_term_filter.push(Box::new(ScalarAlphaBeta::new(alpha, 0.)));
+
+		let mut pid = Pid::<f32>::new((0., 0., d), target, None)
+			.with_d_term_filter(d_term_filter);
+
+		assert_approx_eq!(pid.estimate(423., initial_instant), 0.);
+		assert_approx_eq!(
+            pid.estimate(421., initial_instant + D_100_MS),
+            alpha * -d * (421. - 423.) / 0.1
+        );
+	}
+}
@@ -0,0 +1,87 @@
+use nalgebra::{DMatrix, Matrix3, Vector3};
+
+const G: f64 = 9.80665;
+
+/// Orientations of the six-position calibration, with the specific force expected in each one
+/// (body frame x forward, y left, z up).
+pub const POSITIONS: [(&'static str, [f64; 3]); 6] = [
+	("level", [0., 0., G]),
+	("upside down", [0., 0., -G]),
+	("nose up", [G, 0., 0.]),
+	("nose down", [-G, 0., 0.]),
+	("right side down", [0., G, 0.]),
+	("left side down", [0., -G, 0.]),
+];
+
+/// Accelerometer bias, and matrix correcting scale factors and misalignment, such that calibrated
+/// samples are `matrix * (raw - offset)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccCalibration {
+	pub offset: Vector3<f64>,
+	pub matrix: Matrix3<f64>,
+}
+
+/// Solves `expected = matrix * (measured - offset)` by least squares, from averaged samples
+/// measured in known orientations such as `POSITIONS`.
+pub fn solve(measurements: &[(Vector3<f64>, Vector3<f64>)]) -> anyhow::Result<AccCalibration> {
+	if measurements.len() < 4 {
+		return Err(anyhow!("Not enough orientations: {} (expected at least 4)", measurements.len()));
+	}
+
+	// Rewritten as expected = matrix * measured + c, with c = -matrix * offset
+	let design = DMatrix::from_fn(measurements.len(), 4, |i, j| {
+		if j < 3 { measurements[i].0[j] } else { 1. }
+	});
+	let expected = DMatrix::from_fn(measurements.len(), 3, |i, j| measurements[i].1[j]);
+
+	let solution = design
+		.svd(true, true)
+		.solve(&expected, 1e-12)
+		.map_err(|e| anyhow!("Failed to solve accelerometer calibration: {}", e))?;
+
+	let matrix = Matrix3::from_fn(|i, j| solution[(j, i)]);
+	let c = Vector3::new(solution[(3, 0)], solution[(3, 1)], solution[(3, 2)]);
+
+	let matrix_inverse = matrix
+		.try_inverse()
+		.ok_or_else(|| anyhow!("Degenerate accelerometer calibration"))?;
+
+	Ok(AccCalibration {
+		offset: -(matrix_inverse * c),
+		matrix,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::acc_calibration::{solve, POSITIONS};
+	use nalgebra::{Matrix3, Vector3};
+
+	#[test]
+	fn six_position_test() {
+		let offset = Vector3::new(0.12, -0.3, 0.25);
+		let matrix = Matrix3::new(1.02, 0.01, -0.02,
+								  0.005, 0.97, 0.015,
+								  -0.01, 0.02, 1.04);
+		let matrix_inverse = matrix.try_inverse().unwrap();
+
+		let measurements = POSITIONS
+			.iter()
+			.map(|(_, expected)| {
+				let expected = Vector3::from_column_slice(expected);
+				(matrix_inverse * expected + offset, expected)
+			})
+			.collect::<Vec<_>>();
+
+		let calibration = solve(&measurements).unwrap();
+
+		assert!((calibration.offset - offset).norm() < 1e-9);
+		assert!((calibration.matrix - matrix).norm() < 1e-9);
+	}
+
+	#[test]
+	fn not_enough_positions_test() {
+		let measurement = (Vector3::new(0., 0., 9.8), Vector3::new(0., 0., 9.8));
+		assert!(solve(&[measurement.clone(), measurement]).is_err());
+	}
+}
@@ -0,0 +1,42 @@
+use ahrs::{Ahrs, Ekf, Madgwick, Mahony};
+use serde::{Deserialize, Serialize};
+
+/// Attitude and heading reference system fed by IMU samples.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AhrsConfig {
+	Madgwick {
+		beta: f64,
+	},
+	Mahony {
+		k_p: f64,
+		k_i: f64,
+	},
+	/// Extended Kalman filter, also estimating gyroscope bias.
+	Ekf {
+		/// Gyroscope noise density (rad/s/√Hz).
+		gyr_noise: f64,
+		/// Gyroscope bias random walk (rad/s²/√Hz).
+		gyr_bias_noise: f64,
+		/// Standard deviation of normalized accelerometer measurements.
+		acc_noise: f64,
+		/// Standard deviation of normalized magnetometer measurements.
+		mag_noise: f64,
+	},
+}
+
+impl Default for AhrsConfig {
+	fn default() -> Self {
+		AhrsConfig::Madgwick { beta: 0.11 }
+	}
+}
+
+pub fn ahrs(config: &AhrsConfig) -> Box<dyn Ahrs<f64>> {
+	match *config {
+		AhrsConfig::Madgwick { beta } => Box::new(Madgwick::new(beta)),
+		AhrsConfig::Mahony { k_p, k_i } => Box::new(Mahony::new(k_p, k_i)),
+		AhrsConfig::Ekf { gyr_noise, gyr_bias_noise, acc_noise, mag_noise } => {
+			Box::new(Ekf::new(gyr_noise, gyr_bias_noise, acc_noise, mag_noise))
+		}
+	}
+}
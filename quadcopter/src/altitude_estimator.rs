@@ -0,0 +1,169 @@
+use std::time::Instant;
+
+use nalgebra::{UnitQuaternion, Vector3};
+
+pub(crate) const G: f64 = 9.80665;
+
+/// Altitude above the first barometer sample, and vertical speed (m/s, positive upwards).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AltitudeEstimate {
+	pub altitude: f64,
+	pub vertical_speed: f64,
+	pub instant: Instant,
+}
+
+/// Complementary filter integrating vertical acceleration, corrected by barometric altitude as an
+/// alpha-beta filter.
+pub struct AltitudeEstimator {
+	reference_pressure: Option<f64>,
+	altitude: f64,
+	vertical_speed: f64,
+	last_acceleration_instant: Option<Instant>,
+	last_pressure_instant: Option<Instant>,
+}
+
+impl AltitudeEstimator {
+	/// Altitude and vertical speed gains of barometric corrections.
+	const ALPHA: f64 = 0.05;
+	const BETA: f64 = 0.005;
+
+	pub fn new() -> Self {
+		Self {
+			reference_pressure: None,
+			altitude: 0.,
+			vertical_speed: 0.,
+			last_acceleration_instant: None,
+			last_pressure_instant: None,
+		}
+	}
+
+	/// Altitude difference (m) between two pressures, using the international standard atmosphere.
+	pub fn barometric_altitude(pressure: f64, reference_pressure: f64) -> f64 {
+		44_330. * (1. - (pressure / reference_pressure).powf(1. / 5.255))
+	}
+
+	/// Pressure of the first barometer sample, from which altitude is measured.
+	pub fn reference_pressure(&self) -> Option<f64> {
+		self.reference_pressure
+	}
+
+	/// Integrates the vertical component of an accelerometer sample, given the rotation from body
+	/// frame to world frame. Samples are ignored until the first barometer sample.
+	pub fn update_acceleration(&mut self,
+							   orientation: &UnitQuaternion<f64>,
+							   acc: &Vector3<f64>,
+							   instant: Instant) {
+		if self.reference_pressure.is_none() {
+			return;
+		}
+
+		if let Some(last_instant) = self.last_acceleration_instant {
+			if instant > last_instant {
+				let dt = (instant - last_instant).as_secs_f64();
+				let vertical_acceleration = orientation.transform_vector(acc).z - G;
+
+				self.altitude += self.vertical_speed * dt + 0.5 * vertical_acceleration * dt * dt;
+				self.vertical_speed += vertical_acceleration * dt;
+			}
+		}
+
+		self.last_acceleration_instant = Some(instant);
+	}
+
+	/// Corrects the estimate with a barometer sample. The first sample sets the reference altitude.
+	pub fn update_pressure(&mut self, pressure: f64, instant: Instant) {
+		let reference_pressure = *self.reference_pressure.get_or_insert(pressure);
+		let error = Self::barometric_altitude(pressure, reference_pressure) - self.altitude;
+
+		self.altitude += Self::ALPHA * error;
+
+		if let Some(last_instant) = self.last_pressure_instant {
+			if instant > last_instant {
+				self.vertical_speed += Self::BETA * error / (instant - last_instant).as_secs_f64();
+			}
+		}
+
+		self.last_pressure_instant = Some(instant);
+	}
+
+	pub fn estimate(&self) -> Option<AltitudeEstimate> {
+		let instant = match (self.last_acceleration_instant, self.last_pressure_instant) {
+			(Some(a), Some(b)) => a.max(b),
+			(None, Some(b)) => b,
+			_ => return None,
+		};
+
+		Some(AltitudeEstimate {
+			altitude: self.altitude,
+			vertical_speed: self.vertical_speed,
+			instant,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::altitude_estimator::{AltitudeEstimator, G};
+	use nalgebra::{UnitQuaternion, Vector3};
+	use std::time::{Duration, Instant};
+
+	const PRESSURE: f64 = 101_325.;
+
+	fn pressure(altitude: f64) -> f64 {
+		PRESSURE * (1. - altitude / 44_330.).powf(5.255)
+	}
+
+	#[test]
+	fn barometric_altitude_test() {
+		assert!((AltitudeEstimator::barometric_altitude(pressure(10.), PRESSURE) - 10.).abs() < 1e-6);
+		assert!(AltitudeEstimator::barometric_altitude(100_000., PRESSURE) > 100.);
+	}
+
+	#[test]
+	fn resting_test() {
+		let mut estimator = AltitudeEstimator::new();
+		assert!(estimator.estimate().is_none());
+
+		let start_instant = Instant::now();
+		let level = UnitQuaternion::identity();
+
+		for i in 0..1000 {
+			let instant = start_instant + Duration::from_millis(2 * i);
+			estimator.update_acceleration(&level, &Vector3::new(0., 0., G), instant);
+
+			if i % 10 == 0 {
+				estimator.update_pressure(PRESSURE, instant);
+			}
+		}
+
+		let estimate = estimator.estimate().unwrap();
+		assert!(estimate.altitude.abs() < 1e-6);
+		assert!(estimate.vertical_speed.abs() < 1e-6);
+	}
+
+	#[test]
+	fn climb_test() {
+		let mut estimator = AltitudeEstimator::new();
+		let start_instant = Instant::now();
+		let level = UnitQuaternion::identity();
+
+		// Constant 1 m/s climb after an initial 1 m/s² acceleration during one second
+		estimator.update_pressure(PRESSURE, start_instant);
+
+		for i in 1..=5000 {
+			let time = 0.002 * i as f64;
+			let instant = start_instant + Duration::from_millis(2 * i);
+			let (acc, altitude) = if time <= 1. { (1., time * time / 2.) } else { (0., time - 0.5) };
+
+			estimator.update_acceleration(&level, &Vector3::new(0., 0., G + acc), instant);
+
+			if i % 10 == 0 {
+				estimator.update_pressure(pressure(altitude), instant);
+			}
+		}
+
+		let estimate = estimator.estimate().unwrap();
+		assert!((estimate.altitude - 9.5).abs() < 0.05);
+		assert!((estimate.vertical_speed - 1.).abs() < 0.05);
+	}
+}
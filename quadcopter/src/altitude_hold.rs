@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use pid::Pid;
+
+use crate::altitude_estimator::AltitudeEstimate;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct AltitudeHoldConfig {
+	/// Gains of the vertical speed PID, whose output is added to the hover throttle.
+	pub pid_values: (f64, f64, f64),
+	/// Throttle for which thrust roughly balances weight.
+	pub hover_throttle: f64,
+	/// Climb rate (m/s) commanded at full throttle stick deflection.
+	pub max_climb_rate: f64,
+	/// Climb rate (m/s) commanded per meter of error on the latched altitude.
+	pub altitude_gain: f64,
+	/// Throttle stick deflection from center, within (0., 1.), below which altitude is held.
+	pub deadband: f64,
+}
+
+impl Default for AltitudeHoldConfig {
+	fn default() -> Self {
+		AltitudeHoldConfig {
+			pid_values: (0.15, 0.05, 0.),
+			hover_throttle: 0.45,
+			max_climb_rate: 2.,
+			altitude_gain: 1.,
+			deadband: 0.1,
+		}
+	}
+}
+
+/// Throttle controller in which the throttle stick commands climb rate. Altitude is latched when
+/// the stick enters the deadband around its center, and held until it leaves it.
+pub struct AltitudeHold {
+	config: AltitudeHoldConfig,
+	pid: Pid<f64>,
+	target_altitude: Option<f64>,
+	engaged: bool,
+}
+
+impl AltitudeHold {
+	pub fn new(config: AltitudeHoldConfig) -> Self {
+		Self {
+			config,
+			pid: Pid::new(config.pid_values, 0., Some((-1., 1.))),
+			target_altitude: None,
+			engaged: false,
+		}
+	}
+
+	/// Target climb rate for a throttle stick position within (0., 1.).
+	fn target_climb_rate(&mut self, throttle_stick: f64, altitude: f64) -> f64 {
+		let deflection = (throttle_stick - 0.5) * 2.;
+		let deadband = self.config.deadband;
+
+		if deflection.abs() <= deadband {
+			let target_altitude = *self.target_altitude.get_or_insert(altitude);
+
+			((target_altitude - altitude) * self.config.altitude_gain)
+				.max(-self.config.max_climb_rate)
+				.min(self.config.max_climb_rate)
+		} else {
+			self.target_altitude = None;
+
+			(deflection - deadband * deflection.signum()) / (1. - deadband) * self.config.max_climb_rate
+		}
+	}
+
+	pub fn throttle(&mut self, throttle_stick: f64, estimate: &AltitudeEstimate) -> f64 {
+		if !self.engaged {
+			info!("Altitude hold engaged at {:.2} m", estimate.altitude);
+			self.engaged = true;
+		}
+
+		let target_climb_rate = self.target_climb_rate(throttle_stick, estimate.altitude);
+
+		self.climb_rate_throttle(target_climb_rate, estimate)
+	}
+
+	/// Throttle tracking a climb rate (m/s) commanded by the autopilot rather than by the stick.
+	pub fn climb_rate_throttle(&mut self, target_climb_rate: f64, estimate: &AltitudeEstimate) -> f64 {
+		debug!(target: "altitude_hold", "{} {} {}",
+			   estimate.altitude,
+			   estimate.vertical_speed,
+			   target_climb_rate);
+
+		// The setpoint is kept at zero and the PID is fed with the climb rate error, as changing
+		// the setpoint would reset the integral term on every control loop.
+		let output = self.pid.estimate(estimate.vertical_speed - target_climb_rate, estimate.instant);
+
+		(self.config.hover_throttle + output).max(0.).min(1.)
+	}
+
+	/// Releases the latched altitude and PID state, when the pilot takes over the throttle.
+	pub fn reset(&mut self) {
+		if self.engaged {
+			info!("Altitude hold disengaged");
+			self.engaged = false;
+		}
+
+		self.target_altitude = None;
+		self.pid.reset();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::altitude_estimator::AltitudeEstimate;
+	use crate::altitude_hold::{AltitudeHold, AltitudeHoldConfig};
+	use std::time::{Duration, Instant};
+
+	#[test]
+	fn stick_climb_rate_test() {
+		let config = AltitudeHoldConfig::default();
+		let mut altitude_hold = AltitudeHold::new(config);
+
+		assert_eq!(altitude_hold.target_climb_rate(1., 5.), config.max_climb_rate);
+		assert_eq!(altitude_hold.target_climb_rate(0., 5.), -config.max_climb_rate);
+		assert_eq!(altitude_hold.target_climb_rate(0.5 + config.deadband / 2., 5.), 0.);
+	}
+
+	#[test]
+	fn altitude_latch_test() {
+		let config = AltitudeHoldConfig::default();
+		let mut altitude_hold = AltitudeHold::new(config);
+
+		// Altitude is latched when the stick enters the deadband, then errors command climb rate
+		assert_eq!(altitude_hold.target_climb_rate(0.5, 5.), 0.);
+		assert!(altitude_hold.target_climb_rate(0.5, 4.5) > 0.);
+		assert!(altitude_hold.target_climb_rate(0.5, 5.5) < 0.);
+
+		// Moving the stick releases the latched altitude
+		altitude_hold.target_climb_rate(1., 5.5);
+		assert_eq!(altitude_hold.target_climb_rate(0.5, 8.), 0.);
+	}
+
+	#[test]
+	fn hover_throttle_test() {
+		let config = AltitudeHoldConfig::default();
+		let mut altitude_hold = AltitudeHold::new(config);
+
+		let instant = Instant::now();
+		let estimate = AltitudeEstimate { altitude: 5., vertical_speed: 0., instant };
+		assert_eq!(altitude_hold.throttle(0.5, &estimate), config.hover_throttle);
+
+		let estimate = AltitudeEstimate { instant: instant + Duration::from_millis(2), ..estimate };
+		assert!(altitude_hold.throttle(1., &estimate) > config.hover_throttle);
+	}
+}
@@ -0,0 +1,293 @@
+use crossbeam_channel::Sender;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::altitude_estimator::{AltitudeEstimate, G};
+use crate::roll_pitch_yaw::RollPitchYaw;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ArmingConfig {
+	/// Throttle, within (0., 1.), at or below which throttle is at minimum. The throttle stick
+	/// must be at minimum when arming, and motors are disarmed once throttle stays at minimum.
+	pub min_throttle: f64,
+	/// Time (s) at minimum throttle, while still, after which motors are disarmed.
+	pub idle_disarm_time: f64,
+	/// Angular rate (rad/s), deviation of acceleration from gravity (m/s²) and, when altitude is
+	/// estimated, vertical speed (m/s) below which the aircraft is still on the ground.
+	pub idle_max_rate: f64,
+	pub idle_max_acceleration_error: f64,
+	pub idle_max_vertical_speed: f64,
+	/// Tilt (rad) beyond which the aircraft may have crashed or flipped.
+	pub crash_tilt: f64,
+	/// Angular rate (rad/s) which, while tilted beyond the crash tilt, reveals a crash. Ignored in
+	/// acro mode, where flips are intended.
+	pub crash_rate: f64,
+	/// Acceleration (m/s²) which, while tilted beyond the crash tilt, reveals an impact.
+	pub crash_acceleration: f64,
+	/// Time (s) tilted beyond the crash tilt after which the aircraft has flipped. Ignored in acro
+	/// mode, where sustained inverted flight is intended.
+	pub crash_time: f64,
+}
+
+impl Default for ArmingConfig {
+	fn default() -> Self {
+		ArmingConfig {
+			min_throttle: 0.05,
+			idle_disarm_time: 15.,
+			idle_max_rate: 0.2,
+			idle_max_acceleration_error: 1.,
+			idle_max_vertical_speed: 0.2,
+			crash_tilt: 1.2,
+			crash_rate: 6.,
+			crash_acceleration: 30.,
+			crash_time: 1.,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DisarmReason {
+	Idle,
+	Crash,
+	Flip,
+}
+
+/// Arming interlock: motors are armed by turning the arm switch on with throttle at minimum, and
+/// disarmed automatically when idle on the ground or after a crash. The arm switch must then be
+/// turned off before arming again.
+pub struct Arming {
+	config: ArmingConfig,
+	/// Whether the arm switch must be turned off before arming.
+	locked: bool,
+	switch_seen: bool,
+	/// Notified once the arm switch has been seen off.
+	switch_off_sender: Option<Sender<()>>,
+	/// Instants throttle reached minimum, and tilt went beyond the crash tilt.
+	idle_since: Option<Instant>,
+	tilted_since: Option<Instant>,
+}
+
+impl Arming {
+	pub fn new(config: ArmingConfig) -> Self {
+		Self {
+			config,
+			locked: false,
+			switch_seen: false,
+			switch_off_sender: None,
+			idle_since: None,
+			tilted_since: None,
+		}
+	}
+
+	/// Notifies `sender` once the arm switch has been seen off, i.e. once turning it on arms,
+	/// e.g. for simulated RC transmitters.
+	pub fn with_switch_off_notification(mut self, sender: Sender<()>) -> Self {
+		self.switch_off_sender = Some(sender);
+		self
+	}
+
+	/// Requires the arm switch to be turned off before arming again.
+	pub fn lock(&mut self, reason: &str) {
+		warn!("Arming locked: {}, turn the arm switch off to unlock", reason);
+		self.locked = true;
+	}
+
+	/// Whether the arm switch and throttle stick, within (0., 1.), allow arming.
+	pub fn arm_allowed(&mut self, switch_on: bool, throttle_stick: f64) -> bool {
+		if !self.switch_seen {
+			self.switch_seen = true;
+
+			if switch_on {
+				self.lock("arm switch on at startup");
+			}
+		}
+
+		match (switch_on, self.locked) {
+			(false, _) => {
+				self.locked = false;
+
+				if let Some(sender) = self.switch_off_sender.take() {
+					let _ = sender.send(());
+				}
+
+				false
+			}
+			(true, true) => false,
+			(true, false) if throttle_stick > self.config.min_throttle => {
+				self.lock("throttle is not at minimum");
+				false
+			}
+			(true, false) => true,
+		}
+	}
+
+	/// Reason to disarm while armed, if any, given the current attitude, body rates (rad/s),
+	/// acceleration (m/s²), altitude estimate and commanded throttle. The arming is locked once
+	/// disarmed.
+	pub fn disarm_reason(&mut self,
+						 orientation: &RollPitchYaw<f64>,
+						 gyr: &Vector3<f64>,
+						 acc: &Vector3<f64>,
+						 altitude: Option<&AltitudeEstimate>,
+						 throttle: f64,
+						 acro: bool,
+						 instant: Instant) -> Option<DisarmReason> {
+		let config = self.config;
+
+		// Angle between body and world vertical axes
+		let tilt = (orientation.roll.cos() * orientation.pitch.cos()).acos();
+		let tilted = tilt > config.crash_tilt;
+
+		// Descending at minimum throttle is not idle
+		let still = gyr.norm() <= config.idle_max_rate
+			&& (acc.norm() - G).abs() <= config.idle_max_acceleration_error
+			&& altitude.map_or(true, |altitude| altitude.vertical_speed.abs() <= config.idle_max_vertical_speed);
+
+		if throttle <= config.min_throttle && still {
+			self.idle_since.get_or_insert(instant);
+		} else {
+			self.idle_since = None;
+		}
+
+		// Inverted flight in acro mode is not timed, so that leaving acro mode is not a flip
+		if tilted && !acro {
+			self.tilted_since.get_or_insert(instant);
+		} else {
+			self.tilted_since = None;
+		}
+
+		let elapsed = |since: Option<Instant>, duration: f64| since
+			.map_or(false, |since| instant.saturating_duration_since(since) >= Duration::from_secs_f64(duration));
+
+		let reason = if tilted && !acro && gyr.norm() > config.crash_rate {
+			Some(DisarmReason::Crash)
+		} else if tilted && acc.norm() > config.crash_acceleration {
+			Some(DisarmReason::Crash)
+		} else if elapsed(self.tilted_since, config.crash_time) {
+			Some(DisarmReason::Flip)
+		} else if elapsed(self.idle_since, config.idle_disarm_time) {
+			Some(DisarmReason::Idle)
+		} else {
+			None
+		};
+
+		if let Some(reason) = reason {
+			match reason {
+				DisarmReason::Idle => info!("Disarmed after {} s still at minimum throttle", config.idle_disarm_time),
+				DisarmReason::Crash => warn!("Crash detected: tilt {:.2} rad, rate {:.1} rad/s, acceleration {:.1} m/s²",
+											 tilt, gyr.norm(), acc.norm()),
+				DisarmReason::Flip => warn!("Flip detected: tilt {:.2} rad for {} s", tilt, config.crash_time),
+			}
+
+			self.lock(&format!("disarmed automatically ({:?})", reason));
+		}
+
+		reason
+	}
+
+	/// Clears timers while disarmed.
+	pub fn reset(&mut self) {
+		self.idle_since = None;
+		self.tilted_since = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::altitude_estimator::{AltitudeEstimate, G};
+	use crate::arming::{Arming, ArmingConfig, DisarmReason};
+	use crate::roll_pitch_yaw::RollPitchYaw;
+	use nalgebra::Vector3;
+	use std::f64::consts::PI;
+	use std::time::{Duration, Instant};
+
+	const LEVEL: RollPitchYaw<f64> = RollPitchYaw { roll: 0., pitch: 0., yaw: 0. };
+	const INVERTED: RollPitchYaw<f64> = RollPitchYaw { roll: PI, pitch: 0., yaw: 0. };
+
+	#[test]
+	fn interlock_test() {
+		let mut arming = Arming::new(ArmingConfig::default());
+
+		// The switch must be turned off after startup
+		assert!(!arming.arm_allowed(true, 0.));
+		assert!(!arming.arm_allowed(false, 0.));
+		assert!(arming.arm_allowed(true, 0.));
+
+		// Arming with throttle up locks until the switch is turned off
+		assert!(!arming.arm_allowed(false, 0.5));
+		assert!(!arming.arm_allowed(true, 0.5));
+		assert!(!arming.arm_allowed(true, 0.));
+		assert!(!arming.arm_allowed(false, 0.));
+		assert!(arming.arm_allowed(true, 0.));
+	}
+
+	#[test]
+	fn switch_off_notification_test() {
+		let (sender, receiver) = crossbeam_channel::unbounded();
+		let mut arming = Arming::new(ArmingConfig::default()).with_switch_off_notification(sender);
+
+		assert!(!arming.arm_allowed(true, 0.));
+		assert!(receiver.try_recv().is_err());
+
+		assert!(!arming.arm_allowed(false, 0.));
+		assert!(receiver.try_recv().is_ok());
+		assert!(arming.arm_allowed(true, 0.));
+	}
+
+	#[test]
+	fn idle_test() {
+		let mut arming = Arming::new(ArmingConfig::default());
+		let instant = Instant::now();
+		let seconds = |seconds| instant + Duration::from_secs(seconds);
+		let zero = Vector3::zeros();
+		let gravity = Vector3::new(0., 0., G);
+		let on_ground = AltitudeEstimate { altitude: 0., vertical_speed: 0., instant };
+		let descending = AltitudeEstimate { altitude: 30., vertical_speed: -3., instant };
+
+		arming.arm_allowed(false, 0.);
+		assert!(arming.arm_allowed(true, 0.));
+
+		// Descending at minimum throttle does not disarm
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, Some(&descending), 0., false, instant), None);
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, Some(&descending), 0., false, seconds(20)), None);
+
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, Some(&on_ground), 0., false, seconds(20)), None);
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, Some(&on_ground), 0.5, false, seconds(30)), None);
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, None, 0., false, seconds(40)), None);
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &gravity, None, 0., false, seconds(60)),
+				   Some(DisarmReason::Idle));
+
+		// Disarming locks arming
+		assert!(!arming.arm_allowed(true, 0.));
+	}
+
+	#[test]
+	fn crash_test() {
+		let config = ArmingConfig::default();
+		let instant = Instant::now();
+		let zero = Vector3::zeros();
+		let spinning = Vector3::new(2. * config.crash_rate, 0., 0.);
+		let impact = Vector3::new(0., 0., 2. * config.crash_acceleration);
+
+		// Spinning while inverted is only a crash outside of acro mode
+		let mut arming = Arming::new(config);
+		assert_eq!(arming.disarm_reason(&INVERTED, &spinning, &zero, None, 0.5, true, instant), None);
+		assert_eq!(arming.disarm_reason(&INVERTED, &spinning, &zero, None, 0.5, false, instant),
+				   Some(DisarmReason::Crash));
+
+		// Impacts are only crashes while tilted
+		let mut arming = Arming::new(config);
+		assert_eq!(arming.disarm_reason(&LEVEL, &zero, &impact, None, 0.5, false, instant), None);
+		assert_eq!(arming.disarm_reason(&INVERTED, &zero, &impact, None, 0.5, true, instant),
+				   Some(DisarmReason::Crash));
+
+		// Staying inverted is only a flip outside of acro mode
+		let mut arming = Arming::new(config);
+		assert_eq!(arming.disarm_reason(&INVERTED, &zero, &zero, None, 0.5, true, instant), None);
+		assert_eq!(arming.disarm_reason(&INVERTED, &zero, &zero, None, 0.5, true, instant + Duration::from_secs(2)), None);
+		assert_eq!(arming.disarm_reason(&INVERTED, &zero, &zero, None, 0.5, false, instant + Duration::from_secs(2)), None);
+		assert_eq!(arming.disarm_reason(&INVERTED, &zero, &zero, None, 0.5, false, instant + Duration::from_secs(4)),
+				   Some(DisarmReason::Flip));
+	}
+}
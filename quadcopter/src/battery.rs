@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use autopilot::NavioAdcData;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct BatteryConfig {
+	/// Number of cells in series.
+	pub cell_count: u32,
+	/// Capacity (mAh).
+	pub capacity: f64,
+	/// Internal resistance (Ω) of the pack, compensating voltage sag under load.
+	pub internal_resistance: f64,
+	/// Resting cell voltage (V) of a full battery.
+	pub full_cell_voltage: f64,
+	/// Resting cell voltage (V) of an empty battery.
+	pub empty_cell_voltage: f64,
+	/// Remaining percentage below which a warning is issued and arming is refused.
+	pub warning_remaining: f64,
+	/// Remaining percentage below which the failsafe action is taken.
+	pub land_remaining: f64,
+	/// Remaining percentage below which the aircraft lands in place.
+	pub critical_remaining: f64,
+	/// Current (A) above which a warning is issued.
+	pub max_current: f64,
+}
+
+impl Default for BatteryConfig {
+	fn default() -> Self {
+		BatteryConfig {
+			cell_count: 3,
+			capacity: 5000.,
+			internal_resistance: 0.03,
+			full_cell_voltage: 4.2,
+			empty_cell_voltage: 3.3,
+			warning_remaining: 30.,
+			land_remaining: 15.,
+			critical_remaining: 5.,
+			max_current: 50.,
+		}
+	}
+}
+
+/// Stages of low battery actions, in increasing severity.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryState {
+	Nominal,
+	Warning,
+	Land,
+	Critical,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BatteryEstimate {
+	/// Measured voltage (V).
+	pub voltage: f64,
+	/// Measured current (A).
+	pub current: f64,
+	/// Voltage (V) compensated for sag and filtered, approximating the resting voltage.
+	pub resting_voltage: f64,
+	/// Consumed capacity (mAh).
+	pub consumed: f64,
+	/// Remaining capacity (%).
+	pub remaining: f64,
+	pub state: BatteryState,
+	pub instant: Instant,
+}
+
+/// External voltage (V) below which no battery is connected, e.g. when the board is powered over
+/// USB on the bench.
+const CONNECTED_VOLTAGE: f64 = 2.;
+/// Time constant (s) of the resting voltage low pass filter, so that current spikes do not
+/// trigger actions.
+const VOLTAGE_TIME_CONSTANT: f64 = 2.;
+
+/// Battery model integrating current into consumed capacity, and estimating the remaining
+/// capacity from both consumption and resting voltage.
+pub struct Battery {
+	config: BatteryConfig,
+	/// Remaining percentage estimated from the resting voltage when first connected.
+	initial_remaining: Option<f64>,
+	resting_voltage: f64,
+	consumed: f64,
+	/// Instant and current of the previous sample.
+	previous: Option<(Instant, f64)>,
+	state: BatteryState,
+	overcurrent: bool,
+}
+
+impl Battery {
+	pub fn new(config: BatteryConfig) -> Self {
+		Self {
+			config,
+			initial_remaining: None,
+			resting_voltage: 0.,
+			consumed: 0.,
+			previous: None,
+			state: BatteryState::Nominal,
+			overcurrent: false,
+		}
+	}
+
+	/// Remaining percentage of a linear discharge curve between empty and full cell voltages.
+	fn voltage_remaining(&self, resting_voltage: f64) -> f64 {
+		let cell_voltage = resting_voltage / self.config.cell_count as f64;
+
+		((cell_voltage - self.config.empty_cell_voltage)
+			/ (self.config.full_cell_voltage - self.config.empty_cell_voltage) * 100.)
+			.max(0.)
+			.min(100.)
+	}
+
+	/// Updates the model with a power module sample, returning `None` if no battery is connected.
+	pub fn update(&mut self, adc: &NavioAdcData<f64>) -> Option<BatteryEstimate> {
+		if adc.external_voltage <= CONNECTED_VOLTAGE {
+			return None;
+		}
+
+		let current = adc.external_current.max(0.);
+		let sag_compensated_voltage = adc.external_voltage + current * self.config.internal_resistance;
+
+		match self.previous {
+			Some((previous_instant, previous_current)) => {
+				let dt = adc.instant.saturating_duration_since(previous_instant).as_secs_f64();
+
+				// Trapezoidal integration, from A.s to mAh
+				self.consumed += (current + previous_current) / 2. * dt / 3.6;
+				self.resting_voltage += (sag_compensated_voltage - self.resting_voltage)
+					* dt / (VOLTAGE_TIME_CONSTANT + dt);
+			}
+			None => self.resting_voltage = sag_compensated_voltage,
+		}
+
+		self.previous = Some((adc.instant, current));
+
+		let voltage_remaining = self.voltage_remaining(self.resting_voltage);
+		let initial_remaining = *self.initial_remaining.get_or_insert(voltage_remaining);
+		let consumption_remaining = (initial_remaining - self.consumed / self.config.capacity * 100.).max(0.);
+
+		// Consumption drifts with current sensor offsets, and voltage with the discharge curve and
+		// internal resistance: the most pessimistic estimate is kept
+		let remaining = consumption_remaining.min(voltage_remaining);
+
+		let state = if remaining <= self.config.critical_remaining {
+			BatteryState::Critical
+		} else if remaining <= self.config.land_remaining {
+			BatteryState::Land
+		} else if remaining <= self.config.warning_remaining {
+			BatteryState::Warning
+		} else {
+			BatteryState::Nominal
+		};
+
+		// Stages are only escalated, as voltage recovers once the load decreases
+		if state > self.state {
+			warn!("Battery {:?}: {:.0} % remaining, {:.0} mAh consumed, {:.2} V at rest",
+				  state, remaining, self.consumed, self.resting_voltage);
+			self.state = state;
+		}
+
+		let overcurrent = current > self.config.max_current;
+		if overcurrent && !self.overcurrent {
+			warn!("Battery current is high: {:.1} A", current);
+		}
+		self.overcurrent = overcurrent;
+
+		debug!(target: "battery", "{} {} {} {} {}",
+			   adc.external_voltage,
+			   current,
+			   self.resting_voltage,
+			   self.consumed,
+			   remaining);
+
+		Some(BatteryEstimate {
+			voltage: adc.external_voltage,
+			current,
+			resting_voltage: self.resting_voltage,
+			consumed: self.consumed,
+			remaining,
+			state: self.state,
+			instant: adc.instant,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::battery::{Battery, BatteryConfig, BatteryState};
+	use autopilot::NavioAdcData;
+	use std::time::{Duration, Instant};
+
+	fn sample(external_voltage: f64, external_current: f64, instant: Instant) -> NavioAdcData<f64> {
+		NavioAdcData {
+			external_voltage,
+			external_current,
+			instant,
+			..NavioAdcData::default()
+		}
+	}
+
+	#[test]
+	fn disconnected_test() {
+		let mut battery = Battery::new(BatteryConfig::default());
+
+		assert!(battery.update(&sample(0.5, 0., Instant::now())).is_none());
+	}
+
+	#[test]
+	fn consumption_test() {
+		let mut battery = Battery::new(BatteryConfig::default());
+		let instant = Instant::now();
+
+		// Full battery at rest, then drawing 18 A for 100 s, i.e. 500 mAh
+		battery.update(&sample(12.6, 0., instant));
+		battery.update(&sample(12.6, 18., instant));
+
+		let mut estimate = None;
+		for i in 1..=1000 {
+			let voltage = 12.6 - 18. * BatteryConfig::default().internal_resistance;
+			estimate = battery.update(&sample(voltage, 18., instant + Duration::from_millis(100 * i)));
+		}
+
+		let estimate = estimate.unwrap();
+		assert!((estimate.consumed - 500.).abs() < 1e-6);
+		assert!((estimate.remaining - 90.).abs() < 1e-6);
+
+		// Sag is compensated
+		assert!((estimate.resting_voltage - 12.6).abs() < 1e-6);
+		assert_eq!(estimate.state, BatteryState::Nominal);
+	}
+
+	#[test]
+	fn stages_test() {
+		let mut battery = Battery::new(BatteryConfig::default());
+		let instant = Instant::now();
+
+		// 3.5 V per cell at rest
+		let estimate = battery.update(&sample(10.5, 0., instant)).unwrap();
+		assert_eq!(estimate.state, BatteryState::Warning);
+
+		// Voltage keeps dropping
+		let mut estimate = None;
+		for i in 1..=100 {
+			estimate = battery.update(&sample(9.9, 0., instant + Duration::from_millis(100 * i)));
+		}
+		assert_eq!(estimate.unwrap().state, BatteryState::Critical);
+
+		// Stages are not released when voltage recovers
+		let estimate = battery.update(&sample(12., 0., instant + Duration::from_secs(11))).unwrap();
+		assert_eq!(estimate.state, BatteryState::Critical);
+	}
+}
@@ -0,0 +1,44 @@
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+
+use autopilot::{BarometerSource, GnssSource, ImuSource, MotorSink, PowerSource, RcSource};
+
+use crate::output_controllers::led_output_controller::LedOutputController;
+use crate::quadcopter_config::QuadcopterConfig;
+
+pub mod navio2;
+pub mod simulation;
+
+/// Hardware the autopilot runs on.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+	Navio2,
+	Simulation,
+}
+
+/// Sensors and actuators of a board. Boards without a RGB LED log LED colors instead.
+pub struct BoardDrivers {
+	pub imu: Box<dyn ImuSource>,
+	/// Whether the IMU passed its startup self-test, if it has one.
+	pub imu_self_test_passed: bool,
+	pub barometer: Box<dyn BarometerSource>,
+	pub gnss: Box<dyn GnssSource>,
+	pub rc: Box<dyn RcSource>,
+	pub power: Box<dyn PowerSource>,
+	pub motors: Box<dyn MotorSink>,
+	pub led: Option<LedOutputController>,
+	/// Notified once the arming interlock has seen the arm switch off, for boards turning it on
+	/// themselves.
+	pub arm_switch_off: Option<Sender<()>>,
+}
+
+impl Board {
+	/// Opens and initializes the drivers of the board.
+	pub fn drivers(&self, config: &QuadcopterConfig) -> anyhow::Result<BoardDrivers> {
+		match self {
+			Board::Navio2 => navio2::drivers(config),
+			Board::Simulation => simulation::drivers(config),
+		}
+	}
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+use autopilot::{ImuData, ImuSource};
+use lsm9ds1::{Settings, LSM9DS1};
+
+/// LSM9DS1 inertial measurement unit of Navio2. Accelerometer and gyroscope samples are read from
+/// the FIFO, so that none is missed nor read twice.
+pub struct Lsm9ds1Imu {
+	lsm9ds1: LSM9DS1,
+	self_test_passed: bool,
+}
+
+const ACC_GYR_PATH: &'static str = "/dev/spidev0.3";
+const MAG_PATH: &'static str = "/dev/spidev0.2";
+
+impl Lsm9ds1Imu {
+	pub fn new(settings: Settings) -> anyhow::Result<Self> {
+		let mut lsm9ds1 = LSM9DS1::new(ACC_GYR_PATH, MAG_PATH)?
+			.with_settings(settings)
+			.init()?;
+
+		// The vehicle is expected to be at rest at startup
+		let self_test = lsm9ds1.self_test()?;
+		let failures = self_test.failures();
+		let self_test_passed = failures.is_empty();
+
+		if self_test_passed {
+			info!("LSM9DS1 self-test passed");
+		} else {
+			error!("LSM9DS1 self-test failed: {} ({:?})", failures.join(", "), self_test);
+		}
+
+		lsm9ds1.enable_fifo()?;
+
+		Ok(Self { lsm9ds1, self_test_passed })
+	}
+
+	/// Whether the startup self-test passed, arming being refused otherwise.
+	pub fn self_test_passed(&self) -> bool {
+		self.self_test_passed
+	}
+}
+
+impl ImuSource for Lsm9ds1Imu {
+	fn read_imu(&mut self) -> Result<ImuData<f64>, Box<dyn Error>> {
+		let ([acc, gyr, mag], temperature, instant) = self.lsm9ds1.read_output()?;
+
+		Ok(ImuData { acc, gyr, mag, temperature, instant })
+	}
+
+	fn read_imu_batch(&mut self) -> Result<Vec<ImuData<f64>>, Box<dyn Error>> {
+		// Data-ready interrupts are not wired on Navio2, the FIFO status is polled once per sample
+		// period instead
+		let (samples, overrun) = loop {
+			let (samples, overrun) = self.lsm9ds1.read_fifo()?;
+
+			if !samples.is_empty() {
+				break (samples, overrun);
+			}
+
+			std::thread::sleep(self.lsm9ds1.sample_period());
+		};
+
+		if overrun {
+			warn!("LSM9DS1 FIFO overrun, IMU samples were lost");
+		}
+
+		// Magnetometer and temperature are sampled at lower rates, and are not queued
+		let mag = self.lsm9ds1.read_mag()?;
+		let temperature = self.lsm9ds1.read_temperature()?;
+
+		Ok(samples
+			.into_iter()
+			.map(|([acc, gyr], instant)| ImuData { acc, gyr, mag, temperature, instant })
+			.collect())
+	}
+}
@@ -0,0 +1,33 @@
+use crate::boards::BoardDrivers;
+use crate::output_controllers::led_output_controller::LedOutputController;
+use crate::quadcopter_config::QuadcopterConfig;
+
+pub mod lsm9ds1_imu;
+pub mod ms5611_barometer;
+pub mod navio_adc;
+pub mod navio_esc;
+pub mod navio_rc;
+pub mod ublox_gnss;
+
+use lsm9ds1_imu::Lsm9ds1Imu;
+use ms5611_barometer::Ms5611Barometer;
+use navio_adc::NavioAdc;
+use navio_esc::NavioEsc;
+use navio_rc::NavioRc;
+use ublox_gnss::UbloxGnss;
+
+pub fn drivers(config: &QuadcopterConfig) -> anyhow::Result<BoardDrivers> {
+	let imu = Lsm9ds1Imu::new(config.lsm9ds1)?;
+
+	Ok(BoardDrivers {
+		imu_self_test_passed: imu.self_test_passed(),
+		imu: Box::new(imu),
+		barometer: Box::new(Ms5611Barometer::new()?),
+		gnss: Box::new(UbloxGnss::new()?),
+		rc: Box::new(NavioRc::new(config.input_rc_range)?),
+		power: Box::new(NavioAdc::new()?),
+		motors: Box::new(NavioEsc::new(config.output_esc_pins).init()?),
+		led: Some(LedOutputController::new()?),
+		arm_switch_off: None,
+	})
+}
@@ -0,0 +1,27 @@
+use std::error::Error;
+use autopilot::{BarometerData, BarometerSource};
+use ms5611::MS5611;
+
+/// MS5611 barometer of Navio2.
+pub struct Ms5611Barometer {
+	ms5611: MS5611,
+}
+
+const I2C_PATH: &'static str = "/dev/i2c-1";
+const I2C_ADDRESS: u16 = 0x77;
+
+impl Ms5611Barometer {
+	pub fn new() -> anyhow::Result<Self> {
+		Ok(Self {
+			ms5611: MS5611::new(I2C_PATH, I2C_ADDRESS)?.init()?,
+		})
+	}
+}
+
+impl BarometerSource for Ms5611Barometer {
+	fn read_barometer(&mut self) -> Result<BarometerData<f64>, Box<dyn Error>> {
+		let (pressure, temperature, instant) = self.ms5611.read_output()?;
+
+		Ok(BarometerData { pressure, temperature, instant })
+	}
+}
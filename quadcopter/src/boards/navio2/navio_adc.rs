@@ -10,10 +10,10 @@ adc.init() function initialize 6 ADC channels. After that it's possible to read
 
 use std::{io, fs};
 use std::error::Error;
-use autopilot::{InputController, Input, NavioAdcData};
-use systemstat::Duration;
+use std::time::Instant;
+use autopilot::{NavioAdcData, PowerSource};
 
-pub struct NavioAdcInputController;
+pub struct NavioAdc;
 
 const ADC_PATH: &'static str = "/sys/kernel/rcio/adc";
 const CHANNELS: usize = 6;
@@ -28,7 +28,7 @@ mod channels {
 	pub const ADC_PORT_3: u8 = 5;
 }
 
-impl NavioAdcInputController {
+impl NavioAdc {
 	pub fn new() -> Result<Self, io::Error> {
 		for i in 0..CHANNELS {
 			fs::metadata(format!("{}/ch{}", ADC_PATH, i))?; // Check if files exist
@@ -45,6 +45,7 @@ impl NavioAdcInputController {
 			external_current: self.read_channel(channels::EXTERNAL_CURRENT)?,
 			adc_port_2: self.read_channel(channels::ADC_PORT_2)?,
 			adc_port_3: self.read_channel(channels::ADC_PORT_3)?,
+			instant: Instant::now(),
 		})
 	}
 
@@ -63,15 +64,8 @@ impl NavioAdcInputController {
 	}
 }
 
-impl InputController for NavioAdcInputController {
-	const DELAY: Option<Duration> = Some(Duration::from_secs(1));
-
-	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+impl PowerSource for NavioAdc {
+	fn read_power(&mut self) -> Result<NavioAdcData<f64>, Box<dyn Error>> {
 		self.read_channels()
-			.map(|channels| {
-			debug!(target: "adc", "{}", channels);
-			channels
-		})
-			.map(Input::NavioAdc)
 	}
-}
\ No newline at end of file
+}
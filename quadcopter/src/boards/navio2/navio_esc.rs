@@ -1,11 +1,11 @@
-use autopilot::OutputController;
+use autopilot::MotorSink;
 use std::error::Error;
 use std::io;
 use pwm::{PwmPin, Polarity};
 
-pub const QUADCOPTER_ESC_CHANNELS: usize = 4;
+use crate::quadcopter::QUADCOPTER_ESC_CHANNELS;
 
-pub struct NavioEscOutputController {
+pub struct NavioEsc {
 	esc_channels: [PwmPin; QUADCOPTER_ESC_CHANNELS],
 }
 
@@ -18,8 +18,8 @@ const MAX_VALUE_NS: u64 = 2000_000;
 // 2000 µs
 const VALUE_RANGE_NS: u64 = MAX_VALUE_NS - MIN_VALUE_NS;
 
-impl NavioEscOutputController {
-	pub fn new(esc_channels: [u32; 4]) -> Self {
+impl NavioEsc {
+	pub fn new(esc_channels: [u32; QUADCOPTER_ESC_CHANNELS]) -> Self {
 		Self {
 			esc_channels: esc_channels.map(|i| PwmPin::new(i)),
 		}
@@ -45,12 +45,18 @@ impl NavioEscOutputController {
 	}
 }
 
-impl OutputController<[f64; QUADCOPTER_ESC_CHANNELS]> for NavioEscOutputController {
-	fn write_output(&mut self, output: [f64; QUADCOPTER_ESC_CHANNELS]) -> Result<(), Box<dyn Error>> {
+impl MotorSink for NavioEsc {
+	fn write_motors(&mut self, outputs: &[f64]) -> Result<(), Box<dyn Error>> {
+		if outputs.len() != QUADCOPTER_ESC_CHANNELS {
+			return Err(anyhow!("Expected {} motor outputs, got {}",
+							   QUADCOPTER_ESC_CHANNELS,
+							   outputs.len()).into());
+		}
+
 		// Outputs are expected to be between (0., +1.)
-		debug!(target: "esc", "{} {} {} {}", output[0], output[1], output[2], output[3]);
+		debug!(target: "esc", "{} {} {} {}", outputs[0], outputs[1], outputs[2], outputs[3]);
 
-		for (channel, &value) in self.esc_channels.iter_mut().zip(output.iter()) {
+		for (channel, &value) in self.esc_channels.iter_mut().zip(outputs.iter()) {
 			let pulse_width_ns = MIN_VALUE_NS + (value * (VALUE_RANGE_NS as f64)) as u64;
 			channel.set_pulse_width(pulse_width_ns)?;
 		}
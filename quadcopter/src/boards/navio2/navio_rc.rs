@@ -1,11 +1,10 @@
-use autopilot::{InputController, Input};
+use autopilot::{RcChannels, RcSource};
 use std::error::Error;
-use systemstat::Duration;
 use std::io;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 
-pub struct NavioRcInputController {
+pub struct NavioRc {
 	channel_files: [File; CHANNELS],
 	connected_file: File,
 	range: (u16, u16),
@@ -16,7 +15,7 @@ const RC_IN_PATH: &'static str = "/sys/kernel/rcio/rcin";
 pub const CHANNELS: usize = 16;
 // pub const FLYSKY_RANGE: (u16, u16) = (1024, 2003);
 
-impl NavioRcInputController {
+impl NavioRc {
 	pub fn new(range: (u16, u16)) -> Result<Self, io::Error> {
 		let channel_files = [
 			File::open(format!("{}/ch0", RC_IN_PATH))?,
@@ -67,10 +66,8 @@ impl NavioRcInputController {
 
 }
 
-impl InputController for NavioRcInputController {
-	const DELAY: Option<Duration> = Some(Duration::from_millis(20));
-
-	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+impl RcSource for NavioRc {
+	fn read_rc(&mut self) -> Result<RcChannels<f64>, Box<dyn Error>> {
 		let mut channels = [0.; CHANNELS];
 
 		for i in 0..CHANNELS {
@@ -94,9 +91,9 @@ impl InputController for NavioRcInputController {
 			  channels[12], channels[13], channels[14], channels[15]);
 
 		if connected {
-			Ok(Input::RcChannels(Some(channels)))
+			Ok(Some(channels))
 		} else {
-			Ok(Input::RcChannels(None))
+			Ok(None)
 		}
 	}
 }
\ No newline at end of file
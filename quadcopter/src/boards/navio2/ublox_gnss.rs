@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+use autopilot::{GnssData, GnssFix, GnssSource};
+use nalgebra::Vector3;
+use ublox::{FixType, Message, NavPvt, Ublox};
+
+/// u-blox NEO-M8N receiver of Navio2.
+pub struct UbloxGnss {
+	ublox: Ublox,
+	fix: GnssFix,
+}
+
+const SPI_PATH: &'static str = "/dev/spidev0.0";
+/// Period between two navigation solutions (5 Hz).
+const NAVIGATION_PERIOD: Duration = Duration::from_millis(200);
+/// Period between two reads of the receiver output.
+const POLL_PERIOD: Duration = Duration::from_millis(20);
+
+impl UbloxGnss {
+	pub fn new() -> anyhow::Result<Self> {
+		let mut ublox = Ublox::new(SPI_PATH)?;
+		ublox.configure(NAVIGATION_PERIOD)?;
+
+		Ok(Self {
+			ublox,
+			fix: GnssFix::NoFix,
+		})
+	}
+}
+
+/// Fix type of a solution, invalid fixes being reported as no fix.
+fn fix(pvt: &NavPvt) -> GnssFix {
+	if !pvt.fix_ok {
+		return GnssFix::NoFix;
+	}
+
+	match pvt.fix_type {
+		FixType::Fix3D | FixType::GnssDeadReckoning => GnssFix::Fix3D,
+		FixType::Fix2D => GnssFix::Fix2D,
+		FixType::DeadReckoning => GnssFix::DeadReckoning,
+		FixType::NoFix | FixType::TimeOnly => GnssFix::NoFix,
+	}
+}
+
+impl GnssSource for UbloxGnss {
+	fn read_gnss(&mut self) -> Result<GnssData<f64>, Box<dyn Error>> {
+		loop {
+			let instant = Instant::now();
+			let mut gnss_data = None;
+
+			for message in self.ublox.read_messages()? {
+				match message {
+					Message::NavPvt(pvt) => {
+						gnss_data = Some(GnssData {
+							fix: fix(&pvt),
+							latitude: pvt.latitude,
+							longitude: pvt.longitude,
+							altitude: pvt.height,
+							velocity: Vector3::new(pvt.velocity[0], pvt.velocity[1], pvt.velocity[2]),
+							horizontal_accuracy: pvt.horizontal_accuracy,
+							vertical_accuracy: pvt.vertical_accuracy,
+							speed_accuracy: pvt.speed_accuracy,
+							satellites: pvt.satellites,
+							instant,
+						});
+					}
+					Message::NavStatus(status) => {
+						debug!(target: "gnss_status", "{:?} {} {} {}",
+							   status.fix_type,
+							   status.fix_ok,
+							   status.time_to_first_fix.as_secs_f64(),
+							   self.ublox.errors());
+					}
+					_ => {}
+				}
+			}
+
+			if let Some(gnss_data) = gnss_data {
+				if gnss_data.fix != self.fix {
+					info!("GNSS fix: {:?} with {} satellites", gnss_data.fix, gnss_data.satellites);
+					self.fix = gnss_data.fix;
+				}
+
+				return Ok(gnss_data);
+			}
+
+			thread::sleep(POLL_PERIOD);
+		}
+	}
+}
@@ -0,0 +1,48 @@
+use crossbeam_channel::bounded;
+use simulator::{QuadcopterParameters, Simulator};
+use std::thread;
+
+use crate::boards::BoardDrivers;
+use crate::quadcopter_config::QuadcopterConfig;
+
+pub fn drivers(_config: &QuadcopterConfig) -> anyhow::Result<BoardDrivers> {
+	let simulator = Simulator::new(QuadcopterParameters::default());
+
+	// Throttle low, sticks centered, arm switch off, altitude hold off, angle mode,
+	// mission off
+	let mut rc_channels = [0.5; 16];
+	rc_channels[2] = 0.;
+	rc_channels[4] = 0.;
+	rc_channels[5] = 0.;
+	rc_channels[6] = 0.;
+	rc_channels[7] = 0.;
+	rc_channels[8] = 0.;
+
+	let rc = simulator.rc(Some(rc_channels));
+
+	// The arm switch is turned on once the arming interlock has seen it off
+	let (arm_switch_off_sender, arm_switch_off_receiver) = bounded::<()>(1);
+	let rc_sender = rc.sender();
+	thread::spawn(move || {
+		if arm_switch_off_receiver.recv().is_ok() {
+			rc_channels[4] = 1.;
+			let _ = rc_sender.send(Some(rc_channels));
+		}
+	});
+
+	let drivers = BoardDrivers {
+		imu: Box::new(simulator.imu()),
+		imu_self_test_passed: true,
+		barometer: Box::new(simulator.barometer()),
+		gnss: Box::new(simulator.gnss()),
+		rc: Box::new(rc),
+		power: Box::new(simulator.power_module()),
+		motors: Box::new(simulator.escs()),
+		led: None,
+		arm_switch_off: Some(arm_switch_off_sender),
+	};
+
+	simulator.spawn();
+
+	Ok(drivers)
+}
@@ -0,0 +1,396 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use autopilot::RcChannels;
+use navigation::NavigationEstimate;
+
+use crate::altitude_estimator::AltitudeEstimate;
+use crate::battery::BatteryState;
+use crate::position_control::PositionSetpoint;
+
+/// Action taken once the RC signal has been lost for longer than the grace period, or the
+/// battery is low.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum FailsafeAction {
+	/// Level and descend in place.
+	Land,
+	/// Climb to the return altitude, fly back above the navigation origin and land there. The
+	/// aircraft lands in place while position is not estimated.
+	ReturnToHome,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct FailsafeConfig {
+	pub action: FailsafeAction,
+	/// Time (s) during which the last valid RC channels are held after the signal is lost.
+	pub grace_period: f64,
+	/// Descent rate (m/s) while landing.
+	pub descent_rate: f64,
+	/// Throttle while landing without altitude estimate, slightly below hover throttle.
+	pub descent_throttle: f64,
+	/// Altitude (m) above the navigation origin at which the aircraft returns home.
+	pub return_altitude: f64,
+	/// Horizontal distance (m) from home within which the aircraft starts descending.
+	pub home_radius: f64,
+	/// Vertical speed (m/s) below which a descending aircraft may have landed.
+	pub landed_speed: f64,
+	/// Time (s) spent below the landed speed before motors are stopped.
+	pub landed_time: f64,
+	/// Time (s) after which a landing without altitude estimate is considered complete, motors
+	/// being stopped. It should exceed the descent time at descent throttle from the highest
+	/// expected altitude.
+	pub blind_landing_time: f64,
+}
+
+impl Default for FailsafeConfig {
+	fn default() -> Self {
+		FailsafeConfig {
+			action: FailsafeAction::Land,
+			grace_period: 1.,
+			descent_rate: 0.7,
+			descent_throttle: 0.4,
+			return_altitude: 20.,
+			home_radius: 3.,
+			landed_speed: 0.2,
+			landed_time: 2.,
+			blind_landing_time: 45.,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FailsafeState {
+	/// RC signal is received.
+	Nominal,
+	/// RC signal is lost, and the last valid channels are held.
+	Hold,
+	/// Levelling and descending in place.
+	Land,
+	/// Climbing to the return altitude.
+	ReturnClimb,
+	/// Flying home at the return altitude.
+	Return,
+	/// Descending above home.
+	ReturnDescend,
+	/// Landed, motors being stopped until the RC signal is recovered, or for good after a low
+	/// battery landing.
+	Landed,
+}
+
+/// Tolerance (m) on the return altitude before flying home.
+const RETURN_ALTITUDE_TOLERANCE: f64 = 1.;
+
+/// State machine taking over the RC commands when the RC signal is lost, instead of cutting
+/// motors mid-air, or when the battery is low.
+pub struct Failsafe {
+	config: FailsafeConfig,
+	state: FailsafeState,
+	rc_channels: RcChannels<f64>,
+	/// Instant the RC signal was lost.
+	loss: Option<Instant>,
+	/// Instant the vertical speed went below the landed speed while descending.
+	landing: Option<Instant>,
+	/// Instant the descent started.
+	descent: Option<Instant>,
+}
+
+impl Failsafe {
+	pub fn new(config: FailsafeConfig) -> Self {
+		Self {
+			config,
+			state: FailsafeState::Nominal,
+			rc_channels: None,
+			loss: None,
+			landing: None,
+			descent: None,
+		}
+	}
+
+	pub fn config(&self) -> &FailsafeConfig {
+		&self.config
+	}
+
+	pub fn state(&self) -> FailsafeState {
+		self.state
+	}
+
+	/// Last valid RC channels, held while the signal is lost.
+	pub fn rc_channels(&self) -> RcChannels<f64> {
+		self.rc_channels
+	}
+
+	/// Updates the state with the received RC channels and the battery stage. Position is only
+	/// used for returning home when altitude is estimated as well, since climb rates are tracked
+	/// from the altitude estimate.
+	pub fn update(&mut self,
+				  rc_channels: &RcChannels<f64>,
+				  battery: BatteryState,
+				  navigation: Option<&NavigationEstimate>,
+				  altitude: Option<&AltitudeEstimate>,
+				  instant: Instant) -> FailsafeState {
+		let low_battery = battery >= BatteryState::Land;
+
+		if rc_channels.is_some() {
+			self.rc_channels = *rc_channels;
+			self.loss = None;
+
+			if !low_battery {
+				self.landing = None;
+				self.set_state(FailsafeState::Nominal);
+
+				return self.state;
+			}
+		}
+
+		let navigation = navigation.filter(|_| altitude.is_some());
+		let grace_period = Duration::from_secs_f64(self.config.grace_period);
+		let signal_lost = self.loss.map_or(false, |loss| instant.saturating_duration_since(loss) >= grace_period);
+
+		if rc_channels.is_none() {
+			self.loss.get_or_insert(instant);
+		}
+
+		let state = match self.state {
+			FailsafeState::Nominal | FailsafeState::Hold if !(low_battery || signal_lost) => FailsafeState::Hold,
+			FailsafeState::Nominal | FailsafeState::Hold => match (self.config.action, navigation) {
+				(FailsafeAction::ReturnToHome, Some(_)) => FailsafeState::ReturnClimb,
+				_ => FailsafeState::Land,
+			},
+			FailsafeState::ReturnClimb => match navigation {
+				Some(navigation)
+				if -navigation.position[2] >= self.config.return_altitude - RETURN_ALTITUDE_TOLERANCE => {
+					FailsafeState::Return
+				}
+				Some(_) => FailsafeState::ReturnClimb,
+				None => FailsafeState::Land,
+			},
+			FailsafeState::Return => match navigation {
+				Some(navigation) if navigation.position[0].hypot(navigation.position[1]) <= self.config.home_radius => {
+					FailsafeState::ReturnDescend
+				}
+				Some(_) => FailsafeState::Return,
+				None => FailsafeState::Land,
+			},
+			FailsafeState::ReturnDescend if navigation.is_none() => FailsafeState::Land,
+			state => state,
+		};
+
+		// A critical battery does not leave enough time to return home
+		let state = match state {
+			FailsafeState::ReturnClimb | FailsafeState::Return | FailsafeState::ReturnDescend
+			if battery == BatteryState::Critical => FailsafeState::Land,
+			state => state,
+		};
+
+		if matches!(state, FailsafeState::Land | FailsafeState::ReturnDescend) {
+			self.descent.get_or_insert(instant);
+		} else {
+			self.descent = None;
+		}
+
+		let state = match state {
+			FailsafeState::Land | FailsafeState::ReturnDescend if self.landed(altitude, instant) => {
+				FailsafeState::Landed
+			}
+			state => state,
+		};
+
+		self.set_state(state);
+
+		self.state
+	}
+
+	/// Position setpoint while returning home, keeping the current yaw (rad).
+	pub fn return_setpoint(&self, estimate: &NavigationEstimate, yaw: f64) -> Option<PositionSetpoint> {
+		let return_down = -self.config.return_altitude;
+
+		match self.state {
+			// Braking while climbing
+			FailsafeState::ReturnClimb => Some(PositionSetpoint {
+				position: [estimate.position[0], estimate.position[1], return_down],
+				velocity: [0.; 3],
+				yaw,
+			}),
+			FailsafeState::Return => Some(PositionSetpoint {
+				position: [0., 0., return_down],
+				velocity: [0.; 3],
+				yaw,
+			}),
+			// Altitude error is kept at zero, so that only the descent rate is commanded
+			FailsafeState::ReturnDescend => Some(PositionSetpoint {
+				position: [0., 0., estimate.position[2]],
+				velocity: [0., 0., self.config.descent_rate],
+				yaw,
+			}),
+			_ => None,
+		}
+	}
+
+	/// Whether the vertical speed has stayed below the landed speed for the landed time, or,
+	/// without altitude estimate, whether the descent has lasted for the blind landing time.
+	fn landed(&mut self, altitude: Option<&AltitudeEstimate>, instant: Instant) -> bool {
+		match altitude {
+			Some(altitude) if altitude.vertical_speed.abs() < self.config.landed_speed => {
+				let landing = *self.landing.get_or_insert(instant);
+
+				instant.saturating_duration_since(landing) >= Duration::from_secs_f64(self.config.landed_time)
+			}
+			Some(_) => {
+				self.landing = None;
+				false
+			}
+			None => {
+				self.landing = None;
+
+				self.descent.map_or(false, |descent| {
+					instant.saturating_duration_since(descent) >= Duration::from_secs_f64(self.config.blind_landing_time)
+				})
+			}
+		}
+	}
+
+	fn set_state(&mut self, state: FailsafeState) {
+		if state != self.state {
+			match state {
+				FailsafeState::Nominal => info!("RC signal recovered"),
+				// Low battery stages are logged by the battery model
+				FailsafeState::Hold => warn!("RC signal lost: holding last commands"),
+				FailsafeState::Land => warn!("Failsafe: landing"),
+				FailsafeState::ReturnClimb => warn!("Failsafe: climbing to {} m", self.config.return_altitude),
+				FailsafeState::Return => warn!("Failsafe: returning home"),
+				FailsafeState::ReturnDescend => warn!("Failsafe: descending above home"),
+				FailsafeState::Landed => warn!("Failsafe: landed, stopping motors"),
+			}
+
+			self.state = state;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::altitude_estimator::AltitudeEstimate;
+	use crate::battery::BatteryState;
+	use crate::failsafe::{Failsafe, FailsafeAction, FailsafeConfig, FailsafeState};
+	use navigation::{GeodeticPosition, NavigationEstimate};
+	use std::time::{Duration, Instant};
+
+	fn estimates(position: [f64; 3], vertical_speed: f64, instant: Instant) -> (NavigationEstimate, AltitudeEstimate) {
+		let navigation = NavigationEstimate {
+			position,
+			velocity: [0., 0., -vertical_speed],
+			position_std: [1.; 3],
+			origin: GeodeticPosition { latitude: 48.713202, longitude: 2.200641, altitude: 160. },
+			instant,
+		};
+
+		let altitude = AltitudeEstimate { altitude: -position[2], vertical_speed, instant };
+
+		(navigation, altitude)
+	}
+
+	#[test]
+	fn land_test() {
+		let mut failsafe = Failsafe::new(FailsafeConfig::default());
+		let instant = Instant::now();
+		let rc_channels = Some([0.5; 16]);
+
+		assert_eq!(failsafe.update(&rc_channels, BatteryState::Nominal, None, None, instant), FailsafeState::Nominal);
+
+		// Last channels are held during the grace period
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, None, instant + Duration::from_millis(100)),
+				   FailsafeState::Hold);
+		assert_eq!(failsafe.rc_channels(), rc_channels);
+
+		// Without position, the aircraft lands even if set to return home
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, None, instant + Duration::from_secs(2)), FailsafeState::Land);
+
+		// Once slow enough for long enough, it has landed
+		let (_, altitude) = estimates([0.; 3], 0., instant);
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, Some(&altitude), instant + Duration::from_secs(3)),
+				   FailsafeState::Land);
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, Some(&altitude), instant + Duration::from_secs(6)),
+				   FailsafeState::Landed);
+
+		// Recovering the signal gives control back
+		assert_eq!(failsafe.update(&rc_channels, BatteryState::Nominal, None, None, instant + Duration::from_secs(7)),
+				   FailsafeState::Nominal);
+	}
+
+	#[test]
+	fn blind_landing_test() {
+		let config = FailsafeConfig::default();
+		let mut failsafe = Failsafe::new(config);
+		let instant = Instant::now();
+		let seconds = |seconds: f64| instant + Duration::from_secs_f64(seconds);
+
+		failsafe.update(&Some([0.5; 16]), BatteryState::Nominal, None, None, instant);
+		failsafe.update(&None, BatteryState::Nominal, None, None, instant);
+
+		// Without barometer, the descent at descent throttle lasts for the blind landing time
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, None, seconds(2.)), FailsafeState::Land);
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, None, seconds(1. + config.blind_landing_time)),
+				   FailsafeState::Land);
+		assert_eq!(failsafe.update(&None, BatteryState::Nominal, None, None, seconds(2. + config.blind_landing_time)),
+				   FailsafeState::Landed);
+	}
+
+	#[test]
+	fn return_to_home_test() {
+		let config = FailsafeConfig { action: FailsafeAction::ReturnToHome, ..FailsafeConfig::default() };
+		let mut failsafe = Failsafe::new(config);
+		let instant = Instant::now();
+
+		failsafe.update(&Some([0.5; 16]), BatteryState::Nominal, None, None, instant);
+		failsafe.update(&None, BatteryState::Nominal, None, None, instant);
+
+		// Climbing above the loss position
+		let (navigation, altitude) = estimates([50., 50., -5.], 0., instant);
+		let state = failsafe.update(&None, BatteryState::Nominal, Some(&navigation), Some(&altitude), instant + Duration::from_secs(2));
+		assert_eq!(state, FailsafeState::ReturnClimb);
+
+		let setpoint = failsafe.return_setpoint(&navigation, 0.).unwrap();
+		assert_eq!(setpoint.position, [50., 50., -config.return_altitude]);
+
+		// Returning home at the return altitude
+		let (navigation, altitude) = estimates([50., 50., -config.return_altitude], 0., instant);
+		let state = failsafe.update(&None, BatteryState::Nominal, Some(&navigation), Some(&altitude), instant + Duration::from_secs(3));
+		assert_eq!(state, FailsafeState::Return);
+
+		let setpoint = failsafe.return_setpoint(&navigation, 0.).unwrap();
+		assert_eq!(setpoint.position, [0., 0., -config.return_altitude]);
+
+		// Descending above home
+		let (navigation, altitude) = estimates([1., 1., -config.return_altitude], -0.5, instant);
+		let state = failsafe.update(&None, BatteryState::Nominal, Some(&navigation), Some(&altitude), instant + Duration::from_secs(4));
+		assert_eq!(state, FailsafeState::ReturnDescend);
+
+		let setpoint = failsafe.return_setpoint(&navigation, 0.).unwrap();
+		assert_eq!(setpoint.velocity[2], config.descent_rate);
+
+		// Losing position while returning falls back to landing in place
+		let state = failsafe.update(&None, BatteryState::Nominal, None, Some(&altitude), instant + Duration::from_secs(5));
+		assert_eq!(state, FailsafeState::Land);
+	}
+
+	#[test]
+	fn low_battery_test() {
+		let config = FailsafeConfig { action: FailsafeAction::ReturnToHome, ..FailsafeConfig::default() };
+		let mut failsafe = Failsafe::new(config);
+		let instant = Instant::now();
+		let rc_channels = Some([0.5; 16]);
+		let (navigation, altitude) = estimates([50., 50., -5.], 0.5, instant);
+
+		// A warning does not take over
+		let state = failsafe.update(&rc_channels, BatteryState::Warning, Some(&navigation), Some(&altitude), instant);
+		assert_eq!(state, FailsafeState::Nominal);
+
+		// The failsafe action is taken without grace period, even though the signal is received
+		let state = failsafe.update(&rc_channels, BatteryState::Land, Some(&navigation), Some(&altitude), instant);
+		assert_eq!(state, FailsafeState::ReturnClimb);
+
+		// A critical battery lands in place
+		let state = failsafe.update(&rc_channels, BatteryState::Critical, Some(&navigation), Some(&altitude), instant);
+		assert_eq!(state, FailsafeState::Land);
+	}
+}
@@ -0,0 +1,60 @@
+use dsp::{AlphaBetaGamma, Biquad, FilterChain, ScalarAlphaBeta};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// Filter applied to accelerometer or gyroscope samples.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImuFilterConfig {
+	LowPass {
+		cutoff_frequency: f64,
+		q: f64,
+		sample_frequency: f64,
+	},
+	AlphaBetaGamma {
+		alpha: f64,
+		beta: f64,
+		gamma: f64,
+	},
+}
+
+/// Filter applied to the derivative term of rate PIDs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DTermFilterConfig {
+	AlphaBeta {
+		alpha: f64,
+		beta: f64,
+	},
+}
+
+pub fn imu_filter_chain(configs: &[ImuFilterConfig]) -> FilterChain<f64, Vector3<f64>> {
+	let mut chain = FilterChain::new();
+
+	for config in configs {
+		match *config {
+			ImuFilterConfig::LowPass { cutoff_frequency, q, sample_frequency } => {
+				chain.push(Box::new(Biquad::low_pass(cutoff_frequency, q, sample_frequency)))
+			}
+			ImuFilterConfig::AlphaBetaGamma { alpha, beta, gamma } => {
+				chain.push(Box::new(AlphaBetaGamma::new(alpha, beta, gamma)))
+			}
+		}
+	}
+
+	chain
+}
+
+pub fn d_term_filter_chain(configs: &[DTermFilterConfig]) -> FilterChain<f64, f64> {
+	let mut chain = FilterChain::new();
+
+	for config in configs {
+		match *config {
+			DTermFilterConfig::AlphaBeta { alpha, beta } => {
+				chain.push(Box::new(ScalarAlphaBeta::new(alpha, beta)))
+			}
+		}
+	}
+
+	chain
+}
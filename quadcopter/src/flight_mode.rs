@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+/// Roll and pitch control laws, yaw being controlled the same way in every mode.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FlightMode {
+	/// Sticks command body rates.
+	Acro,
+	/// Sticks command attitude, within limits.
+	Angle,
+	/// Angle mode around stick center, blended into acro mode at full stick deflection.
+	Horizon,
+}
+
+impl FlightMode {
+	/// Mode selected by a three-position switch channel within (0., 1.).
+	pub fn from_switch(value: f64) -> Self {
+		if value < 1. / 3. {
+			FlightMode::Angle
+		} else if value < 2. / 3. {
+			FlightMode::Horizon
+		} else {
+			FlightMode::Acro
+		}
+	}
+}
+
+/// Tracks flight mode changes, so that target rates of the previous mode can be blended into
+/// those of the new mode during a short transition.
+pub struct FlightModeSelector {
+	mode: FlightMode,
+	transition: Option<(FlightMode, Instant)>,
+}
+
+impl FlightModeSelector {
+	pub const TRANSITION_DURATION: Duration = Duration::from_millis(300);
+
+	pub fn new(mode: FlightMode) -> Self {
+		Self {
+			mode,
+			transition: None,
+		}
+	}
+
+	/// Forces the mode without transition, e.g. when disarmed.
+	pub fn reset(&mut self, mode: FlightMode) {
+		self.mode = mode;
+		self.transition = None;
+	}
+
+	/// Returns the current mode, and the previous mode with its weight within (0., 1.) if a
+	/// transition is in progress.
+	pub fn update(&mut self, mode: FlightMode, instant: Instant) -> (FlightMode, Option<(FlightMode, f64)>) {
+		if mode != self.mode {
+			info!("Flight mode: {:?}", mode);
+			self.transition = Some((self.mode, instant));
+			self.mode = mode;
+		}
+
+		let previous_mode = self.transition.and_then(|(previous_mode, start_instant)| {
+			let elapsed = instant.saturating_duration_since(start_instant);
+
+			if elapsed < Self::TRANSITION_DURATION {
+				let weight = 1. - elapsed.as_secs_f64() / Self::TRANSITION_DURATION.as_secs_f64();
+				Some((previous_mode, weight))
+			} else {
+				None
+			}
+		});
+
+		if previous_mode.is_none() {
+			self.transition = None;
+		}
+
+		(self.mode, previous_mode)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::flight_mode::{FlightMode, FlightModeSelector};
+	use std::time::{Duration, Instant};
+
+	#[test]
+	fn switch_test() {
+		assert_eq!(FlightMode::from_switch(0.), FlightMode::Angle);
+		assert_eq!(FlightMode::from_switch(0.5), FlightMode::Horizon);
+		assert_eq!(FlightMode::from_switch(1.), FlightMode::Acro);
+	}
+
+	#[test]
+	fn transition_test() {
+		let instant = Instant::now();
+		let mut selector = FlightModeSelector::new(FlightMode::Angle);
+
+		assert_eq!(selector.update(FlightMode::Angle, instant), (FlightMode::Angle, None));
+		assert_eq!(selector.update(FlightMode::Acro, instant), (FlightMode::Acro, Some((FlightMode::Angle, 1.))));
+
+		let half_transition = FlightModeSelector::TRANSITION_DURATION / 2;
+		let (mode, previous_mode) = selector.update(FlightMode::Acro, instant + half_transition);
+		assert_eq!(mode, FlightMode::Acro);
+		assert_eq!(previous_mode.unwrap().0, FlightMode::Angle);
+		assert!((previous_mode.unwrap().1 - 0.5).abs() < 1e-9);
+
+		let after_transition = FlightModeSelector::TRANSITION_DURATION + Duration::from_millis(1);
+		assert_eq!(selector.update(FlightMode::Acro, instant + after_transition), (FlightMode::Acro, None));
+	}
+}
@@ -0,0 +1,145 @@
+use nalgebra::{DMatrix, Vector3};
+use serde::{Serialize, Deserialize};
+
+/// Gyroscope bias as a polynomial of die temperature, such that calibrated samples are
+/// `raw - bias(temperature)`. Coefficients are in ascending powers of
+/// `temperature - reference_temperature`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GyrBiasModel {
+	pub reference_temperature: f64,
+	pub coefficients: Vec<[f64; 3]>,
+}
+
+impl Default for GyrBiasModel {
+	fn default() -> Self {
+		Self::constant(Vector3::zeros(), 25.)
+	}
+}
+
+impl GyrBiasModel {
+	/// Temperature independent bias, as measured by a flat trim.
+	pub fn constant(bias: Vector3<f64>, reference_temperature: f64) -> Self {
+		Self {
+			reference_temperature,
+			coefficients: vec![[bias.x, bias.y, bias.z]],
+		}
+	}
+
+	pub fn bias(&self, temperature: f64) -> Vector3<f64> {
+		let t = temperature - self.reference_temperature;
+
+		self.coefficients
+			.iter()
+			.rev()
+			.fold(Vector3::zeros(), |bias, c| bias * t + Vector3::new(c[0], c[1], c[2]))
+	}
+
+	/// Shifts the model so that it matches `bias` at `temperature`, keeping its temperature
+	/// dependency.
+	pub fn with_bias_at(mut self, bias: Vector3<f64>, temperature: f64) -> Self {
+		let shift = bias - self.bias(temperature);
+
+		match self.coefficients.first_mut() {
+			Some(c) => {
+				c[0] += shift.x;
+				c[1] += shift.y;
+				c[2] += shift.z;
+			}
+			None => self.coefficients.push([shift.x, shift.y, shift.z]),
+		}
+
+		self
+	}
+}
+
+/// Degree of the polynomial fitted by the temperature sweep calibration.
+pub const DEGREE: usize = 2;
+
+/// Minimum temperature range (°C) covered by samples, for the fit to be meaningful.
+pub const MIN_TEMPERATURE_RANGE: f64 = 5.;
+
+/// Fits a polynomial of the given degree to gyroscope samples taken at rest over a temperature
+/// sweep, by least squares.
+pub fn fit(samples: &[(f64, Vector3<f64>)], degree: usize) -> anyhow::Result<GyrBiasModel> {
+	if samples.len() <= degree {
+		return Err(anyhow!("Not enough gyroscope samples: {} (expected more than {})",
+						   samples.len(), degree));
+	}
+
+	let (min_temperature, max_temperature) = samples
+		.iter()
+		.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (temperature, _)| {
+			(min.min(*temperature), max.max(*temperature))
+		});
+
+	if max_temperature - min_temperature < MIN_TEMPERATURE_RANGE {
+		return Err(anyhow!("Temperature range is too narrow: {:.1} °C (expected at least {:.1} °C)",
+						   max_temperature - min_temperature, MIN_TEMPERATURE_RANGE));
+	}
+
+	// Centering temperatures keeps the design matrix well conditioned
+	let reference_temperature = (min_temperature + max_temperature) / 2.;
+
+	let design = DMatrix::from_fn(samples.len(), degree + 1, |i, j| {
+		(samples[i].0 - reference_temperature).powi(j as i32)
+	});
+	let biases = DMatrix::from_fn(samples.len(), 3, |i, j| samples[i].1[j]);
+
+	let solution = design
+		.svd(true, true)
+		.solve(&biases, 1e-12)
+		.map_err(|e| anyhow!("Failed to fit gyroscope bias: {}", e))?;
+
+	Ok(GyrBiasModel {
+		reference_temperature,
+		coefficients: (0..=degree)
+			.map(|i| [solution[(i, 0)], solution[(i, 1)], solution[(i, 2)]])
+			.collect(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::gyr_calibration::{fit, GyrBiasModel, DEGREE};
+	use nalgebra::Vector3;
+
+	#[test]
+	fn temperature_sweep_test() {
+		let bias = |t: f64| Vector3::new(0.01 + 0.002 * t,
+										 -0.02 + 0.0001 * t * t,
+										 0.005 - 0.001 * t + 0.00002 * t * t);
+
+		let samples = (0..200)
+			.map(|i| 30. + i as f64 * 0.1)
+			.map(|t| (t, bias(t)))
+			.collect::<Vec<_>>();
+
+		let model = fit(&samples, DEGREE).unwrap();
+
+		for &t in [30., 35.5, 49.9].iter() {
+			assert!((model.bias(t) - bias(t)).norm() < 1e-9);
+		}
+	}
+
+	#[test]
+	fn narrow_temperature_range_test() {
+		let samples = (0..200)
+			.map(|i| (40. + i as f64 * 0.01, Vector3::zeros()))
+			.collect::<Vec<_>>();
+
+		assert!(fit(&samples, DEGREE).is_err());
+	}
+
+	#[test]
+	fn with_bias_at_test() {
+		let model = GyrBiasModel {
+			reference_temperature: 40.,
+			coefficients: vec![[0.01, 0., 0.], [0.001, 0.002, 0.]],
+		};
+
+		let shifted = model.clone().with_bias_at(Vector3::new(0., 0.1, 0.), 30.);
+
+		assert!((shifted.bias(30.) - Vector3::new(0., 0.1, 0.)).norm() < 1e-12);
+		assert!(((shifted.bias(50.) - shifted.bias(30.)) - (model.bias(50.) - model.bias(30.))).norm() < 1e-12);
+	}
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct HeadingHoldConfig {
+	/// Yaw rate (rad/s) commanded per radian of heading error.
+	pub gain: f64,
+	/// Yaw stick deflection from center, within (0., 1.), below which heading is held.
+	pub deadband: f64,
+}
+
+impl Default for HeadingHoldConfig {
+	fn default() -> Self {
+		HeadingHoldConfig {
+			gain: 3.,
+			deadband: 0.05,
+		}
+	}
+}
+
+/// Yaw controller latching heading when the yaw stick is centered, so that the aircraft does not
+/// drift with gyroscope bias. The yaw stick commands yaw rate when moved out of the deadband.
+pub struct HeadingHold {
+	config: HeadingHoldConfig,
+	target_heading: Option<f64>,
+}
+
+impl HeadingHold {
+	pub fn new(config: HeadingHoldConfig) -> Self {
+		Self {
+			config,
+			target_heading: None,
+		}
+	}
+
+	/// Target yaw rate for a yaw stick position within (0., 1.), given the current heading (rad)
+	/// and the yaw rate commanded at full stick deflection.
+	pub fn target_rate(&mut self, yaw_stick: f64, heading: f64, max_rate: f64) -> f64 {
+		let deflection = (yaw_stick - 0.5) * 2.;
+		let deadband = self.config.deadband;
+
+		if deflection.abs() <= deadband {
+			let target_heading = *self.target_heading.get_or_insert(heading);
+
+			navigation::angle_difference(target_heading as f32, heading as f32) as f64 * self.config.gain
+		} else {
+			self.target_heading = None;
+
+			(deflection - deadband * deflection.signum()) / (1. - deadband) * max_rate
+		}
+	}
+
+	/// Target yaw rate towards a heading (rad) commanded by the autopilot rather than latched.
+	pub fn rate_towards(&mut self, target_heading: f64, heading: f64) -> f64 {
+		self.target_heading = None;
+
+		navigation::angle_difference(target_heading as f32, heading as f32) as f64 * self.config.gain
+	}
+
+	pub fn reset(&mut self) {
+		self.target_heading = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::heading_hold::{HeadingHold, HeadingHoldConfig};
+
+	const MAX_RATE: f64 = 2.;
+
+	#[test]
+	fn latch_test() {
+		let mut heading_hold = HeadingHold::new(HeadingHoldConfig::default());
+
+		assert_eq!(heading_hold.target_rate(0.5, 1., MAX_RATE), 0.);
+		assert!(heading_hold.target_rate(0.5, 0.9, MAX_RATE) > 0.);
+		assert!(heading_hold.target_rate(0.5, 1.1, MAX_RATE) < 0.);
+
+		// Moving the stick releases the latched heading
+		assert_eq!(heading_hold.target_rate(1., 1.5, MAX_RATE), MAX_RATE);
+		assert_eq!(heading_hold.target_rate(0., 1.5, MAX_RATE), -MAX_RATE);
+		assert_eq!(heading_hold.target_rate(0.5, 2., MAX_RATE), 0.);
+	}
+
+	#[test]
+	fn wraparound_test() {
+		let config = HeadingHoldConfig::default();
+		let mut heading_hold = HeadingHold::new(config);
+
+		heading_hold.target_rate(0.5, 179f64.to_radians(), MAX_RATE);
+
+		// Shortest correction from -179° to 179° is a 2° turn in negative direction
+		let rate = heading_hold.target_rate(0.5, -179f64.to_radians(), MAX_RATE);
+		assert!((rate + 2f64.to_radians() * config.gain).abs() < 1e-4);
+	}
+}
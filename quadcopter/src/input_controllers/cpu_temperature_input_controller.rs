@@ -0,0 +1,24 @@
+use std::{error::Error, time::Duration};
+use systemstat::Platform;
+use autopilot::{InputController, Input};
+
+/// Polls the CPU temperature, so that arming can be refused when overheating.
+pub struct CpuTemperatureInputController {
+	system: systemstat::System,
+}
+
+impl CpuTemperatureInputController {
+	pub fn new() -> Self {
+		CpuTemperatureInputController {
+			system: systemstat::System::new(),
+		}
+	}
+}
+
+impl InputController for CpuTemperatureInputController {
+	const DELAY: Option<Duration> = Some(Duration::from_secs(1));
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> {
+		Ok(Input::CpuTemperature(self.system.cpu_temp()? as f64))
+	}
+}
@@ -0,0 +1,246 @@
+use std::{error::Error, time::Duration};
+use std::time::Instant;
+use autopilot::{InputController, Input, ImuData, ImuSource};
+
+use nalgebra::{Matrix3, Vector3};
+
+use crate::gyr_calibration::GyrBiasModel;
+use crate::mag_calibration::MagCalibration;
+
+use ahrs::{Ahrs};
+use dsp::{Filter, FilterChain};
+
+/// Filters IMU samples and feeds them to an AHRS.
+pub struct ImuInputController<AHRS: Ahrs<f64>> {
+	ahrs: AHRS,
+	acc_offset: Vector3<f64>,
+	acc_matrix: Matrix3<f64>,
+	acc_filter: FilterChain<f64, Vector3<f64>>,
+	gyr_bias: GyrBiasModel,
+	gyr_filter: FilterChain<f64, Vector3<f64>>,
+	mag_calibration: Option<MagCalibration>,
+	last_data_instant: Option<Instant>,
+	use_magnetometer: bool,
+	imu: Box<dyn ImuSource>,
+}
+
+
+impl<AHRS: Ahrs<f64>> ImuInputController<AHRS> {
+	pub fn new(imu: Box<dyn ImuSource>,
+			   ahrs: AHRS,
+			   acc_filter: FilterChain<f64, Vector3<f64>>,
+			   gyr_filter: FilterChain<f64, Vector3<f64>>) -> Self {
+		Self {
+			imu,
+			ahrs,
+			acc_offset: Vector3::<f64>::zeros(),
+			acc_matrix: Matrix3::identity(),
+			gyr_bias: GyrBiasModel::default(),
+			acc_filter,
+			gyr_filter,
+			mag_calibration: None,
+			last_data_instant: None,
+			use_magnetometer: false,
+		}
+	}
+
+	/// Feeds magnetometer samples to the AHRS, so that yaw does not drift. By default, the
+	/// magnetometer is ignored.
+	pub fn with_magnetometer(mut self, use_magnetometer: bool) -> Self {
+		self.use_magnetometer = use_magnetometer;
+		self
+	}
+
+	/// Averages raw accelerometer and gyroscope samples, and die temperature, while the vehicle is
+	/// at rest.
+	pub fn average_samples(&mut self) -> Result<(Vector3<f64>, Vector3<f64>, f64), Box<dyn Error>> {
+		//TODO: reject 20% extreme values
+
+		const CALIBRATION_MEASUREMENTS: usize = 500;
+
+		let mut acc_average = Vector3::<f64>::zeros();
+		let mut gyr_average = Vector3::<f64>::zeros();
+		let mut temperature_average = 0.;
+		let mut count = 0;
+
+		while count < CALIBRATION_MEASUREMENTS {
+			for ImuData { acc, gyr, temperature, .. } in self.imu.read_imu_batch()? {
+				acc_average = acc_average + acc;
+				gyr_average = gyr_average + gyr;
+				temperature_average += temperature;
+				count += 1;
+			}
+		}
+
+		acc_average = acc_average / count as f64;
+		gyr_average = gyr_average / count as f64;
+		temperature_average /= count as f64;
+
+		Ok((acc_average, gyr_average, temperature_average))
+	}
+
+	/// Flat trim calibration, assuming the vehicle is level. The accelerometer offset is computed
+	/// with the current accelerometer matrix, and the gyroscope bias model is shifted to match the
+	/// measured bias at the current temperature.
+	pub fn calibrate(&mut self) -> Result<(Vector3<f64>, GyrBiasModel), Box<dyn Error>> {
+		let (acc_average, gyr_average, temperature) = self.average_samples()?;
+
+		const G: f64 = 9.80665;
+		let acc_matrix_inverse = self.acc_matrix
+			.try_inverse()
+			.ok_or("Accelerometer matrix is not invertible")?;
+
+		Ok((acc_average - acc_matrix_inverse * Vector3::new(0., 0., G),
+			self.gyr_bias.clone().with_bias_at(gyr_average, temperature)))
+	}
+
+	pub fn set_calibration(&mut self, acc_offset: Vector3<f64>, gyr_bias: GyrBiasModel) {
+		self.acc_offset = acc_offset;
+		self.gyr_bias = gyr_bias;
+	}
+
+	/// Sets the matrix correcting accelerometer scale factors and misalignment, applied after
+	/// the offset.
+	pub fn set_acc_matrix(&mut self, acc_matrix: Matrix3<f64>) {
+		self.acc_matrix = acc_matrix;
+	}
+
+	/// Collects raw magnetometer samples for `duration`, while the vehicle is rotated in all
+	/// directions by hand.
+	pub fn collect_mag_samples(&mut self, duration: Duration) -> Result<Vec<Vector3<f64>>, Box<dyn Error>> {
+		const SAMPLE_PERIOD: Duration = Duration::from_millis(20);
+		const PROGRESS_PERIOD: Duration = Duration::from_secs(5);
+
+		let start_instant = Instant::now();
+		let mut last_progress_instant = start_instant;
+		let mut samples = Vec::new();
+
+		while start_instant.elapsed() < duration {
+			samples.push(self.imu.read_imu()?.mag);
+
+			if last_progress_instant.elapsed() >= PROGRESS_PERIOD {
+				info!("Collected {} magnetometer samples, {} s remaining",
+					  samples.len(),
+					  duration.saturating_sub(start_instant.elapsed()).as_secs());
+				last_progress_instant = Instant::now();
+			}
+
+			std::thread::sleep(SAMPLE_PERIOD);
+		}
+
+		Ok(samples)
+	}
+
+	/// Collects raw gyroscope samples with die temperature for `duration`, while the vehicle is at
+	/// rest and warms up from a cold start.
+	pub fn collect_gyr_samples(&mut self, duration: Duration) -> Result<Vec<(f64, Vector3<f64>)>, Box<dyn Error>> {
+		const SAMPLE_PERIOD: Duration = Duration::from_millis(20);
+		const PROGRESS_PERIOD: Duration = Duration::from_secs(30);
+
+		let start_instant = Instant::now();
+		let mut last_progress_instant = start_instant;
+		let mut samples = Vec::new();
+
+		while start_instant.elapsed() < duration {
+			let ImuData { gyr, temperature, .. } = self.imu.read_imu()?;
+			samples.push((temperature, gyr));
+
+			if last_progress_instant.elapsed() >= PROGRESS_PERIOD {
+				info!("Collected {} gyroscope samples at {:.1} °C, {} s remaining",
+					  samples.len(),
+					  temperature,
+					  duration.saturating_sub(start_instant.elapsed()).as_secs());
+				last_progress_instant = Instant::now();
+			}
+
+			std::thread::sleep(SAMPLE_PERIOD);
+		}
+
+		Ok(samples)
+	}
+
+	pub fn set_mag_calibration(&mut self, mag_calibration: MagCalibration) {
+		self.mag_calibration = Some(mag_calibration);
+	}
+
+	/// Calibrates and filters a raw sample, and feeds it to the AHRS.
+	fn process_sample(&mut self, sample: ImuData<f64>) -> Result<ImuData<f64>, Box<dyn Error>> {
+		let ImuData { acc, gyr, mag, temperature, instant } = sample;
+
+		// Removing calibration offsets
+		let calibrated_acc = self.acc_matrix * (acc - &self.acc_offset);
+		let calibrated_gyr = gyr - self.gyr_bias.bias(temperature);
+		let mag = match &self.mag_calibration {
+			Some(mag_calibration) => mag_calibration.apply(&mag),
+			None => mag,
+		};
+
+		// Filters are only updated once the sample period is known
+		let processed_imu_data = if let Some(last_data_instant) = self.last_data_instant {
+			let dt = (instant - last_data_instant).as_secs_f64();
+
+			let acc = self.acc_filter.filter(calibrated_acc, dt);
+			let gyr = self.gyr_filter.filter(calibrated_gyr, dt);
+
+			// Updating AHRS
+			let result = if self.use_magnetometer {
+				self.ahrs.update_marg(&gyr, &acc, &mag, dt)
+			} else {
+				self.ahrs.update_imu(&gyr, &acc, dt)
+			};
+
+			if let Err(e) = result {
+				return Err(anyhow!("{:?}", e).into());
+			}
+
+			debug!(target: "lsm9ds1", "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+				   calibrated_acc.x, calibrated_acc.y, calibrated_acc.z,
+				   calibrated_gyr.x, calibrated_gyr.y, calibrated_gyr.z,
+				   acc.x, acc.y, acc.z,
+				   gyr.x, gyr.y, gyr.z,
+				   mag.x, mag.y, mag.z,
+				   temperature,
+			);
+
+			ImuData { acc, gyr, mag, temperature, instant }
+		} else {
+			ImuData {
+				acc: calibrated_acc,
+				gyr: calibrated_gyr,
+				mag,
+				temperature,
+				instant,
+			}
+		};
+
+		self.last_data_instant = Some(instant);
+
+		Ok(processed_imu_data)
+	}
+}
+
+
+impl<AHRS: Ahrs<f64>> InputController for ImuInputController<AHRS> {
+	// IMU sources are paced by their sample rate
+	const DELAY: Option<Duration> = None;
+
+	fn read_input(&mut self) -> Result<Input, Box<dyn Error>> { // Processing a sample is about 300 µs long
+		// Every sample is fed to filters and AHRS, only the latest one is forwarded
+		let mut processed_imu_data = None;
+
+		for sample in self.imu.read_imu_batch()? {
+			processed_imu_data = Some(self.process_sample(sample)?);
+		}
+
+		let processed_imu_data = processed_imu_data.ok_or("IMU source returned no sample")?;
+		let instant = processed_imu_data.instant;
+
+		let orientation = self.ahrs.orientation();
+
+		let as_euler_angles = orientation.euler_angles();
+		debug!(target: "ahrs", "{} {} {}", as_euler_angles.0, as_euler_angles.1, as_euler_angles.2);
+
+		Ok(Input::Orientation((orientation, processed_imu_data, instant)))
+	}
+}
+
@@ -1,5 +1,4 @@
 pub mod soft_arm_input_controller;
-pub mod lsm9ds1_input_controller;
-pub mod navio_adc_input_controller;
-pub mod navio_rc_input_controller;
+pub mod cpu_temperature_input_controller;
+pub mod imu_input_controller;
 // pub mod remote_input_controller;
\ No newline at end of file
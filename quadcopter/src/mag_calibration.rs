@@ -0,0 +1,134 @@
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+
+/// Hard-iron offset and soft-iron matrix, such that calibrated samples are
+/// `matrix * (raw - offset)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagCalibration {
+	pub offset: Vector3<f64>,
+	pub matrix: Matrix3<f64>,
+}
+
+impl MagCalibration {
+	pub fn apply(&self, mag: &Vector3<f64>) -> Vector3<f64> {
+		self.matrix * (mag - self.offset)
+	}
+}
+
+/// Minimum number of samples for the fit to be meaningful.
+pub const MIN_SAMPLES: usize = 100;
+
+/// Fits an ellipsoid to magnetometer samples taken in many orientations, by least squares on the
+/// quadric `x'Ax + 2v'x = 1`. The soft-iron matrix maps the ellipsoid onto a sphere whose radius
+/// is the geometric mean of the ellipsoid radii, so that field strength is roughly preserved.
+pub fn fit_ellipsoid(samples: &[Vector3<f64>]) -> anyhow::Result<MagCalibration> {
+	if samples.len() < MIN_SAMPLES {
+		return Err(anyhow!("Not enough magnetometer samples: {} (expected at least {})",
+						   samples.len(), MIN_SAMPLES));
+	}
+
+	let design = DMatrix::from_fn(samples.len(), 9, |i, j| {
+		let s = &samples[i];
+		match j {
+			0 => s.x * s.x,
+			1 => s.y * s.y,
+			2 => s.z * s.z,
+			3 => 2. * s.x * s.y,
+			4 => 2. * s.x * s.z,
+			5 => 2. * s.y * s.z,
+			6 => 2. * s.x,
+			7 => 2. * s.y,
+			_ => 2. * s.z,
+		}
+	});
+
+	let p = design
+		.svd(true, true)
+		.solve(&DVector::from_element(samples.len(), 1.), 1e-12)
+		.map_err(|e| anyhow!("Failed to fit ellipsoid: {}", e))?;
+
+	let a = Matrix3::new(p[0], p[3], p[4],
+						 p[3], p[1], p[5],
+						 p[4], p[5], p[2]);
+	let v = Vector3::new(p[6], p[7], p[8]);
+
+	let a_inverse = a
+		.try_inverse()
+		.ok_or_else(|| anyhow!("Degenerate magnetometer samples"))?;
+	let offset = -(a_inverse * v);
+
+	// Translated to its center, the ellipsoid is (x - c)'M(x - c) = 1
+	let m = a / (1. + offset.dot(&(a * offset)));
+	let eigen = m.symmetric_eigen();
+
+	if eigen.eigenvalues.iter().any(|&eigenvalue| eigenvalue <= 0.) {
+		return Err(anyhow!("Magnetometer samples do not fit an ellipsoid"));
+	}
+
+	let radius = eigen.eigenvalues.iter().map(|eigenvalue| eigenvalue.powf(-1. / 6.)).product::<f64>();
+	let sqrt_m = eigen.eigenvectors
+		* Matrix3::from_diagonal(&eigen.eigenvalues.map(f64::sqrt))
+		* eigen.eigenvectors.transpose();
+
+	Ok(MagCalibration {
+		offset,
+		matrix: sqrt_m * radius,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::mag_calibration::fit_ellipsoid;
+	use nalgebra::{Matrix3, Vector3};
+
+	/// Field of constant strength in many directions.
+	fn sphere(radius: f64) -> Vec<Vector3<f64>> {
+		let mut samples = Vec::new();
+
+		for i in 0..20 {
+			let latitude = -1.4 + 2.8 * i as f64 / 19.;
+
+			for j in 0..20 {
+				let longitude = std::f64::consts::TAU * j as f64 / 20.;
+				samples.push(Vector3::new(latitude.cos() * longitude.cos(),
+										  latitude.cos() * longitude.sin(),
+										  latitude.sin()) * radius);
+			}
+		}
+
+		samples
+	}
+
+	#[test]
+	fn hard_iron_test() {
+		let offset = Vector3::new(0.1, -0.2, 0.05);
+		let samples = sphere(0.5).iter().map(|s| s + offset).collect::<Vec<_>>();
+
+		let calibration = fit_ellipsoid(&samples).unwrap();
+
+		assert!((calibration.offset - offset).norm() < 1e-6);
+		assert!((calibration.matrix - Matrix3::identity()).norm() < 1e-6);
+	}
+
+	#[test]
+	fn soft_iron_test() {
+		let offset = Vector3::new(-0.3, 0.15, 0.2);
+		let soft_iron = Matrix3::new(1.2, 0.1, 0.,
+									 0.1, 0.8, 0.05,
+									 0., 0.05, 1.05);
+		let samples = sphere(0.5).iter().map(|s| soft_iron * s + offset).collect::<Vec<_>>();
+
+		let calibration = fit_ellipsoid(&samples).unwrap();
+		assert!((calibration.offset - offset).norm() < 1e-6);
+
+		let norms = samples.iter().map(|s| calibration.apply(s).norm()).collect::<Vec<_>>();
+		let mean = norms.iter().sum::<f64>() / norms.len() as f64;
+
+		assert!(norms.iter().all(|norm| (norm - mean).abs() < 1e-6));
+		assert!((mean - 0.5).abs() < 0.05);
+	}
+
+	#[test]
+	fn not_enough_samples_test() {
+		assert!(fit_ellipsoid(&sphere(0.5)[..10]).is_err());
+	}
+}
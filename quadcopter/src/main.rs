@@ -9,30 +9,49 @@ extern crate log;
 use crossbeam_channel::unbounded;
 use nalgebra::Vector3;
 
+use crate::boards::{Board, BoardDrivers};
 use crate::quadcopter_config::TryIntoLevelFilter;
 use crate::input_controllers::soft_arm_input_controller::SoftArmInputController;
-use crate::input_controllers::navio_adc_input_controller::NavioAdcInputController;
+use crate::input_controllers::cpu_temperature_input_controller::CpuTemperatureInputController;
+use crate::monitors::health_monitor::HealthMonitor;
 use crate::monitors::system_information_monitor::SystemInformationMonitor;
-use crate::input_controllers::navio_rc_input_controller::NavioRcInputController;
-use crate::quadcopter::{LedColor, QuadcopterOutputFrame, QuadcopterInputFrame, QuadcopterCollector};
-use crate::output_controllers::led_output_controller::LedOutputController;
-use crate::output_controllers::navio_esc_output_controller::{QUADCOPTER_ESC_CHANNELS, NavioEscOutputController};
+use crate::quadcopter::{LedColor, QuadcopterOutputFrame, QuadcopterInputFrame, QuadcopterCollector, QUADCOPTER_ESC_CHANNELS};
+use crate::output_controllers::log_led_output_controller::LogLedOutputController;
 use crate::quadcopter_autopilot::QuadcopterAutopilot;
-use crate::input_controllers::lsm9ds1_input_controller::LSM9DS1InputController;
+use crate::input_controllers::imu_input_controller::ImuInputController;
+use crate::mag_calibration::MagCalibration;
+use crate::pre_arm::PreArmChecks;
 
 use autopilot::*;
-use ahrs::Madgwick;
 use black_box::BlackBox;
 use crate::mixer::Mixer;
 use std::error::Error;
 
+mod acc_calibration;
+mod ahrs_config;
+mod altitude_estimator;
+mod altitude_hold;
+mod arming;
+mod battery;
+mod boards;
+mod failsafe;
+mod filter_config;
+mod flight_mode;
+mod gyr_calibration;
+mod heading_hold;
 mod input_controllers;
+mod mag_calibration;
+mod mission;
 mod monitors;
 mod output_controllers;
+mod position_control;
+mod pre_arm;
 mod quadcopter;
 mod quadcopter_autopilot;
 mod quadcopter_config;
+mod replay;
 mod roll_pitch_yaw;
+mod watchdog;
 mod mixer;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -40,6 +59,12 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	// Command line arguments
 	const FLAT_TRIM_ARG: &'static str = "flat-trim";
+	const CALIBRATE_ACC_ARG: &'static str = "calibrate-acc";
+	const CALIBRATE_GYR_ARG: &'static str = "calibrate-gyr";
+	const CALIBRATE_MAG_ARG: &'static str = "calibrate-mag";
+	const SIMULATION_ARG: &'static str = "simulation";
+	const REPLAY_ARG: &'static str = "replay";
+	const MISSION_ARG: &'static str = "mission";
 
 	let args = clap::App::new("Autopilot")
 		.version(env!("CARGO_PKG_VERSION"))
@@ -48,10 +73,40 @@ fn main() -> Result<(), Box<dyn Error>> {
 			.long("flat-trim")
 			.about("Calibrate gyroscope and accelerometer upon start")
 			.takes_value(false))
+		.arg(clap::Arg::new(CALIBRATE_ACC_ARG)
+			.long("calibrate-acc")
+			.about("Calibrate accelerometer offsets, scale factors and misalignment upon start, with the vehicle placed in six orientations")
+			.takes_value(false))
+		.arg(clap::Arg::new(CALIBRATE_GYR_ARG)
+			.long("calibrate-gyr")
+			.about("Calibrate gyroscope bias over a temperature sweep upon start, while the vehicle is at rest and warms up")
+			.takes_value(false))
+		.arg(clap::Arg::new(CALIBRATE_MAG_ARG)
+			.long("calibrate-mag")
+			.about("Calibrate magnetometer upon start, while the vehicle is rotated in all directions")
+			.takes_value(false))
+		.arg(clap::Arg::new(SIMULATION_ARG)
+			.long("simulation")
+			.about("Replace sensors and actuators with a simulated quadcopter")
+			.takes_value(false))
+		.arg(clap::Arg::new(REPLAY_ARG)
+			.long("replay")
+			.about("Replay the inputs of a black box log and print recomputed ESC commands")
+			.value_name("LOG_FILE")
+			.takes_value(true))
+		.arg(clap::Arg::new(MISSION_ARG)
+			.long("mission")
+			.about("Load a waypoint mission, flown while the mission switch is on")
+			.value_name("MISSION_FILE")
+			.takes_value(true))
 		.get_matches();
 
 	// Configuration
-	let mut config = quadcopter_config::read()?;
+	let (mut config, legacy_notices) = quadcopter_config::read()?;
+
+	if let Some(log_file) = args.value_of(REPLAY_ARG) {
+		return Ok(replay::replay(log_file, &config)?);
+	}
 
 	// Log
 	let level_filter = config.log_level_filter
@@ -62,22 +117,69 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	info!("Autopilot {}", env!("CARGO_PKG_VERSION"));
 
+	for notice in legacy_notices {
+		warn!("{}", notice);
+	}
+
+	// Real-time options apply to every stage spawned from now on, supervised or not
+	set_stage_options(config.realtime.clone());
+
+	let board = if args.is_present(SIMULATION_ARG) {
+		Board::Simulation
+	} else {
+		config.board
+	};
+
+	info!("Board: {:?}", board);
+
+	// Missions are validated before any driver is started, so that an invalid one prevents arming
+	let mission = match args.value_of(MISSION_ARG) {
+		Some(mission_file) => {
+			let mission = mission::Mission::load(mission_file)?;
+			info!("Mission: {} waypoints at {} m/s", mission.waypoints.len(), mission.cruise_speed);
+
+			Some(mission)
+		}
+		None => None,
+	};
+
+	let BoardDrivers { imu, imu_self_test_passed, barometer, gnss, rc, power, motors, led, arm_switch_off } = board.drivers(&config)?;
+
 	let armed_input_controller = SoftArmInputController::new();
 	let armed_sender = armed_input_controller.sender();
 
-	// Output controllers
 	let (led_sender,
 		led_receiver) = unbounded::<Option<LedColor>>();
 
-	LedOutputController::new()?
-		.spawn(led_receiver);
-
 	let (esc_channels_sender,
 		esc_channels_receiver) = unbounded::<[f64; QUADCOPTER_ESC_CHANNELS]>();
 
-	NavioEscOutputController::new(config.output_esc_pins)
-		.init()?
-		.spawn(esc_channels_receiver);
+	// Supervisor: the failsafe action disarms through the autopilot, and stops motors and shows the
+	// disarmed LED directly in case the autopilot, collector or dispatcher is the failed stage.
+	// Every output controller is written, as shutdown waits for them
+	let mut supervisor = {
+		let armed_sender = armed_sender.clone();
+		let led_sender = led_sender.clone();
+		let esc_channels_sender = esc_channels_sender.clone();
+
+		Supervisor::new()
+			.with_failsafe(move || {
+				let _ = armed_sender.send(false);
+				let _ = led_sender.send(Some(LedColor::Red));
+				let _ = esc_channels_sender.send([0.; QUADCOPTER_ESC_CHANNELS]);
+			})
+	};
+
+	// Output controllers are restarted, as nothing else can drive motors and LED
+	if let Some(led) = led {
+		led.supervise(&mut supervisor, StagePolicy::Restart, led_receiver);
+	} else {
+		LogLedOutputController::new()
+			.supervise(&mut supervisor, StagePolicy::Restart, led_receiver);
+	}
+
+	MotorOutputController::new(motors)
+		.supervise(&mut supervisor, StagePolicy::Restart, esc_channels_receiver);
 
 	// Dispatcher
 	let (output_frame_sender,
@@ -85,86 +187,222 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	let dispatcher = quadcopter::QuadcopterDispatcher { led_sender, esc_channels_sender };
 
-	dispatcher.spawn(output_frame_receiver);
+	// Without dispatcher, motors would keep their last output
+	dispatcher.supervise(&mut supervisor, StagePolicy::Failsafe, output_frame_receiver);
 
-	// Autopilot
-	let (input_frame_sender,
-		input_frame_receiver) = unbounded::<QuadcopterInputFrame>();
+	// Input controllers
+	let mut imu_input_controller = ImuInputController::new(
+		imu,
+		ahrs_config::ahrs(&config.ahrs),
+		filter_config::imu_filter_chain(&config.filter_acc),
+		filter_config::imu_filter_chain(&config.filter_gyr),
+	).with_magnetometer(config.ahrs_use_magnetometer);
 
-	QuadcopterAutopilot::new(config.pid_values,
-							 config.rates,
-							 config.limits,
-							 Mixer {
-								 min_output: config.output_esc_min_value
-							 })
-		.spawn(input_frame_receiver, output_frame_sender);
+	// Six-position accelerometer calibration
+	if args.is_present(CALIBRATE_ACC_ARG) {
+		info!("Performing six-position accelerometer calibration");
 
-	// Collector
-	let (input_sender, input_receiver) = unbounded::<Input>();
+		let mut measurements = Vec::with_capacity(acc_calibration::POSITIONS.len());
 
-	let collector = QuadcopterCollector::new();
-	collector.spawn(input_receiver, input_frame_sender);
+		for (position, expected) in acc_calibration::POSITIONS.iter() {
+			info!("Place the vehicle {} and press Enter", position);
+			std::io::stdin().read_line(&mut String::new())?;
 
-	// Input controllers
-	const NAVIO2_ACC_GYR_PATH: &'static str = "/dev/spidev0.3";
-	const NAVIO2_MAG_PATH: &'static str = "/dev/spidev0.2";
+			let (acc_average, _, _) = imu_input_controller.average_samples()?;
+			measurements.push((acc_average, Vector3::from_column_slice(expected)));
+		}
+
+		let acc_calibration = acc_calibration::solve(&measurements)?;
+
+		info!("Accelerometer calibration: offset {:?}, matrix {:?}",
+			  acc_calibration.offset.as_slice(),
+			  acc_calibration.matrix.as_slice());
+
+		let offset = &acc_calibration.offset;
+		config.calibration_acc = [offset.x, offset.y, offset.z];
+		config.calibration_acc_matrix = quadcopter_config::matrix_to_rows(&acc_calibration.matrix);
+
+		quadcopter_config::save(&config)?;
+	}
+
+	imu_input_controller.set_acc_matrix(quadcopter_config::matrix_from_rows(&config.calibration_acc_matrix));
+
+	// Gyroscope temperature calibration
+	if args.is_present(CALIBRATE_GYR_ARG) {
+		const GYR_CALIBRATION_DURATION: std::time::Duration = std::time::Duration::from_secs(15 * 60);
 
-	let mut lsm9ds1 = LSM9DS1InputController::new(
-		NAVIO2_ACC_GYR_PATH,
-		NAVIO2_MAG_PATH,
-		Madgwick::<f64>::new(config.ahrs_madgwick_beta),
-	)?;
+		info!("Performing gyroscope temperature calibration: keep the vehicle at rest while it warms up for {} s",
+			  GYR_CALIBRATION_DURATION.as_secs());
+
+		let samples = imu_input_controller.collect_gyr_samples(GYR_CALIBRATION_DURATION)?;
+		config.calibration_gyr_bias = gyr_calibration::fit(&samples, gyr_calibration::DEGREE)?;
+
+		info!("Gyroscope bias model: {:?}", config.calibration_gyr_bias);
+
+		quadcopter_config::save(&config)?;
+	}
 
 	// Flat-trim calibration
-	let (acc_offset, gyr_offset) = {
+	let (acc_offset, gyr_bias) = {
 		if args.is_present(FLAT_TRIM_ARG) {
 			info!("Performing flat trim calibration");
 
-			let (acc_offset, gyr_offset) = lsm9ds1.calibrate()?;
+			let (acc_offset, gyr_bias) = imu_input_controller.calibrate()?;
 
 			config.calibration_acc = [acc_offset.x, acc_offset.y, acc_offset.z];
-			config.calibration_gyr = [gyr_offset.x, gyr_offset.y, gyr_offset.z];
+			config.calibration_gyr_bias = gyr_bias.clone();
 
 			quadcopter_config::save(&config)?;
 
-			(acc_offset, gyr_offset)
+			(acc_offset, gyr_bias)
 		} else {
 			info!("Using previously saved calibration");
 			let acc_offset = Vector3::new(config.calibration_acc[0],
 										  config.calibration_acc[1],
 										  config.calibration_acc[2]);
 
-			let gyr_offset = Vector3::new(config.calibration_gyr[0],
-										  config.calibration_gyr[1],
-										  config.calibration_gyr[2]);
+			(acc_offset, config.calibration_gyr_bias.clone())
+		}
+	};
+
+	imu_input_controller.set_calibration(acc_offset, gyr_bias);
+
+	// Magnetometer calibration
+	let mag_calibration = {
+		if args.is_present(CALIBRATE_MAG_ARG) {
+			const MAG_CALIBRATION_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+
+			info!("Performing magnetometer calibration: rotate the vehicle slowly in all directions for {} s",
+				  MAG_CALIBRATION_DURATION.as_secs());
+
+			let samples = imu_input_controller.collect_mag_samples(MAG_CALIBRATION_DURATION)?;
+			let mag_calibration = mag_calibration::fit_ellipsoid(&samples)?;
 
-			(acc_offset, gyr_offset)
+			info!("Magnetometer calibration: offset {:?}, matrix {:?}",
+				  mag_calibration.offset.as_slice(),
+				  mag_calibration.matrix.as_slice());
+
+			let (offset, matrix) = (&mag_calibration.offset, &mag_calibration.matrix);
+			config.calibration_mag_offset = [offset.x, offset.y, offset.z];
+			config.calibration_mag_matrix = quadcopter_config::matrix_to_rows(matrix);
+
+			quadcopter_config::save(&config)?;
+
+			mag_calibration
+		} else {
+			let offset = config.calibration_mag_offset;
+
+			MagCalibration {
+				offset: Vector3::new(offset[0], offset[1], offset[2]),
+				matrix: quadcopter_config::matrix_from_rows(&config.calibration_mag_matrix),
+			}
 		}
 	};
 
-	lsm9ds1.set_calibration(acc_offset, gyr_offset);
+	imu_input_controller.set_mag_calibration(mag_calibration);
 
-	lsm9ds1.spawn(input_sender.clone());
+	// Autopilot
+	let (input_frame_sender,
+		input_frame_receiver) = unbounded::<QuadcopterInputFrame>();
+
+	// Calibrations are complete once the calibration procedures above have been run, simulated
+	// sensors requiring none
+	let missing_calibrations = match board {
+		Board::Simulation => Vec::new(),
+		_ => quadcopter_config::missing_calibrations(&config),
+	};
+
+	if !missing_calibrations.is_empty() {
+		warn!("Missing calibrations: {}", missing_calibrations.join(", "));
+	}
+
+	let pre_arm_checks = PreArmChecks::new(config.pre_arm,
+										   config.lsm9ds1.acc_range.full_scale(),
+										   config.lsm9ds1.gyr_range.full_scale(),
+										   missing_calibrations.is_empty())
+		.with_imu_self_test(imu_self_test_passed);
+
+	let mut quadcopter_autopilot = QuadcopterAutopilot::new(config.pid_values,
+															&config.filter_d_term,
+															config.rates,
+															config.limits,
+															config.acro_rates,
+															Mixer {
+																min_output: config.output_esc_min_value
+															},
+															config.altitude_hold,
+															config.heading_hold,
+															config.position_control,
+															config.failsafe,
+															config.arming,
+															config.watchdog)
+		.with_pre_arm_checks(pre_arm_checks);
+
+	if let Some(mission) = mission {
+		quadcopter_autopilot = quadcopter_autopilot.with_mission(mission);
+	}
+
+	if let Some(arm_switch_off) = arm_switch_off {
+		quadcopter_autopilot = quadcopter_autopilot.with_arm_switch_off_notification(arm_switch_off);
+	}
+
+	// Controller states cannot be trusted after a panic
+	quadcopter_autopilot.supervise(&mut supervisor, StagePolicy::Abort, input_frame_receiver, output_frame_sender);
+
+	// Collector
+	let (input_sender, input_receiver) = unbounded::<Input>();
+
+	let collector = QuadcopterCollector::new(config.battery);
+	collector.supervise(&mut supervisor, StagePolicy::Failsafe, input_receiver, input_frame_sender);
 
-	NavioAdcInputController::new()?
-		.spawn(input_sender.clone());
+	// Sensors are read once calibrated, and reported as stale by the health registry beyond the
+	// maximum ages of their inputs. Input controllers are restarted, the autopilot handling stale
+	// inputs meanwhile
+	let max_age = |seconds| Some(std::time::Duration::from_secs_f64(seconds));
 
-	NavioRcInputController::new(config.input_rc_range)?
-		.spawn(input_sender.clone());
+	imu_input_controller.supervise(&mut supervisor,
+								   StagePolicy::Restart,
+								   input_sender.clone(),
+								   max_age(config.watchdog.max_orientation_age));
 
-	armed_input_controller.spawn(input_sender.clone());
+	BarometerInputController::new(barometer)
+		.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), max_age(config.watchdog.max_barometer_age));
+
+	GnssInputController::new(gnss)
+		.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), max_age(config.watchdog.max_gnss_age));
+
+	PowerInputController::new(power)
+		.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), max_age(config.watchdog.max_navio_adc_age));
+
+	RcInputController::new(rc)
+		.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), max_age(config.watchdog.max_rc_channels_age));
+
+	CpuTemperatureInputController::new()
+		.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), None);
+
+	armed_input_controller.supervise(&mut supervisor, StagePolicy::Restart, input_sender.clone(), None);
 
 	// Monitors
-	SystemInformationMonitor::new().spawn();
+	SystemInformationMonitor::new().supervise(&mut supervisor, StagePolicy::Restart);
+	HealthMonitor::new().supervise(&mut supervisor, StagePolicy::Restart);
 
 	armed_sender.send(true)?;
 
-	info!("Press enter to stop autopilot");
-	std::io::stdin().read_line(&mut String::new())?;
+	let shutdown_handle = supervisor.shutdown_handle();
+
+	std::thread::spawn(move || {
+		info!("Press enter to stop autopilot");
+
+		if let Err(e) = std::io::stdin().read_line(&mut String::new()) {
+			error!("Failed to read standard input: {}", e);
+		}
 
-	armed_sender.send(false)?;
+		shutdown_handle.request();
+	});
 
-	std::thread::sleep(std::time::Duration::from_millis(100));
+	// Runs until stopped, or aborted by a failed stage. Motors are stopped in both cases
+	let result = supervisor.wait();
+	supervisor.shutdown(std::time::Duration::from_millis(500));
 
-	Ok(())
+	result
 }
\ No newline at end of file
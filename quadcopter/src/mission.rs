@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use navigation::{GeodeticPosition, LocalFrame, NavigationEstimate};
+
+use crate::position_control::PositionSetpoint;
+
+/// Highest altitude (m) above the origin accepted for a waypoint.
+pub const MAX_ALTITUDE: f64 = 120.;
+/// Longest distance (m) accepted between two successive waypoints.
+pub const MAX_LEG_DISTANCE: u32 = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Waypoint {
+	/// Degrees, positive northwards.
+	pub latitude: f64,
+	/// Degrees, positive eastwards.
+	pub longitude: f64,
+	/// Altitude (m) above the navigation origin, i.e. the first GNSS fix.
+	pub altitude: f64,
+	/// Horizontal and vertical distance (m) within which the waypoint is reached.
+	pub acceptance_radius: f64,
+	/// Time (s) spent at the waypoint before heading to the next one.
+	pub loiter_time: f64,
+}
+
+/// Whether a value is within [min, max], NaN being out of range.
+fn within(value: f64, min: f64, max: f64) -> bool {
+	value >= min && value <= max
+}
+
+/// Waypoints flown in order, the last one being held once reached.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Mission {
+	/// Horizontal speed (m/s) between waypoints.
+	pub cruise_speed: f64,
+	pub waypoints: Vec<Waypoint>,
+}
+
+impl Mission {
+	/// Reads and validates a mission file.
+	pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+		let mission: Mission = serde_json::from_reader(File::open(path)?)?;
+		mission.validate()?;
+
+		Ok(mission)
+	}
+
+	/// Checks that every waypoint is within reasonable bounds, so that a typo cannot send the
+	/// aircraft away once armed.
+	pub fn validate(&self) -> anyhow::Result<()> {
+		if self.waypoints.is_empty() {
+			return Err(anyhow!("Mission has no waypoint"));
+		}
+
+		if !within(self.cruise_speed, f64::MIN_POSITIVE, f64::MAX) {
+			return Err(anyhow!("Invalid cruise speed: {} m/s", self.cruise_speed));
+		}
+
+		for (i, waypoint) in self.waypoints.iter().enumerate() {
+			if !(within(waypoint.latitude, -90., 90.) && within(waypoint.longitude, -180., 180.)) {
+				return Err(anyhow!("Waypoint {}: invalid coordinates ({}, {})",
+								   i, waypoint.latitude, waypoint.longitude));
+			}
+
+			if !within(waypoint.altitude, f64::MIN_POSITIVE, MAX_ALTITUDE) {
+				return Err(anyhow!("Waypoint {}: altitude {} m out of (0, {}]",
+								   i, waypoint.altitude, MAX_ALTITUDE));
+			}
+
+			if !within(waypoint.acceptance_radius, f64::MIN_POSITIVE, f64::MAX) {
+				return Err(anyhow!("Waypoint {}: invalid acceptance radius {} m", i, waypoint.acceptance_radius));
+			}
+
+			if !within(waypoint.loiter_time, 0., f64::MAX) {
+				return Err(anyhow!("Waypoint {}: invalid loiter time {} s", i, waypoint.loiter_time));
+			}
+		}
+
+		for (i, leg) in self.waypoints.windows(2).enumerate() {
+			let leg_distance = navigation::distance(
+				(leg[0].latitude as f32, leg[0].longitude as f32),
+				(leg[1].latitude as f32, leg[1].longitude as f32));
+
+			if leg_distance > MAX_LEG_DISTANCE {
+				return Err(anyhow!("Waypoints {} and {} are {} m apart (at most {} m)",
+								   i, i + 1, leg_distance, MAX_LEG_DISTANCE));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Sequences the waypoints of a mission, producing position setpoints.
+pub struct MissionExecutor {
+	mission: Mission,
+	/// Position control gain, so that cruise ends where position control reaches cruise speed.
+	position_gain: f64,
+	index: usize,
+	/// Instant the current waypoint was reached.
+	arrival: Option<Instant>,
+	frame: Option<LocalFrame>,
+}
+
+impl MissionExecutor {
+	pub fn new(mission: Mission, position_gain: f64) -> Self {
+		Self {
+			mission,
+			position_gain,
+			index: 0,
+			arrival: None,
+			frame: None,
+		}
+	}
+
+	/// Index of the waypoint currently flown to.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	/// Restarts the mission from its first waypoint.
+	pub fn reset(&mut self) {
+		self.index = 0;
+		self.arrival = None;
+		self.frame = None;
+	}
+
+	/// Setpoint towards the current waypoint, given the current yaw (rad, counterclockwise from
+	/// north). The aircraft cruises towards the waypoint, facing it, until position control
+	/// takes over on approach.
+	pub fn setpoint(&mut self, estimate: &NavigationEstimate, yaw: f64, instant: Instant) -> PositionSetpoint {
+		let frame = self.frame.get_or_insert_with(|| LocalFrame::new(estimate.origin));
+		let current = frame.to_geodetic(estimate.position);
+
+		let waypoint = self.mission.waypoints[self.index];
+		let waypoint_ned = frame.to_ned(&GeodeticPosition {
+			latitude: waypoint.latitude,
+			longitude: waypoint.longitude,
+			altitude: estimate.origin.altitude + waypoint.altitude,
+		});
+
+		let from = (current.latitude as f32, current.longitude as f32);
+		let to = (waypoint.latitude as f32, waypoint.longitude as f32);
+		let horizontal_distance = navigation::distance(from, to) as f64;
+		let vertical_distance = (waypoint_ned[2] - estimate.position[2]).abs();
+
+		// Waypoint sequencing
+		if horizontal_distance <= waypoint.acceptance_radius && vertical_distance <= waypoint.acceptance_radius {
+			let index = self.index;
+			let arrival = *self.arrival.get_or_insert_with(|| {
+				info!("Waypoint {} reached", index);
+				instant
+			});
+
+			let loitered = instant.saturating_duration_since(arrival) >= Duration::from_secs_f64(waypoint.loiter_time);
+
+			if loitered && self.index + 1 < self.mission.waypoints.len() {
+				self.index += 1;
+				self.arrival = None;
+				info!("Heading to waypoint {}", self.index);
+
+				return self.setpoint(estimate, yaw, instant);
+			}
+		}
+
+		// Bearing is kept once close to the waypoint, where it is meaningless
+		let bearing = navigation::heading_deg(from, to).to_radians() as f64;
+		let target_yaw = if horizontal_distance > waypoint.acceptance_radius { -bearing } else { yaw };
+
+		// Cruise speed is reduced while the aircraft is not facing the waypoint
+		let heading_error = navigation::angle_difference(target_yaw as f32, yaw as f32) as f64;
+		let cruise_speed = self.mission.cruise_speed * heading_error.cos().max(0.);
+
+		let approach_distance = self.mission.cruise_speed / self.position_gain;
+
+		let (position, velocity) = if horizontal_distance > approach_distance {
+			// Cruise: horizontal velocity only, position control correcting altitude
+			([estimate.position[0], estimate.position[1], waypoint_ned[2]],
+			 [cruise_speed * bearing.cos(), cruise_speed * bearing.sin(), 0.])
+		} else {
+			(waypoint_ned, [0.; 3])
+		};
+
+		debug!(target: "mission", "{} {} {} {}",
+			   self.index,
+			   horizontal_distance,
+			   vertical_distance,
+			   bearing);
+
+		PositionSetpoint { position, velocity, yaw: target_yaw }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::mission::{Mission, MissionExecutor, Waypoint};
+	use navigation::{GeodeticPosition, LocalFrame, NavigationEstimate};
+	use std::time::{Duration, Instant};
+
+	const ORIGIN: GeodeticPosition = GeodeticPosition {
+		latitude: 48.713202,
+		longitude: 2.200641,
+		altitude: 160.,
+	};
+
+	const POSITION_GAIN: f64 = 0.5;
+
+	/// Waypoints 100 m north of the origin, then 100 m east of it.
+	fn mission() -> Mission {
+		let frame = LocalFrame::new(ORIGIN);
+		let north = frame.to_geodetic([100., 0., 0.]);
+		let east = frame.to_geodetic([0., 100., 0.]);
+
+		Mission {
+			cruise_speed: 3.,
+			waypoints: vec![
+				Waypoint {
+					latitude: north.latitude,
+					longitude: north.longitude,
+					altitude: 10.,
+					acceptance_radius: 2.,
+					loiter_time: 1.,
+				},
+				Waypoint {
+					latitude: east.latitude,
+					longitude: east.longitude,
+					altitude: 20.,
+					acceptance_radius: 2.,
+					loiter_time: 0.,
+				},
+			],
+		}
+	}
+
+	fn estimate(position: [f64; 3]) -> NavigationEstimate {
+		NavigationEstimate {
+			position,
+			velocity: [0.; 3],
+			position_std: [1.; 3],
+			origin: ORIGIN,
+			instant: Instant::now(),
+		}
+	}
+
+	#[test]
+	fn validate_test() {
+		assert!(mission().validate().is_ok());
+
+		let mut empty = mission();
+		empty.waypoints.clear();
+		assert!(empty.validate().is_err());
+
+		let mut too_high = mission();
+		too_high.waypoints[1].altitude = 500.;
+		assert!(too_high.validate().is_err());
+
+		let mut too_far = mission();
+		too_far.waypoints[1].latitude += 0.1;
+		assert!(too_far.validate().is_err());
+
+		let mut invalid_radius = mission();
+		invalid_radius.waypoints[0].acceptance_radius = f64::NAN;
+		assert!(invalid_radius.validate().is_err());
+	}
+
+	#[test]
+	fn cruise_test() {
+		let mut executor = MissionExecutor::new(mission(), POSITION_GAIN);
+
+		// Facing north, cruising towards the first waypoint and climbing
+		let setpoint = executor.setpoint(&estimate([0., 0., 0.]), 0., Instant::now());
+		assert!((setpoint.velocity[0] - 3.).abs() < 1e-3);
+		assert!(setpoint.velocity[1].abs() < 1e-3);
+		assert!((setpoint.position[2] + 10.).abs() < 1e-3);
+		assert!(setpoint.yaw.abs() < 1e-3);
+
+		// Facing east, the aircraft turns before cruising
+		let setpoint = executor.setpoint(&estimate([0., 0., 0.]), -std::f64::consts::FRAC_PI_2, Instant::now());
+		assert!(setpoint.velocity[0].abs() < 1e-3);
+	}
+
+	#[test]
+	fn sequence_test() {
+		let mut executor = MissionExecutor::new(mission(), POSITION_GAIN);
+		let instant = Instant::now();
+
+		// Approaching the first waypoint, position control takes over
+		let setpoint = executor.setpoint(&estimate([97., 0., -10.]), 0., instant);
+		assert_eq!(setpoint.velocity, [0.; 3]);
+		assert!((setpoint.position[0] - 100.).abs() < 1e-3);
+
+		// Loitering at the first waypoint
+		executor.setpoint(&estimate([100., 0., -10.]), 0., instant);
+		assert_eq!(executor.index(), 0);
+
+		executor.setpoint(&estimate([100., 0., -10.]), 0., instant + Duration::from_secs(2));
+		assert_eq!(executor.index(), 1);
+
+		// The last waypoint is held
+		let setpoint = executor.setpoint(&estimate([0., 100., -20.]), 0., instant + Duration::from_secs(3));
+		assert_eq!(executor.index(), 1);
+		assert!((setpoint.position[1] - 100.).abs() < 1e-3);
+	}
+}
@@ -0,0 +1,74 @@
+use std::{
+	error::Error,
+	fmt,
+	fmt::{Display, Formatter},
+	time::{Duration, Instant},
+};
+use autopilot::health::{self, ControllerHealth, HealthStatus};
+use autopilot::Monitor;
+
+/// Health of every registered controller.
+pub struct HealthReport {
+	controllers: Vec<(&'static str, ControllerHealth)>,
+	instant: Instant,
+}
+
+impl HealthReport {
+	fn is_healthy(&self) -> bool {
+		self.controllers
+			.iter()
+			.all(|(_, health)| health.status(self.instant) == HealthStatus::Healthy)
+	}
+}
+
+impl Display for HealthReport {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut separator = "";
+
+		for (controller, health) in &self.controllers {
+			write!(f, "{}{}: ", separator, controller)?;
+			separator = ", ";
+
+			match health.status(self.instant) {
+				HealthStatus::Healthy => write!(f, "healthy")?,
+				HealthStatus::Stale => match health.last_success {
+					Some(last_success) => write!(f, "stale for {:.1} s",
+												 self.instant.saturating_duration_since(last_success).as_secs_f64())?,
+					None => write!(f, "stale, never succeeded")?,
+				},
+				HealthStatus::Failing => match &health.last_error {
+					Some((_, error)) => write!(f, "failing ({} errors, last: {})", health.consecutive_errors, error)?,
+					None => write!(f, "failing")?,
+				},
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Reports controllers that are stale or failing, as registered by their loops.
+pub struct HealthMonitor;
+
+impl HealthMonitor {
+	pub fn new() -> Self {
+		HealthMonitor
+	}
+}
+
+impl Monitor<HealthReport> for HealthMonitor {
+	const DELAY: Option<Duration> = Some(Duration::from_secs(5));
+
+	fn monitor(&mut self) -> Result<HealthReport, Box<dyn Error>> {
+		let report = HealthReport {
+			controllers: health::health(),
+			instant: Instant::now(),
+		};
+
+		if report.is_healthy() {
+			Ok(report)
+		} else {
+			Err(format!("Unhealthy controllers: {}", report).into())
+		}
+	}
+}
@@ -1 +1,2 @@
+pub mod health_monitor;
 pub mod system_information_monitor;
@@ -0,0 +1,29 @@
+use std::error::Error;
+
+use crate::quadcopter::LedColor;
+use autopilot::OutputController;
+
+/// Logs LED color changes, for platforms without a LED such as simulation. Changes are logged at
+/// debug level, as pre-arm failures blink the LED.
+pub struct LogLedOutputController {
+	last_output: Option<LedColor>,
+}
+
+impl LogLedOutputController {
+	pub fn new() -> Self {
+		Self {
+			last_output: None,
+		}
+	}
+}
+
+impl OutputController<Option<LedColor>> for LogLedOutputController {
+	fn write_output(&mut self, output: Option<LedColor>) -> Result<(), Box<dyn Error>> {
+		if output != self.last_output {
+			debug!(target: "led", "{:?}", output);
+			self.last_output = output;
+		}
+
+		Ok(())
+	}
+}
@@ -1,2 +1,2 @@
 pub mod led_output_controller;
-pub mod navio_esc_output_controller;
\ No newline at end of file
+pub mod log_led_output_controller;
\ No newline at end of file
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+use navigation::NavigationEstimate;
+
+use crate::roll_pitch_yaw::RollPitch;
+
+const G: f64 = 9.80665;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct PositionControlConfig {
+	/// Horizontal speed (m/s) commanded per meter of position error.
+	pub position_gain: f64,
+	/// Horizontal acceleration (m/s²) commanded per m/s of velocity error.
+	pub velocity_gain: f64,
+	/// Horizontal speed limit (m/s).
+	pub max_speed: f64,
+	/// Roll and pitch limit (rad).
+	pub max_tilt: f64,
+	/// Climb rate (m/s) commanded per meter of altitude error.
+	pub altitude_gain: f64,
+	/// Climb and descent rate limit (m/s).
+	pub max_climb_rate: f64,
+}
+
+impl Default for PositionControlConfig {
+	fn default() -> Self {
+		PositionControlConfig {
+			position_gain: 0.5,
+			velocity_gain: 1.,
+			max_speed: 5.,
+			max_tilt: 0.35,
+			altitude_gain: 1.,
+			max_climb_rate: 1.5,
+		}
+	}
+}
+
+/// Position and velocity in the local north-east-down frame, with a heading, to be reached by
+/// the position controller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionSetpoint {
+	/// North, east and down coordinates (m).
+	pub position: [f64; 3],
+	/// North, east and down velocity (m/s), followed in addition to position corrections.
+	pub velocity: [f64; 3],
+	/// Yaw (rad), counterclockwise from north as the AHRS yaw.
+	pub yaw: f64,
+}
+
+/// Cascaded proportional controller, turning position errors into velocity commands, then
+/// velocity errors into roll and pitch angles, and altitude errors into climb rates.
+pub struct PositionControl {
+	config: PositionControlConfig,
+}
+
+impl PositionControl {
+	pub fn new(config: PositionControlConfig) -> Self {
+		Self { config }
+	}
+
+	pub fn config(&self) -> &PositionControlConfig {
+		&self.config
+	}
+
+	/// Target roll and pitch angles (rad) and climb rate (m/s) reaching a setpoint, given the
+	/// current yaw (rad).
+	pub fn output(&self,
+				  setpoint: &PositionSetpoint,
+				  estimate: &NavigationEstimate,
+				  yaw: f64) -> (RollPitch<f64>, f64) {
+		let config = &self.config;
+
+		// Horizontal velocity command, limited in norm so that its direction is kept
+		let mut velocity = [0.; 2];
+		for (i, v) in velocity.iter_mut().enumerate() {
+			*v = setpoint.velocity[i] + (setpoint.position[i] - estimate.position[i]) * config.position_gain;
+		}
+
+		let speed = velocity[0].hypot(velocity[1]);
+		if speed > config.max_speed {
+			velocity[0] *= config.max_speed / speed;
+			velocity[1] *= config.max_speed / speed;
+		}
+
+		let acceleration_north = (velocity[0] - estimate.velocity[0]) * config.velocity_gain;
+		let acceleration_east = (velocity[1] - estimate.velocity[1]) * config.velocity_gain;
+
+		// Body axes are forward, left and up: pitching nose down accelerates forward, and rolling
+		// right accelerates rightwards
+		let (sin_yaw, cos_yaw) = yaw.sin_cos();
+		let acceleration_forward = acceleration_north * cos_yaw - acceleration_east * sin_yaw;
+		let acceleration_right = acceleration_north * sin_yaw + acceleration_east * cos_yaw;
+
+		let tilt = |acceleration: f64| (acceleration / G).atan()
+			.max(-config.max_tilt)
+			.min(config.max_tilt);
+
+		let angles = RollPitch {
+			roll: tilt(acceleration_right),
+			pitch: tilt(acceleration_forward),
+		};
+
+		let climb_rate = ((estimate.position[2] - setpoint.position[2]) * config.altitude_gain
+			- setpoint.velocity[2])
+			.max(-config.max_climb_rate)
+			.min(config.max_climb_rate);
+
+		debug!(target: "position_control", "{} {} {} {} {}",
+			   velocity[0], velocity[1],
+			   angles.roll, angles.pitch,
+			   climb_rate);
+
+		(angles, climb_rate)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::position_control::{PositionControl, PositionControlConfig, PositionSetpoint};
+	use navigation::{GeodeticPosition, NavigationEstimate};
+	use std::f64::consts::FRAC_PI_2;
+	use std::time::Instant;
+
+	fn estimate(position: [f64; 3]) -> NavigationEstimate {
+		NavigationEstimate {
+			position,
+			velocity: [0.; 3],
+			position_std: [1.; 3],
+			origin: GeodeticPosition { latitude: 48.713202, longitude: 2.200641, altitude: 160. },
+			instant: Instant::now(),
+		}
+	}
+
+	#[test]
+	fn direction_test() {
+		let control = PositionControl::new(PositionControlConfig::default());
+		let setpoint = PositionSetpoint { position: [10., 0., -5.], velocity: [0.; 3], yaw: 0. };
+
+		// Facing north, the setpoint is ahead and above
+		let (angles, climb_rate) = control.output(&setpoint, &estimate([0.; 3]), 0.);
+		assert!(angles.pitch > 0.);
+		assert!(angles.roll.abs() < 1e-9);
+		assert!(climb_rate > 0.);
+
+		// Facing west, the setpoint is on the right
+		let (angles, _) = control.output(&setpoint, &estimate([0.; 3]), FRAC_PI_2);
+		assert!(angles.pitch.abs() < 1e-9);
+		assert!(angles.roll > 0.);
+	}
+
+	#[test]
+	fn limits_test() {
+		let config = PositionControlConfig::default();
+		let control = PositionControl::new(config);
+		let setpoint = PositionSetpoint { position: [1000., 1000., -500.], velocity: [0.; 3], yaw: 0. };
+
+		let (angles, climb_rate) = control.output(&setpoint, &estimate([0.; 3]), 0.);
+		assert!(angles.pitch <= config.max_tilt && angles.roll <= config.max_tilt);
+		assert_eq!(climb_rate, config.max_climb_rate);
+	}
+}
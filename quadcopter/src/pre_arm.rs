@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::battery::BatteryState;
+use crate::quadcopter::{LedColor, QuadcopterInputFrame};
+use crate::roll_pitch_yaw::RollPitchYaw;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct PreArmConfig {
+	/// Throttle stick position, within (0., 1.), above which arming is refused.
+	pub max_throttle: f64,
+	/// Roll and pitch (rad) above which arming is refused.
+	pub max_tilt: f64,
+	/// Age (s) above which the last IMU sample is stale.
+	pub max_imu_age: f64,
+	/// Fraction of the IMU full-scale ranges above which samples are saturated.
+	pub imu_saturation: f64,
+	/// Whether a battery must be connected, which can be disabled for bench tests powered over
+	/// USB.
+	pub require_battery: bool,
+	/// CPU temperature (°C) above which arming is refused.
+	pub max_cpu_temperature: f64,
+	/// Control loop rate (Hz) below which arming is refused.
+	pub min_loop_rate: f64,
+}
+
+impl Default for PreArmConfig {
+	fn default() -> Self {
+		PreArmConfig {
+			max_throttle: 0.05,
+			max_tilt: 0.35,
+			max_imu_age: 0.05,
+			imu_saturation: 0.9,
+			require_battery: true,
+			max_cpu_temperature: 75.,
+			min_loop_rate: 200.,
+		}
+	}
+}
+
+/// Conditions verified before arming, in the order they are reported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PreArmCheck {
+	ThrottleLow,
+	Level,
+	ImuFresh,
+	ImuNotSaturated,
+	Calibrated,
+	BatteryHealthy,
+	CpuTemperature,
+	LoopRate,
+	ImuSelfTest,
+}
+
+impl PreArmCheck {
+	/// Number of LED blinks identifying the check.
+	pub fn blinks(&self) -> u32 {
+		*self as u32 + 1
+	}
+}
+
+/// Durations of LED blinks, and of the pause between two sequences.
+const BLINK_ON: Duration = Duration::from_millis(200);
+const BLINK_OFF: Duration = Duration::from_millis(200);
+const BLINK_PAUSE: Duration = Duration::from_millis(1000);
+/// Time constant (s) of the control loop period low pass filter.
+const LOOP_PERIOD_TIME_CONSTANT: f64 = 1.;
+
+/// Verifies that the aircraft is safe to arm, reporting failed checks in the log and by blinking
+/// the LED as many times as the number of the first failed check.
+pub struct PreArmChecks {
+	config: PreArmConfig,
+	/// Accelerometer (m/s²) and gyroscope (rad/s) full-scale ranges.
+	acc_full_scale: f64,
+	gyr_full_scale: f64,
+	calibrated: bool,
+	imu_self_test_passed: bool,
+	/// Filtered control loop period (s), and instant of the last loop.
+	loop_period: Option<f64>,
+	last_loop: Option<Instant>,
+	failures: Vec<PreArmCheck>,
+	/// Instant the current failures were first reported, starting LED sequences.
+	failure_instant: Option<Instant>,
+}
+
+impl PreArmChecks {
+	pub fn new(config: PreArmConfig, acc_full_scale: f64, gyr_full_scale: f64, calibrated: bool) -> Self {
+		Self {
+			config,
+			acc_full_scale,
+			gyr_full_scale,
+			calibrated,
+			imu_self_test_passed: true,
+			loop_period: None,
+			last_loop: None,
+			failures: Vec::new(),
+			failure_instant: None,
+		}
+	}
+
+	/// Result of the IMU startup self-test, assumed passed if not given.
+	pub fn with_imu_self_test(mut self, passed: bool) -> Self {
+		self.imu_self_test_passed = passed;
+		self
+	}
+
+	/// Measures the control loop rate, to be called on every control loop.
+	pub fn update_loop_rate(&mut self, instant: Instant) {
+		if let Some(last_loop) = self.last_loop {
+			let period = instant.saturating_duration_since(last_loop).as_secs_f64();
+
+			let filtered_period = match self.loop_period {
+				Some(loop_period) => {
+					loop_period + (period - loop_period) * period / (LOOP_PERIOD_TIME_CONSTANT + period)
+				}
+				None => period,
+			};
+
+			self.loop_period = Some(filtered_period);
+		}
+
+		self.last_loop = Some(instant);
+	}
+
+	/// Failed checks for an input frame, at the current instant.
+	fn failed_checks(&self, input_frame: &QuadcopterInputFrame, instant: Instant) -> Vec<PreArmCheck> {
+		let config = &self.config;
+		let (quaternion, imu_data, _) = &input_frame.orientation;
+		let orientation: RollPitchYaw<f64> = quaternion.euler_angles().into();
+
+		let throttle_low = input_frame.rc_channels
+			.map_or(false, |rc_channels| rc_channels[2] <= config.max_throttle);
+
+		let level = orientation.roll.abs() <= config.max_tilt && orientation.pitch.abs() <= config.max_tilt;
+
+		let imu_age = instant.saturating_duration_since(imu_data.instant);
+		let imu_fresh = imu_age <= Duration::from_secs_f64(config.max_imu_age);
+
+		let imu_not_saturated = imu_data.acc.amax() < self.acc_full_scale * config.imu_saturation
+			&& imu_data.gyr.amax() < self.gyr_full_scale * config.imu_saturation;
+
+		let battery_healthy = match input_frame.battery {
+			Some(battery) => battery.state == BatteryState::Nominal,
+			None => !config.require_battery,
+		};
+
+		// Unavailable temperatures are reported by the CPU temperature input controller
+		let cpu_temperature = input_frame.cpu_temperature
+			.map_or(true, |cpu_temperature| cpu_temperature <= config.max_cpu_temperature);
+
+		let loop_rate = self.loop_period
+			.map_or(false, |loop_period| loop_period * config.min_loop_rate <= 1.);
+
+		[(PreArmCheck::ThrottleLow, throttle_low),
+			(PreArmCheck::Level, level),
+			(PreArmCheck::ImuFresh, imu_fresh),
+			(PreArmCheck::ImuNotSaturated, imu_not_saturated),
+			(PreArmCheck::Calibrated, self.calibrated),
+			(PreArmCheck::BatteryHealthy, battery_healthy),
+			(PreArmCheck::CpuTemperature, cpu_temperature),
+			(PreArmCheck::LoopRate, loop_rate),
+			(PreArmCheck::ImuSelfTest, self.imu_self_test_passed)]
+			.iter()
+			.filter(|(_, passed)| !passed)
+			.map(|&(check, _)| check)
+			.collect()
+	}
+
+	/// Runs every check, logging failures when they change. Returns whether arming is allowed.
+	pub fn check(&mut self, input_frame: &QuadcopterInputFrame, instant: Instant) -> bool {
+		let failures = self.failed_checks(input_frame, instant);
+
+		if failures != self.failures {
+			if failures.is_empty() {
+				info!("Pre-arm checks passed");
+			} else {
+				warn!("Arming refused, failed pre-arm checks: {:?}", failures);
+			}
+
+			self.failure_instant = Some(instant);
+			self.failures = failures;
+		}
+
+		self.failures.is_empty()
+	}
+
+	/// Forgets reported failures, once arming is no longer requested.
+	pub fn clear(&mut self) {
+		self.failures.clear();
+		self.failure_instant = None;
+	}
+
+	/// LED color blinking the number of the first failed check, if any: red while on, `None`
+	/// while off.
+	pub fn led(&self, instant: Instant) -> Option<Option<LedColor>> {
+		let check = self.failures.first()?;
+		let failure_instant = self.failure_instant?;
+
+		let blink = BLINK_ON + BLINK_OFF;
+		let sequence = blink * check.blinks() + BLINK_PAUSE;
+
+		let elapsed = instant.saturating_duration_since(failure_instant).as_nanos() % sequence.as_nanos();
+		let blinking = elapsed < (blink * check.blinks()).as_nanos()
+			&& elapsed % blink.as_nanos() < BLINK_ON.as_nanos();
+
+		Some(if blinking { Some(LedColor::Red) } else { None })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::battery::{BatteryEstimate, BatteryState};
+	use crate::pre_arm::{PreArmCheck, PreArmChecks, PreArmConfig};
+	use crate::quadcopter::{InputTimestamps, LedColor, QuadcopterInputFrame};
+	use autopilot::{ImuData, NavioAdcData};
+	use nalgebra::{UnitQuaternion, Vector3};
+	use std::time::{Duration, Instant};
+
+	const ACC_FULL_SCALE: f64 = 39.2;
+	const GYR_FULL_SCALE: f64 = 8.7;
+
+	fn input_frame(instant: Instant) -> QuadcopterInputFrame {
+		let mut rc_channels = [0.5; 16];
+		rc_channels[2] = 0.;
+
+		QuadcopterInputFrame {
+			navio_adc: NavioAdcData::default(),
+			orientation: (UnitQuaternion::identity(),
+						  ImuData { acc: Vector3::new(0., 0., 9.81), instant, ..ImuData::default() },
+						  instant),
+			rc_channels: Some(rc_channels),
+			soft_armed: true,
+			altitude: None,
+			navigation: None,
+			battery: Some(BatteryEstimate {
+				voltage: 12.6,
+				current: 0.,
+				resting_voltage: 12.6,
+				consumed: 0.,
+				remaining: 100.,
+				state: BatteryState::Nominal,
+				instant,
+			}),
+			cpu_temperature: Some(50.),
+			timestamps: InputTimestamps::default(),
+			instant,
+		}
+	}
+
+	/// Checks with a control loop running at 500 Hz.
+	fn checks(calibrated: bool, instant: Instant) -> PreArmChecks {
+		let mut checks = PreArmChecks::new(PreArmConfig::default(), ACC_FULL_SCALE, GYR_FULL_SCALE, calibrated);
+
+		for i in 0..100 {
+			checks.update_loop_rate(instant + Duration::from_millis(2 * i));
+		}
+
+		checks
+	}
+
+	#[test]
+	fn passed_test() {
+		let instant = Instant::now();
+		let mut checks = checks(true, instant);
+
+		assert!(checks.check(&input_frame(instant), instant));
+		assert_eq!(checks.led(instant), None);
+	}
+
+	#[test]
+	fn failures_test() {
+		let instant = Instant::now();
+		// Without control loop rate measurement
+		let mut checks = PreArmChecks::new(PreArmConfig::default(), ACC_FULL_SCALE, GYR_FULL_SCALE, false)
+			.with_imu_self_test(false);
+
+		let mut frame = input_frame(instant);
+		frame.rc_channels.as_mut().unwrap()[2] = 0.5;
+		frame.orientation.0 = UnitQuaternion::from_euler_angles(0.5, 0., 0.);
+		frame.orientation.1.gyr = Vector3::new(0., 0., GYR_FULL_SCALE);
+		frame.battery = None;
+		frame.cpu_temperature = Some(90.);
+
+		let later = instant + Duration::from_secs(1);
+		assert!(!checks.check(&frame, later));
+		assert_eq!(checks.failures, vec![
+			PreArmCheck::ThrottleLow,
+			PreArmCheck::Level,
+			PreArmCheck::ImuFresh,
+			PreArmCheck::ImuNotSaturated,
+			PreArmCheck::Calibrated,
+			PreArmCheck::BatteryHealthy,
+			PreArmCheck::CpuTemperature,
+			PreArmCheck::LoopRate,
+			PreArmCheck::ImuSelfTest,
+		]);
+	}
+
+	#[test]
+	fn led_test() {
+		let instant = Instant::now();
+		let mut checks = checks(true, instant);
+
+		let mut frame = input_frame(instant);
+		frame.orientation.0 = UnitQuaternion::from_euler_angles(0., 0.5, 0.);
+		checks.check(&frame, instant);
+
+		// Two blinks, then a pause
+		let led = |milliseconds| checks.led(instant + Duration::from_millis(milliseconds)).unwrap();
+		assert_eq!(led(100), Some(LedColor::Red));
+		assert_eq!(led(300), None);
+		assert_eq!(led(500), Some(LedColor::Red));
+		assert_eq!(led(700), None);
+		assert_eq!(led(900), None);
+		assert_eq!(led(1900), Some(LedColor::Red));
+	}
+}
@@ -1,13 +1,18 @@
 use crossbeam_channel::Sender;
 use std::time::Instant;
 
-use autopilot::{Collector, Input, Dispatcher, ImuData, RcChannels, NavioAdcData, Orientation};
-use nalgebra::{UnitQuaternion, Quaternion};
+use autopilot::{Collector, Input, Dispatcher, GnssFix, ImuData, RcChannels, NavioAdcData, Orientation};
+use black_box::Frame;
 
-use crate::output_controllers::navio_esc_output_controller::QUADCOPTER_ESC_CHANNELS;
+use crate::altitude_estimator::{AltitudeEstimate, AltitudeEstimator, G};
+use crate::battery::{Battery, BatteryConfig, BatteryEstimate};
+use nalgebra::{UnitQuaternion, Quaternion, Vector3};
+use navigation::{GeodeticPosition, NavigationEstimate, NavigationEstimator};
+
+pub const QUADCOPTER_ESC_CHANNELS: usize = 4;
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LedColor {
 	Red,
 	Green,
@@ -37,11 +42,14 @@ impl Dispatcher<QuadcopterOutputFrame> for QuadcopterDispatcher {
 }
 
 pub struct QuadcopterCollector {
-	input_frame: QuadcopterInputFrame
+	input_frame: QuadcopterInputFrame,
+	altitude_estimator: AltitudeEstimator,
+	navigation_estimator: NavigationEstimator,
+	battery: Battery,
 }
 
 impl QuadcopterCollector {
-	pub fn new() -> Self {
+	pub fn new(battery: BatteryConfig) -> Self {
 		Self {
 			input_frame: QuadcopterInputFrame {
 				navio_adc: NavioAdcData::default(),
@@ -50,7 +58,16 @@ impl QuadcopterCollector {
 							  Instant::now()),
 				rc_channels: RcChannels::default(),
 				soft_armed: false,
-			}
+				altitude: None,
+				navigation: None,
+				battery: None,
+				cpu_temperature: None,
+				timestamps: InputTimestamps::default(),
+				instant: Instant::now(),
+			},
+			altitude_estimator: AltitudeEstimator::new(),
+			navigation_estimator: NavigationEstimator::new(),
+			battery: Battery::new(battery),
 		}
 	}
 }
@@ -61,19 +78,161 @@ pub struct QuadcopterInputFrame {
 	pub orientation: Orientation<f64>,
 	pub rc_channels: RcChannels<f64>,
 	pub soft_armed: bool,
+	/// Fused from barometer and accelerometer, available once the barometer has been read.
+	pub altitude: Option<AltitudeEstimate>,
+	/// Fused from GNSS, barometer and accelerometer, available once GNSS has a fix.
+	pub navigation: Option<NavigationEstimate>,
+	/// Estimated from the power module, available while a battery is connected.
+	pub battery: Option<BatteryEstimate>,
+	/// CPU temperature (°C), available once read.
+	pub cpu_temperature: Option<f64>,
+	/// Instants inputs were last collected.
+	pub timestamps: InputTimestamps,
+	/// Instant of the latest input, by which the autopilot measures input ages and its loop rate.
+	pub instant: Instant,
 }
 
-impl Collector<QuadcopterInputFrame> for QuadcopterCollector {
-	fn collect(&mut self, input: Input) -> QuadcopterInputFrame {
+/// Instants inputs were last collected, `None` until first collected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputTimestamps {
+	pub orientation: Option<Instant>,
+	pub rc_channels: Option<Instant>,
+	pub navio_adc: Option<Instant>,
+	pub barometer: Option<Instant>,
+	pub gnss: Option<Instant>,
+}
+
+impl QuadcopterCollector {
+	/// Collects an input received at `instant`. Inputs carrying their own instant are stamped
+	/// with it instead, so that replayed inputs keep their logged timing.
+	pub fn collect_at(&mut self, input: Input, instant: Instant) -> QuadcopterInputFrame {
+		record(&input);
+
+		let instant = input_instant(&input).unwrap_or(instant);
+		let timestamps = &mut self.input_frame.timestamps;
+
+		match input {
+			Input::Orientation(_) => timestamps.orientation = Some(instant),
+			Input::RcChannels(_) => timestamps.rc_channels = Some(instant),
+			Input::NavioAdc(_) => timestamps.navio_adc = Some(instant),
+			Input::Barometer(_) => timestamps.barometer = Some(instant),
+			Input::Gnss(_) => timestamps.gnss = Some(instant),
+			_ => {}
+		}
+
+		self.input_frame.instant = instant;
+
 		#[allow(unreachable_patterns)]
 		match input {
-			Input::Orientation(orientation) => self.input_frame.orientation = orientation,
-			Input::NavioAdc(navio_adc) => self.input_frame.navio_adc = navio_adc,
+			Input::Orientation(orientation) => {
+				let (quaternion, imu_data, _) = &orientation;
+				self.altitude_estimator.update_acceleration(quaternion, &imu_data.acc, imu_data.instant);
+				self.input_frame.altitude = self.altitude_estimator.estimate();
+				self.navigation_estimator.update_acceleration(
+					ned_acceleration(quaternion, &imu_data.acc), imu_data.instant);
+				self.input_frame.navigation = self.navigation_estimator.estimate();
+				self.input_frame.orientation = orientation
+			}
+			Input::Barometer(barometer) => {
+				self.altitude_estimator.update_pressure(barometer.pressure, barometer.instant);
+				self.input_frame.altitude = self.altitude_estimator.estimate();
+
+				if let Some(reference_pressure) = self.altitude_estimator.reference_pressure() {
+					let altitude = AltitudeEstimator::barometric_altitude(barometer.pressure, reference_pressure);
+					self.navigation_estimator.update_barometric_altitude(altitude, barometer.instant);
+					self.input_frame.navigation = self.navigation_estimator.estimate();
+				}
+			}
+			Input::Gnss(gnss) => {
+				if gnss.fix == GnssFix::Fix3D {
+					let position = GeodeticPosition {
+						latitude: gnss.latitude,
+						longitude: gnss.longitude,
+						altitude: gnss.altitude,
+					};
+
+					self.navigation_estimator.update_gnss(position,
+														  [gnss.velocity.x, gnss.velocity.y, gnss.velocity.z],
+														  gnss.horizontal_accuracy,
+														  gnss.vertical_accuracy,
+														  gnss.speed_accuracy,
+														  gnss.instant);
+					self.input_frame.navigation = self.navigation_estimator.estimate();
+				}
+			}
+			Input::NavioAdc(navio_adc) => {
+				self.input_frame.battery = self.battery.update(&navio_adc);
+				self.input_frame.navio_adc = navio_adc
+			}
 			Input::RcChannels(rc_channels) => self.input_frame.rc_channels = rc_channels,
 			Input::SoftArmed(soft_armed) => self.input_frame.soft_armed = soft_armed,
+			Input::CpuTemperature(cpu_temperature) => self.input_frame.cpu_temperature = Some(cpu_temperature),
 			_ => error!("Unhandled input: {:?}", input),
 		}
 
 		self.input_frame.clone()
 	}
 }
+
+impl Collector<QuadcopterInputFrame> for QuadcopterCollector {
+	fn collect(&mut self, input: Input) -> QuadcopterInputFrame {
+		self.collect_at(input, Instant::now())
+	}
+}
+
+/// Instant an input was sampled, if it carries one.
+fn input_instant(input: &Input) -> Option<Instant> {
+	match input {
+		Input::Orientation((_, _, instant)) => Some(*instant),
+		Input::NavioAdc(navio_adc) => Some(navio_adc.instant),
+		Input::Barometer(barometer) => Some(barometer.instant),
+		Input::Gnss(gnss) => Some(gnss.instant),
+		_ => None,
+	}
+}
+
+/// Acceleration in the north-east-down frame, gravity excluded, given the rotation from body frame
+/// to world frame (north-west-up once the AHRS uses the magnetometer).
+fn ned_acceleration(orientation: &UnitQuaternion<f64>, acc: &Vector3<f64>) -> [f64; 3] {
+	let acceleration = orientation.transform_vector(acc);
+
+	[acceleration.x, -acceleration.y, G - acceleration.z]
+}
+
+fn vector_values(vector: &Vector3<f64>) -> [f32; 3] {
+	[vector.x as f32, vector.y as f32, vector.z as f32]
+}
+
+/// Records inputs in the binary black box log.
+fn record(input: &Input) {
+	match input {
+		Input::Orientation((quaternion, imu_data, _)) => {
+			black_box::record(Frame::Imu {
+				acc: vector_values(&imu_data.acc),
+				gyr: vector_values(&imu_data.gyr),
+				mag: vector_values(&imu_data.mag),
+			});
+			black_box::record(Frame::Attitude {
+				quaternion: [quaternion.w as f32, quaternion.i as f32, quaternion.j as f32, quaternion.k as f32],
+			});
+		}
+		Input::RcChannels(rc_channels) => {
+			let mut channels = [0.; 16];
+
+			if let Some(rc_channels) = rc_channels {
+				for (channel, &value) in channels.iter_mut().zip(rc_channels.iter()) {
+					*channel = value as f32;
+				}
+			}
+
+			black_box::record(Frame::Rc { connected: rc_channels.is_some(), channels });
+		}
+		Input::NavioAdc(navio_adc) => {
+			black_box::record(Frame::Power {
+				voltage: navio_adc.external_voltage as f32,
+				current: navio_adc.external_current as f32,
+			});
+		}
+		_ => {}
+	}
+}
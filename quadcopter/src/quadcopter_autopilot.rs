@@ -1,12 +1,25 @@
-use std::time::{Duration};
+use std::time::{Duration, Instant};
 
 use autopilot::Autopilot;
+use crossbeam_channel::Sender;
+use black_box::{Axis, Frame};
+use navigation::NavigationEstimate;
 use pid::Pid;
 
-use crate::quadcopter::{QuadcopterInputFrame, QuadcopterOutputFrame, LedColor};
-use crate::output_controllers::navio_esc_output_controller::QUADCOPTER_ESC_CHANNELS;
+use crate::altitude_hold::{AltitudeHold, AltitudeHoldConfig};
+use crate::arming::{Arming, ArmingConfig};
+use crate::battery::BatteryState;
+use crate::failsafe::{Failsafe, FailsafeConfig, FailsafeState};
+use crate::filter_config::{self, DTermFilterConfig};
+use crate::flight_mode::{FlightMode, FlightModeSelector};
+use crate::heading_hold::{HeadingHold, HeadingHoldConfig};
+use crate::mission::{Mission, MissionExecutor};
+use crate::position_control::{PositionControl, PositionControlConfig};
+use crate::pre_arm::PreArmChecks;
+use crate::quadcopter::{QuadcopterInputFrame, QuadcopterOutputFrame, LedColor, QUADCOPTER_ESC_CHANNELS};
 use crate::roll_pitch_yaw::{RollPitchYaw, RollPitch};
 use crate::mixer::Mixer;
+use crate::watchdog::{InputWatchdog, WatchdogConfig};
 
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -20,53 +33,142 @@ pub struct QuadcopterAutopilot {
 	pids: RollPitchYaw<Pid<f64>>,
 	rates: RollPitchYaw<f64>,
 	limits: RollPitch<f64>,
+	acro_rates: RollPitch<f64>,
 	mixer: Mixer<f64>,
+	altitude_hold: AltitudeHold,
+	heading_hold: HeadingHold,
+	position_control: PositionControl,
+	mission: Option<MissionExecutor>,
+	mission_engaged: bool,
+	failsafe: Failsafe,
+	arming: Arming,
+	watchdog: InputWatchdog,
+	pre_arm_checks: Option<PreArmChecks>,
+	flight_mode_selector: FlightModeSelector,
 	previous_mode: Mode,
 }
 
+/// RC channel of the altitude hold switch.
+const ALTITUDE_HOLD_CHANNEL: usize = 6;
+/// RC channel of the three-position flight mode switch.
+const FLIGHT_MODE_CHANNEL: usize = 7;
+/// RC channel of the mission switch.
+const MISSION_CHANNEL: usize = 8;
+
+/// Weighted average of `a` and `b`, with `b` weighted by `weight` within (0., 1.).
+fn blend(a: RollPitch<f64>, b: RollPitch<f64>, weight: f64) -> RollPitch<f64> {
+	RollPitch {
+		roll: a.roll * (1. - weight) + b.roll * weight,
+		pitch: a.pitch * (1. - weight) + b.pitch * weight,
+	}
+}
+
 impl QuadcopterAutopilot {
 	pub fn new(pid_values: RollPitchYaw<(f64, f64, f64)>,
+			   d_term_filter: &[DTermFilterConfig],
 			   rates: RollPitchYaw<f64>,
 			   limits: RollPitch<f64>,
-			   mixer: Mixer<f64>) -> Self {
+			   acro_rates: RollPitch<f64>,
+			   mixer: Mixer<f64>,
+			   altitude_hold: AltitudeHoldConfig,
+			   heading_hold: HeadingHoldConfig,
+			   position_control: PositionControlConfig,
+			   failsafe: FailsafeConfig,
+			   arming: ArmingConfig,
+			   watchdog: WatchdogConfig) -> Self {
+		let rate_pid = |k: (f64, f64, f64)| Pid::new(k, 0., Some((-1., 1.)))
+			.with_d_term_filter(filter_config::d_term_filter_chain(d_term_filter));
+
 		Self {
 			pids: RollPitchYaw {
-				roll: Pid::new(pid_values.roll, 0., Some((-1., 1.))),
-				pitch: Pid::new(pid_values.pitch, 0., Some((-1., 1.))),
-				yaw: Pid::new(pid_values.yaw, 0., Some((-1., 1.))),
+				roll: rate_pid(pid_values.roll),
+				pitch: rate_pid(pid_values.pitch),
+				yaw: rate_pid(pid_values.yaw),
 			},
 			rates,
 			limits,
+			acro_rates,
 			mixer,
+			altitude_hold: AltitudeHold::new(altitude_hold),
+			heading_hold: HeadingHold::new(heading_hold),
+			position_control: PositionControl::new(position_control),
+			mission: None,
+			mission_engaged: false,
+			failsafe: Failsafe::new(failsafe),
+			arming: Arming::new(arming),
+			watchdog: InputWatchdog::new(watchdog),
+			pre_arm_checks: None,
+			flight_mode_selector: FlightModeSelector::new(FlightMode::Angle),
 			previous_mode: Mode::Off,
 		}
 	}
 
-	fn mode(&self, input_frame: &QuadcopterInputFrame) -> Mode {
-		const MINIMAL_EXTERNAL_VOLTAGE: f64 = 10.0;
-		//TODO: add max current
+	/// Mission flown while the mission switch is on, once position and altitude are estimated.
+	pub fn with_mission(mut self, mission: Mission) -> Self {
+		self.mission = Some(MissionExecutor::new(mission, self.position_control.config().position_gain));
+		self
+	}
+
+	/// Notifies `sender` once the arming interlock has seen the arm switch off.
+	pub fn with_arm_switch_off_notification(mut self, sender: Sender<()>) -> Self {
+		self.arming = self.arming.with_switch_off_notification(sender);
+		self
+	}
+
+	/// Checks verified before arming. Without them, e.g. when replaying, arming only depends on
+	/// the arm switch.
+	pub fn with_pre_arm_checks(mut self, pre_arm_checks: PreArmChecks) -> Self {
+		self.pre_arm_checks = Some(pre_arm_checks);
+		self
+	}
+
+	fn mode(&mut self, input_frame: &QuadcopterInputFrame, failsafe_state: FailsafeState) -> Mode {
+		// Once armed, the last valid RC channels are held by the failsafe when the signal is lost,
+		// rather than cutting motors mid-air
+		let rc_channels = match failsafe_state {
+			FailsafeState::Nominal => input_frame.rc_channels,
+			_ if self.previous_mode == Mode::Armed => self.failsafe.rc_channels(),
+			_ => None,
+		};
 
-		// const MAXIMAL_EXTERNAL_CURRENT: f64 = 50.0;
 		// Arming conditions:
 		// - Soft arm: true
-		// - RC channels: Some
-		// - RC channel n°8 (ie 7): > 0.5
-		// - Battery voltage >= min
-		if input_frame.soft_armed && input_frame.rc_channels.is_some() {
-			if input_frame.rc_channels.unwrap()[4] > 0.5 {
-				if input_frame.navio_adc.external_voltage <= 2.0 {
-					Mode::Armed
-				} else if input_frame.navio_adc.external_voltage >= MINIMAL_EXTERNAL_VOLTAGE {
+		// - RC channels: Some, or held by the failsafe
+		// - RC channel n°5 (ie 4): > 0.5, turned on with throttle at minimum and not locked by
+		//   the arming interlock
+		// - Pre-arm checks passed
+		// - Not landed by the failsafe
+		// Low battery stages are handled by the failsafe once armed
+		if input_frame.soft_armed && failsafe_state == FailsafeState::Landed {
+			// Motors must not restart once the RC signal is recovered, until the arm switch is cycled
+			if self.previous_mode == Mode::Armed {
+				self.arming.lock("landed by failsafe");
+			}
+
+			Mode::Disarmed
+		} else if input_frame.soft_armed && rc_channels.is_some() {
+			let rc_channels = rc_channels.unwrap();
+			let switch_on = rc_channels[4] > 0.5;
+
+			if self.previous_mode == Mode::Armed && switch_on {
+				Mode::Armed
+			} else if self.arming.arm_allowed(switch_on, rc_channels[2]) {
+				let pre_arm_checks_passed = self.pre_arm_checks
+					.as_mut()
+					.map_or(true, |pre_arm_checks| pre_arm_checks.check(input_frame, input_frame.instant));
+
+				if pre_arm_checks_passed {
 					Mode::Armed
 				} else {
-					match self.previous_mode {
-						Mode::Armed => {
-							warn!("Ext. voltage is low: {:.2} V", input_frame.navio_adc.external_voltage);
-							Mode::Armed
-						}
-						_ => Mode::Disarmed
-					}
+					Mode::Disarmed
 				}
+			} else if !switch_on {
+				// Failures are reported again on the next arming attempt
+				if let Some(pre_arm_checks) = &mut self.pre_arm_checks {
+					pre_arm_checks.clear();
+				}
+
+				Mode::Disarmed
 			} else {
 				Mode::Disarmed
 			}
@@ -74,33 +176,175 @@ impl QuadcopterAutopilot {
 			Mode::Off
 		}
 	}
+
+	/// Target roll and pitch rates commanded by sticks in the given flight mode.
+	fn flight_mode_rates(&self,
+						 flight_mode: FlightMode,
+						 rc_channels: &[f64; 16],
+						 current_orientation: &RollPitchYaw<f64>) -> RollPitch<f64> {
+		// Stick deflections are within (-0.5, 0.5)
+		let deflection = RollPitch {
+			roll: rc_channels[0] - 0.5,
+			pitch: rc_channels[1] - 0.5,
+		};
+
+		let angle_rates = || RollPitch {
+			roll: (deflection.roll * self.limits.roll - current_orientation.roll) * self.rates.roll,
+			pitch: (deflection.pitch * self.limits.pitch - current_orientation.pitch) * self.rates.pitch,
+		};
+
+		let acro_rates = || RollPitch {
+			roll: deflection.roll * self.acro_rates.roll,
+			pitch: deflection.pitch * self.acro_rates.pitch,
+		};
+
+		match flight_mode {
+			FlightMode::Angle => angle_rates(),
+			FlightMode::Acro => acro_rates(),
+			FlightMode::Horizon => {
+				let angle_weight = 1. - (deflection.roll.abs().max(deflection.pitch.abs()) * 2.).min(1.);
+				blend(acro_rates(), angle_rates(), angle_weight)
+			}
+		}
+	}
+
+	/// Target roll and pitch angles, climb rate and yaw of the mission, if it is engaged.
+	fn mission_targets(&mut self,
+					   rc_channels: &[f64; 16],
+					   navigation: Option<&NavigationEstimate>,
+					   altitude_estimated: bool,
+					   yaw: f64,
+					   instant: Instant) -> Option<(RollPitch<f64>, f64, Option<f64>)> {
+		let switch_on = rc_channels[MISSION_CHANNEL] > 0.5;
+
+		let (mission, navigation) = match (&mut self.mission, navigation) {
+			(Some(mission), Some(navigation)) if switch_on && altitude_estimated => (mission, navigation),
+			_ => {
+				if self.mission_engaged {
+					info!("Mission disengaged");
+					self.mission_engaged = false;
+				}
+
+				return None;
+			}
+		};
+
+		if !self.mission_engaged {
+			info!("Mission engaged towards waypoint {}", mission.index());
+			self.mission_engaged = true;
+		}
+
+		let setpoint = mission.setpoint(navigation, yaw, instant);
+		let (angles, climb_rate) = self.position_control.output(&setpoint, navigation, yaw);
+
+		Some((angles, climb_rate, Some(setpoint.yaw)))
+	}
+
+	/// Target roll and pitch angles and climb rate of the failsafe, if it has taken over, heading
+	/// being held.
+	fn failsafe_targets(&self,
+						failsafe_state: FailsafeState,
+						navigation: Option<&NavigationEstimate>,
+						yaw: f64) -> Option<(RollPitch<f64>, f64, Option<f64>)> {
+		match failsafe_state {
+			FailsafeState::Land => Some((RollPitch { roll: 0., pitch: 0. },
+										 -self.failsafe.config().descent_rate,
+										 None)),
+			_ => {
+				let navigation = navigation?;
+				let setpoint = self.failsafe.return_setpoint(navigation, yaw)?;
+				let (angles, climb_rate) = self.position_control.output(&setpoint, navigation, yaw);
+
+				Some((angles, climb_rate, None))
+			}
+		}
+	}
+
+	/// Clears controller states while motors are stopped.
+	fn reset(&mut self) {
+		self.pids.roll.reset();
+		self.pids.pitch.reset();
+		self.pids.yaw.reset();
+		self.altitude_hold.reset();
+		self.heading_hold.reset();
+		self.flight_mode_selector.reset(FlightMode::Angle);
+		self.arming.reset();
+
+		if let Some(mission) = &mut self.mission {
+			mission.reset();
+		}
+	}
+
+	/// Records setpoints, PID terms and mixer outputs in the binary black box log.
+	fn record(&self, target_rates: &RollPitchYaw<f64>, outputs: &[f64; QUADCOPTER_ESC_CHANNELS]) {
+		black_box::record(Frame::Setpoint {
+			roll: target_rates.roll as f32,
+			pitch: target_rates.pitch as f32,
+			yaw: target_rates.yaw as f32,
+		});
+
+		for &(axis, pid) in [(Axis::Roll, &self.pids.roll),
+			(Axis::Pitch, &self.pids.pitch),
+			(Axis::Yaw, &self.pids.yaw)].iter() {
+			if let Some((p, i, d)) = pid.terms() {
+				black_box::record(Frame::PidTerms { axis, p: p as f32, i: i as f32, d: d as f32 });
+			}
+		}
+
+		black_box::record(Frame::Mixer {
+			outputs: [outputs[0] as f32, outputs[1] as f32, outputs[2] as f32, outputs[3] as f32],
+		});
+	}
 }
 
 
 impl Autopilot<QuadcopterInputFrame, QuadcopterOutputFrame> for QuadcopterAutopilot {
 	const MAX_CONTROL_LOOP_PERIOD: Duration = Duration::from_millis(50);
 
-	fn output_frame(&mut self, input_frame: QuadcopterInputFrame) -> QuadcopterOutputFrame {
-		let mode = self.mode(&input_frame);
-		self.previous_mode = mode;
+	fn output_frame(&mut self, mut input_frame: QuadcopterInputFrame) -> QuadcopterOutputFrame {
+		// Input instants rather than the wall clock, so that replayed logs keep their timing
+		let instant = input_frame.instant;
 
-		match mode {
-			Mode::Armed => {
-				let rc_channels = input_frame.rc_channels.unwrap();
+		if let Some(pre_arm_checks) = &mut self.pre_arm_checks {
+			pre_arm_checks.update_loop_rate(instant);
+		}
 
-				// Orientation
+		// Stale inputs are removed from the frame before any use
+		let orientation_stale = self.watchdog.apply(&mut input_frame, instant);
 
-				// Target is set differently for yaw, as input controls angular rate on yaw axis.
-				let target_orientation = RollPitchYaw {
-					roll: (rc_channels[0] - 0.5) * self.limits.roll,
-					pitch: (rc_channels[1] - 0.5) * self.limits.pitch,
-					yaw: 0.0, // TODO: keep yaw constant, currently ignored
-				};
+		// The failsafe only takes over once armed
+		let failsafe_state = if input_frame.rc_channels.is_some() || self.previous_mode == Mode::Armed {
+			let battery_state = match (self.previous_mode, input_frame.battery) {
+				(Mode::Armed, Some(battery)) => battery.state,
+				_ => BatteryState::Nominal,
+			};
+
+			self.failsafe.update(&input_frame.rc_channels,
+								 battery_state,
+								 input_frame.navigation.as_ref(),
+								 input_frame.altitude.as_ref(),
+								 input_frame.orientation.2)
+		} else {
+			self.failsafe.state()
+		};
+
+		// Motors are stopped rather than controlled from a frozen attitude
+		let mode = match self.mode(&input_frame, failsafe_state) {
+			Mode::Armed if orientation_stale => {
+				if self.previous_mode == Mode::Armed {
+					self.arming.lock("orientation is stale");
+				}
+
+				Mode::Disarmed
+			}
+			mode => mode,
+		};
 
-				debug!(target: "target_orientation", "{} {} {}",
-					   target_orientation.roll,
-					   target_orientation.pitch,
-					   target_orientation.yaw);
+		self.previous_mode = mode;
+
+		match mode {
+			Mode::Armed => {
+				let rc_channels = self.failsafe.rc_channels().unwrap();
 
 				let (quaternion,
 					imu_data,
@@ -113,13 +357,57 @@ impl Autopilot<QuadcopterInputFrame, QuadcopterOutputFrame> for QuadcopterAutopi
 					   current_orientation.pitch,
 					   current_orientation.yaw);
 
-				let delta_orientation = target_orientation - current_orientation;
+				// The failsafe, then missions, take over sticks
+				let targets = match self.failsafe_targets(failsafe_state,
+														  input_frame.navigation.as_ref(),
+														  current_orientation.yaw) {
+					Some(targets) => Some(targets),
+					None => self.mission_targets(&rc_channels,
+												 input_frame.navigation.as_ref(),
+												 input_frame.altitude.is_some(),
+												 current_orientation.yaw,
+												 instant),
+				};
 
 				// Rates
+				let roll_pitch_rates = match targets {
+					// Attitude is commanded as in angle mode
+					Some((angles, _, _)) => RollPitch {
+						roll: (angles.roll - current_orientation.roll) * self.rates.roll,
+						pitch: (angles.pitch - current_orientation.pitch) * self.rates.pitch,
+					},
+					None => {
+						let flight_mode = FlightMode::from_switch(rc_channels[FLIGHT_MODE_CHANNEL]);
+
+						match self.flight_mode_selector.update(flight_mode, instant) {
+							(flight_mode, None) => {
+								self.flight_mode_rates(flight_mode, &rc_channels, &current_orientation)
+							}
+							(flight_mode, Some((previous_flight_mode, previous_weight))) => blend(
+								self.flight_mode_rates(flight_mode, &rc_channels, &current_orientation),
+								self.flight_mode_rates(previous_flight_mode, &rc_channels, &current_orientation),
+								previous_weight,
+							),
+						}
+					}
+				};
+
+				// Yaw stick controls angular rate in every flight mode, heading being held while
+				// it is centered
+				let yaw_rate = match targets {
+					Some((_, _, Some(target_yaw))) => self.heading_hold.rate_towards(target_yaw, current_orientation.yaw),
+					Some((_, _, None)) => self.heading_hold.target_rate(0.5,
+																		current_orientation.yaw,
+																		0.5 * self.rates.yaw),
+					None => self.heading_hold.target_rate(rc_channels[3],
+														  current_orientation.yaw,
+														  0.5 * self.rates.yaw),
+				};
+
 				let target_rates = RollPitchYaw {
-					roll: delta_orientation.roll * self.rates.roll,
-					pitch: delta_orientation.pitch * self.rates.pitch,
-					yaw: (rc_channels[3] - 0.5) * self.rates.yaw,
+					roll: roll_pitch_rates.roll,
+					pitch: roll_pitch_rates.pitch,
+					yaw: yaw_rate,
 				};
 
 				debug!(target: "target_rates", "{} {} {}",
@@ -127,9 +415,11 @@ impl Autopilot<QuadcopterInputFrame, QuadcopterOutputFrame> for QuadcopterAutopi
 					   target_rates.pitch,
 					   target_rates.yaw);
 
-				self.pids.roll.set_setpoint(target_rates.roll);
-				self.pids.pitch.set_setpoint(target_rates.pitch);
-				self.pids.yaw.set_setpoint(target_rates.yaw);
+				// Setpoints are tracked rather than set, so that integral terms are kept across
+				// stick moves and flight mode transitions
+				self.pids.roll.track_setpoint(target_rates.roll);
+				self.pids.pitch.track_setpoint(target_rates.pitch);
+				self.pids.yaw.track_setpoint(target_rates.yaw);
 
 				let pid_outputs = RollPitchYaw {
 					roll: self.pids.roll.estimate(imu_data.gyr.x, instant),
@@ -137,32 +427,165 @@ impl Autopilot<QuadcopterInputFrame, QuadcopterOutputFrame> for QuadcopterAutopi
 					yaw: self.pids.yaw.estimate(imu_data.gyr.z, instant),
 				};
 
-				let mut outputs = self.mixer.mix(pid_outputs,
-												 rc_channels[2]);
+				// Throttle stick commands climb rate in altitude hold, once altitude is estimated
+				let throttle = match (targets, input_frame.altitude) {
+					(Some((_, climb_rate, _)), Some(altitude)) => {
+						self.altitude_hold.climb_rate_throttle(climb_rate, &altitude)
+					}
+					// Landing without altitude estimate
+					(Some(_), None) => self.failsafe.config().descent_throttle,
+					(None, Some(altitude)) if rc_channels[ALTITUDE_HOLD_CHANNEL] > 0.5 => {
+						self.altitude_hold.throttle(rc_channels[2], &altitude)
+					}
+					_ => {
+						self.altitude_hold.reset();
+						rc_channels[2]
+					}
+				};
 
-				if rc_channels[5] > 0.5 {
+				// Flips are intended in acro mode
+				let acro = targets.is_none()
+					&& FlightMode::from_switch(rc_channels[FLIGHT_MODE_CHANNEL]) == FlightMode::Acro;
+
+				if self.arming.disarm_reason(&current_orientation,
+											 &imu_data.gyr,
+											 &imu_data.acc,
+											 input_frame.altitude.as_ref(),
+											 throttle,
+											 acro,
+											 instant).is_some() {
+					self.previous_mode = Mode::Disarmed;
+					self.reset();
+
+					return QuadcopterOutputFrame {
+						led: Some(LedColor::Red),
+						esc_channels: [0.; QUADCOPTER_ESC_CHANNELS],
+					};
+				}
+
+				let mut outputs = self.mixer.mix(pid_outputs, throttle);
+
+				// Motor test is ignored once the RC signal is lost
+				if failsafe_state == FailsafeState::Nominal && rc_channels[5] > 0.5 {
 					for output in outputs.iter_mut() {
 						*output = rc_channels[2];
 					}
 				}
 
+				self.record(&target_rates, &outputs);
+
+				let led = match (failsafe_state, input_frame.battery) {
+					(FailsafeState::Nominal, Some(battery)) if battery.state != BatteryState::Nominal => LedColor::Magenta,
+					(FailsafeState::Nominal, _) => LedColor::Green,
+					_ => LedColor::Yellow,
+				};
+
 				QuadcopterOutputFrame {
-					led: Some(LedColor::Green),
+					led: Some(led),
 					esc_channels: outputs,
 				}
 			}
 			Mode::Off => {
+				self.reset();
+
 				QuadcopterOutputFrame {
 					led: None,
 					esc_channels: [0.; QUADCOPTER_ESC_CHANNELS],
 				}
 			}
 			Mode::Disarmed => {
+				self.reset();
+
+				// Failed pre-arm checks blink the LED
+				let led = self.pre_arm_checks
+					.as_ref()
+					.and_then(|pre_arm_checks| pre_arm_checks.led(instant))
+					.unwrap_or(Some(LedColor::Red));
+
 				QuadcopterOutputFrame {
-					led: Some(LedColor::Red),
+					led,
 					esc_channels: [0.; QUADCOPTER_ESC_CHANNELS],
 				}
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::altitude_estimator::AltitudeEstimate;
+	use crate::battery::BatteryConfig;
+	use crate::mixer::Mixer;
+	use crate::quadcopter::{QuadcopterCollector, QuadcopterInputFrame};
+	use crate::quadcopter_autopilot::{Mode, QuadcopterAutopilot};
+	use crate::quadcopter_config::QuadcopterConfig;
+	use autopilot::{Autopilot, Collector, Input, RcChannels};
+	use std::time::{Duration, Instant};
+
+	fn autopilot() -> QuadcopterAutopilot {
+		let config = QuadcopterConfig::default();
+
+		QuadcopterAutopilot::new(config.pid_values,
+								 &config.filter_d_term,
+								 config.rates,
+								 config.limits,
+								 config.acro_rates,
+								 Mixer { min_output: config.output_esc_min_value },
+								 config.altitude_hold,
+								 config.heading_hold,
+								 config.position_control,
+								 config.failsafe,
+								 config.arming,
+								 config.watchdog)
+	}
+
+	/// Frame of an aircraft at rest on the ground.
+	fn input_frame(rc_channels: RcChannels<f64>, instant: Instant) -> QuadcopterInputFrame {
+		let mut input_frame = QuadcopterCollector::new(BatteryConfig::default()).collect(Input::SoftArmed(true));
+		input_frame.rc_channels = rc_channels;
+		input_frame.orientation.2 = instant;
+		input_frame.instant = instant;
+		input_frame.altitude = Some(AltitudeEstimate { altitude: 0., vertical_speed: 0., instant });
+
+		input_frame
+	}
+
+	/// RC channels with throttle low, every switch off but the arm switch.
+	fn rc_channels(arm_switch_on: bool) -> RcChannels<f64> {
+		let mut rc_channels = [0.; 16];
+		rc_channels[0] = 0.5;
+		rc_channels[1] = 0.5;
+		rc_channels[3] = 0.5;
+		rc_channels[4] = if arm_switch_on { 1. } else { 0. };
+
+		Some(rc_channels)
+	}
+
+	#[test]
+	fn failsafe_landed_test() {
+		let mut autopilot = autopilot();
+		let instant = Instant::now();
+		let seconds = |seconds| instant + Duration::from_secs(seconds);
+
+		autopilot.output_frame(input_frame(rc_channels(false), instant));
+		autopilot.output_frame(input_frame(rc_channels(true), instant));
+		assert_eq!(autopilot.previous_mode, Mode::Armed);
+
+		// The failsafe lands after the grace period, then stops motors
+		autopilot.output_frame(input_frame(None, seconds(1)));
+		autopilot.output_frame(input_frame(None, seconds(2)));
+		assert_eq!(autopilot.previous_mode, Mode::Armed);
+
+		let output_frame = autopilot.output_frame(input_frame(None, seconds(5)));
+		assert_eq!(autopilot.previous_mode, Mode::Disarmed);
+		assert_eq!(output_frame.esc_channels, [0.; 4]);
+
+		// Recovering the signal with the arm switch on does not restart motors
+		autopilot.output_frame(input_frame(rc_channels(true), seconds(6)));
+		assert_eq!(autopilot.previous_mode, Mode::Disarmed);
+
+		autopilot.output_frame(input_frame(rc_channels(false), seconds(7)));
+		autopilot.output_frame(input_frame(rc_channels(true), seconds(8)));
+		assert_eq!(autopilot.previous_mode, Mode::Armed);
+	}
+}
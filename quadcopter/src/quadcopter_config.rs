@@ -4,28 +4,78 @@ use std::fs::{OpenOptions};
 use std::f64::consts::PI;
 use std::io::Write;
 use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use nalgebra::{Matrix3, Vector3};
 
+use crate::ahrs_config::AhrsConfig;
+use crate::altitude_hold::AltitudeHoldConfig;
+use crate::arming::ArmingConfig;
+use crate::battery::BatteryConfig;
+use crate::boards::Board;
+use crate::filter_config::{DTermFilterConfig, ImuFilterConfig};
+use crate::gyr_calibration::GyrBiasModel;
+use crate::failsafe::FailsafeConfig;
+use crate::heading_hold::HeadingHoldConfig;
+use crate::position_control::PositionControlConfig;
+use crate::pre_arm::PreArmConfig;
 use crate::roll_pitch_yaw::{RollPitchYaw, RollPitch};
+use crate::watchdog::WatchdogConfig;
+use std::collections::BTreeMap;
 use std::path::Path;
+use autopilot::RealtimeOptions;
 
 #[serde(default)]
 #[derive(Serialize, Deserialize)]
 pub struct QuadcopterConfig {
 	pub log_level_filter: String,
+	pub board: Board,
 	pub pid_values: RollPitchYaw<(f64, f64, f64)>,
 	pub rates: RollPitchYaw<f64>,
 	pub limits: RollPitch<f64>,
+	/// Roll and pitch rates (rad/s) per unit of stick deflection in acro and horizon modes.
+	pub acro_rates: RollPitch<f64>,
+	/// Accelerometer offset, subtracted from raw samples.
 	pub calibration_acc: [f64; 3],
-	pub calibration_gyr: [f64; 3],
-	pub ahrs_madgwick_beta: f64,
+	/// Accelerometer scale factor and misalignment matrix (row-major), applied after the offset.
+	pub calibration_acc_matrix: [[f64; 3]; 3],
+	/// Gyroscope bias as a polynomial of die temperature, subtracted from raw samples.
+	pub calibration_gyr_bias: GyrBiasModel,
+	/// Magnetometer hard-iron offset, subtracted from raw samples.
+	pub calibration_mag_offset: [f64; 3],
+	/// Magnetometer soft-iron matrix (row-major), applied after the hard-iron offset.
+	pub calibration_mag_matrix: [[f64; 3]; 3],
+	/// LSM9DS1 ranges, output data rates and bandwidths. The `sample_frequency` of IMU low-pass
+	/// filters should match `acc_gyr_odr`.
+	pub lsm9ds1: lsm9ds1::Settings,
+	pub ahrs: AhrsConfig,
+	/// Whether the AHRS is corrected by the magnetometer, making yaw observable.
+	pub ahrs_use_magnetometer: bool,
 	pub input_rc_range: (u16, u16),
 	pub output_esc_pins: [u32; 4],
 	pub output_esc_min_value: f64,
-	pub filter_gyr_abg: (f64, f64, f64),
-	pub filter_gyr_low_pass: (f64, f64, f64),
-	pub filter_acc_abg: (f64, f64, f64),
-	pub filter_acc_low_pass: (f64, f64, f64),
-	pub filter_d_term_ab: (f64, f64),
+	/// Filters applied in order to calibrated gyroscope samples.
+	pub filter_gyr: Vec<ImuFilterConfig>,
+	/// Filters applied in order to calibrated accelerometer samples.
+	pub filter_acc: Vec<ImuFilterConfig>,
+	/// Filters applied in order to the derivative term of each rate PID.
+	pub filter_d_term: Vec<DTermFilterConfig>,
+	/// Altitude hold mode, selected by RC channel 7.
+	pub altitude_hold: AltitudeHoldConfig,
+	pub heading_hold: HeadingHoldConfig,
+	/// Position control of missions, selected by RC channel 9.
+	pub position_control: PositionControlConfig,
+	/// Actions taken when the RC signal is lost while armed.
+	pub failsafe: FailsafeConfig,
+	/// Battery model and low battery thresholds.
+	pub battery: BatteryConfig,
+	/// Conditions verified before arming.
+	pub pre_arm: PreArmConfig,
+	/// Arming interlock, and disarming when idle or after a crash.
+	pub arming: ArmingConfig,
+	/// Maximum ages of inputs, above which the autopilot takes degraded actions.
+	pub watchdog: WatchdogConfig,
+	/// Real-time options of stages, by name (e.g. `ImuInputController`, `QuadcopterAutopilot`).
+	pub realtime: BTreeMap<String, RealtimeOptions>,
 }
 
 pub trait TryIntoLevelFilter {
@@ -50,6 +100,7 @@ impl Default for QuadcopterConfig {
 	fn default() -> Self {
 		QuadcopterConfig {
 			log_level_filter: String::from("all"),
+			board: Board::Navio2,
 			pid_values: RollPitchYaw {
 				roll: (0.1375, 0., 0.),
 				pitch: (0.1375, 0., 0.),
@@ -64,40 +115,220 @@ impl Default for QuadcopterConfig {
 				roll: PI / 4.,
 				pitch: PI / 4.,
 			},
+			acro_rates: RollPitch {
+				roll: 4. * PI,
+				pitch: 4. * PI,
+			},
 			calibration_acc: [0., 0., 0.],
-			calibration_gyr: [0., 0., 0.],
-			ahrs_madgwick_beta: 0.11,
+			calibration_acc_matrix: [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+			calibration_gyr_bias: GyrBiasModel::default(),
+			calibration_mag_offset: [0., 0., 0.],
+			calibration_mag_matrix: [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+			lsm9ds1: lsm9ds1::Settings::default(),
+			ahrs: AhrsConfig::default(),
+			ahrs_use_magnetometer: false,
 			input_rc_range: (1024, 2003),
 			output_esc_pins: [13, 12, 1, 0],
 			output_esc_min_value: 0.025,
-			filter_gyr_abg: (0.06, 0.004, 0.011),
-			filter_gyr_low_pass: (170.0, 0.45, 500.0),
-			filter_acc_abg: (0.008, 0.0002, 0.0),
-			filter_acc_low_pass: (145.0, 0.48, 500.0),
-			filter_d_term_ab: (0.008, 0.0005),
+			filter_gyr: vec![
+				ImuFilterConfig::LowPass {
+					cutoff_frequency: 170.0,
+					q: 0.45,
+					sample_frequency: lsm9ds1::Settings::default().acc_gyr_odr.frequency(),
+				},
+				ImuFilterConfig::AlphaBetaGamma {
+					alpha: 0.06,
+					beta: 0.004,
+					gamma: 0.011,
+				},
+			],
+			filter_acc: vec![
+				ImuFilterConfig::LowPass {
+					cutoff_frequency: 145.0,
+					q: 0.48,
+					sample_frequency: lsm9ds1::Settings::default().acc_gyr_odr.frequency(),
+				},
+				ImuFilterConfig::AlphaBetaGamma {
+					alpha: 0.008,
+					beta: 0.0002,
+					gamma: 0.0,
+				},
+			],
+			filter_d_term: vec![
+				DTermFilterConfig::AlphaBeta {
+					alpha: 0.008,
+					beta: 0.0005,
+				},
+			],
+			altitude_hold: AltitudeHoldConfig::default(),
+			heading_hold: HeadingHoldConfig::default(),
+			position_control: PositionControlConfig::default(),
+			failsafe: FailsafeConfig::default(),
+			battery: BatteryConfig::default(),
+			pre_arm: PreArmConfig::default(),
+			arming: ArmingConfig::default(),
+			watchdog: WatchdogConfig::default(),
+			realtime: default_realtime_options(),
 		}
 	}
 }
 
+/// IMU reads and the control loop, from the collector to motor outputs, run on the two last CPUs
+/// of the Raspberry Pi, above kernel threads.
+fn default_realtime_options() -> BTreeMap<String, RealtimeOptions> {
+	let options = |fifo_priority, cpu, lock_memory| RealtimeOptions {
+		fifo_priority: Some(fifo_priority),
+		cpu_affinity: vec![cpu],
+		lock_memory,
+	};
+
+	vec![
+		("ImuInputController", options(80, 2, true)),
+		("QuadcopterCollector", options(75, 3, false)),
+		("QuadcopterAutopilot", options(75, 3, false)),
+		("QuadcopterDispatcher", options(70, 3, false)),
+		("MotorOutputController", options(70, 3, false)),
+	]
+		.into_iter()
+		.map(|(stage, options)| (stage.to_string(), options))
+		.collect()
+}
+
+/// Names of the stages spawned by the autopilot, by which real-time options are configured.
+const STAGES: [&str; 15] = [
+	"ImuInputController",
+	"BarometerInputController",
+	"GnssInputController",
+	"PowerInputController",
+	"RcInputController",
+	"CpuTemperatureInputController",
+	"SoftArmInputController",
+	"QuadcopterCollector",
+	"QuadcopterAutopilot",
+	"QuadcopterDispatcher",
+	"MotorOutputController",
+	"LedOutputController",
+	"LogLedOutputController",
+	"SystemInformationMonitor",
+	"HealthMonitor",
+];
+
 const CONFIG_FILE_PATH: &'static str = "config.json";
 
 
-pub fn read() -> Result<QuadcopterConfig, Box<dyn Error>> {
+/// Reads the config, along with notices of the legacy fields converted on the way, to be logged
+/// once the logger is set up.
+pub fn read() -> Result<(QuadcopterConfig, Vec<String>), Box<dyn Error>> {
 	if Path::new(CONFIG_FILE_PATH).exists() {
 		let config_file = OpenOptions::new()
 			.read(true)
 			.open(CONFIG_FILE_PATH)?;
 
-		let config: QuadcopterConfig = serde_json::from_reader(config_file)?;
-
-		Ok(config)
+		from_json(serde_json::from_reader(config_file)?)
 	} else {
 		let config = QuadcopterConfig::default();
 
 		save(&config)?;
 
-		Ok(config)
+		Ok((config, Vec::new()))
+	}
+}
+
+/// Parses a config, converting fields that were renamed or restructured, which would otherwise be
+/// silently replaced by their defaults.
+fn from_json(mut value: Value) -> Result<(QuadcopterConfig, Vec<String>), Box<dyn Error>> {
+	let mut notices = Vec::new();
+
+	if let Some(fields) = value.as_object_mut() {
+		let defaults = QuadcopterConfig::default();
+
+		if let Some(bias) = fields.remove("calibration_gyr") {
+			let bias: [f64; 3] = serde_json::from_value(bias)?;
+			let model = GyrBiasModel::constant(Vector3::from(bias), defaults.calibration_gyr_bias.reference_temperature);
+
+			convert_legacy_field(fields, &mut notices, "calibration_gyr", "calibration_gyr_bias", &model)?;
+		}
+
+		if let Some(beta) = fields.remove("ahrs_madgwick_beta") {
+			let beta: f64 = serde_json::from_value(beta)?;
+
+			convert_legacy_field(fields, &mut notices, "ahrs_madgwick_beta", "ahrs", &AhrsConfig::Madgwick { beta })?;
+		}
+
+		for &(legacy_low_pass, legacy_abg, field, default) in [
+			("filter_gyr_low_pass", "filter_gyr_abg", "filter_gyr", &defaults.filter_gyr),
+			("filter_acc_low_pass", "filter_acc_abg", "filter_acc", &defaults.filter_acc),
+		].iter() {
+			let low_pass = fields.remove(legacy_low_pass);
+			let abg = fields.remove(legacy_abg);
+
+			if low_pass.is_none() && abg.is_none() {
+				continue;
+			}
+
+			let low_pass: Option<(f64, f64, f64)> = low_pass.map(serde_json::from_value).transpose()?;
+			let abg: Option<(f64, f64, f64)> = abg.map(serde_json::from_value).transpose()?;
+
+			// Legacy chains were a low-pass filter followed by an alpha-beta-gamma filter
+			let filters: Vec<ImuFilterConfig> = default
+				.iter()
+				.map(|filter| match (filter, low_pass, abg) {
+					(ImuFilterConfig::LowPass { .. }, Some((cutoff_frequency, q, sample_frequency)), _) => {
+						ImuFilterConfig::LowPass { cutoff_frequency, q, sample_frequency }
+					}
+					(ImuFilterConfig::AlphaBetaGamma { .. }, _, Some((alpha, beta, gamma))) => {
+						ImuFilterConfig::AlphaBetaGamma { alpha, beta, gamma }
+					}
+					(filter, _, _) => filter.clone(),
+				})
+				.collect();
+
+			let legacy = format!("{} and {}", legacy_low_pass, legacy_abg);
+			convert_legacy_field(fields, &mut notices, &legacy, field, &filters)?;
+		}
+
+		if let Some(ab) = fields.remove("filter_d_term_ab") {
+			let (alpha, beta): (f64, f64) = serde_json::from_value(ab)?;
+
+			convert_legacy_field(fields, &mut notices, "filter_d_term_ab", "filter_d_term",
+								 &vec![DTermFilterConfig::AlphaBeta { alpha, beta }])?;
+		}
+	}
+
+	let config: QuadcopterConfig = serde_json::from_value(value)?;
+	validate_realtime_options(&config.realtime)?;
+
+	Ok((config, notices))
+}
+
+/// Rejects options of unknown stages, e.g. mistyped names, and options that cannot be applied.
+fn validate_realtime_options(options: &BTreeMap<String, RealtimeOptions>) -> Result<(), Box<dyn Error>> {
+	for (stage, options) in options {
+		if !STAGES.contains(&stage.as_str()) {
+			return Err(format!("Real-time options of unknown stage {}, expected one of {}",
+							   stage, STAGES.join(", ")).into());
+		}
+
+		options.validate().map_err(|e| format!("Invalid real-time options of stage {}: {}", stage, e))?;
+	}
+
+	Ok(())
+}
+
+/// Sets `field` to the conversion of a legacy field, unless it is already set.
+fn convert_legacy_field<T: Serialize>(fields: &mut Map<String, Value>,
+									   notices: &mut Vec<String>,
+									   legacy: &str,
+									   field: &str,
+									   converted: &T) -> Result<(), Box<dyn Error>> {
+	if fields.contains_key(field) {
+		notices.push(format!("Legacy config field {} ignored, as {} is set", legacy, field));
+	} else {
+		fields.insert(field.to_string(), serde_json::to_value(converted)?);
+		notices.push(format!("Legacy config field {} converted to {}", legacy, field));
 	}
+
+	Ok(())
 }
 
 pub fn save(config: &QuadcopterConfig) -> Result<(), Box<dyn Error>> {
@@ -111,3 +342,95 @@ pub fn save(config: &QuadcopterConfig) -> Result<(), Box<dyn Error>> {
 
 	Ok(())
 }
+
+/// Converts a row-major matrix, as stored in config.
+pub fn matrix_from_rows(rows: &[[f64; 3]; 3]) -> Matrix3<f64> {
+	Matrix3::from_fn(|i, j| rows[i][j])
+}
+
+/// Converts a matrix to rows, as stored in config.
+pub fn matrix_to_rows(matrix: &Matrix3<f64>) -> [[f64; 3]; 3] {
+	[
+		[matrix[(0, 0)], matrix[(0, 1)], matrix[(0, 2)]],
+		[matrix[(1, 0)], matrix[(1, 1)], matrix[(1, 2)]],
+		[matrix[(2, 0)], matrix[(2, 1)], matrix[(2, 2)]],
+	]
+}
+
+/// Sensors whose calibration still has its default value, the magnetometer being only required
+/// when used by the AHRS.
+pub fn missing_calibrations(config: &QuadcopterConfig) -> Vec<&'static str> {
+	const IDENTITY: [[f64; 3]; 3] = [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+
+	let mut missing = Vec::new();
+
+	if config.calibration_acc == [0.; 3] && config.calibration_acc_matrix == IDENTITY {
+		missing.push("accelerometer");
+	}
+
+	if config.calibration_gyr_bias == GyrBiasModel::default() {
+		missing.push("gyroscope");
+	}
+
+	if config.ahrs_use_magnetometer
+		&& config.calibration_mag_offset == [0.; 3]
+		&& config.calibration_mag_matrix == IDENTITY {
+		missing.push("magnetometer");
+	}
+
+	missing
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::ahrs_config::AhrsConfig;
+	use crate::filter_config::{DTermFilterConfig, ImuFilterConfig};
+	use crate::quadcopter_config::{from_json, missing_calibrations, validate_realtime_options, QuadcopterConfig};
+	use serde_json::json;
+
+	#[test]
+	fn legacy_fields_test() {
+		let (config, notices) = from_json(json!({
+			"calibration_gyr": [0.01, -0.02, 0.03],
+			"ahrs_madgwick_beta": 0.05,
+			"filter_gyr_abg": [0.1, 0.01, 0.02],
+			"filter_gyr_low_pass": [100.0, 0.5, 952.0],
+			"filter_d_term_ab": [0.01, 0.001],
+		})).unwrap();
+
+		assert_eq!(notices.len(), 4);
+		assert_eq!(config.calibration_gyr_bias.coefficients, vec![[0.01, -0.02, 0.03]]);
+		assert!(missing_calibrations(&config).iter().all(|&sensor| sensor != "gyroscope"));
+
+		assert!(matches!(config.ahrs, AhrsConfig::Madgwick { beta } if beta == 0.05));
+
+		assert!(matches!(config.filter_gyr[..], [
+			ImuFilterConfig::LowPass { cutoff_frequency, q, sample_frequency },
+			ImuFilterConfig::AlphaBetaGamma { alpha, beta, gamma },
+		] if (cutoff_frequency, q, sample_frequency, alpha, beta, gamma) == (100., 0.5, 952., 0.1, 0.01, 0.02)));
+
+		assert!(matches!(config.filter_d_term[..], [DTermFilterConfig::AlphaBeta { alpha, beta }]
+			if (alpha, beta) == (0.01, 0.001)));
+	}
+
+	#[test]
+	fn legacy_field_ignored_test() {
+		let (config, notices) = from_json(json!({
+			"ahrs_madgwick_beta": 0.05,
+			"ahrs": { "type": "mahony", "k_p": 1.0, "k_i": 0.1 },
+		})).unwrap();
+
+		assert_eq!(notices.len(), 1);
+		assert!(matches!(config.ahrs, AhrsConfig::Mahony { .. }));
+	}
+
+	#[test]
+	fn realtime_options_test() {
+		assert!(validate_realtime_options(&QuadcopterConfig::default().realtime).is_ok());
+
+		assert!(from_json(json!({ "realtime": { "ImuInputController": { "fifo_priority": 99 } } })).is_ok());
+		assert!(from_json(json!({ "realtime": { "ImuInputControler": { "fifo_priority": 80 } } })).is_err());
+		assert!(from_json(json!({ "realtime": { "ImuInputController": { "fifo_priority": 0 } } })).is_err());
+		assert!(from_json(json!({ "realtime": { "ImuInputController": { "fifo_priority": 100 } } })).is_err());
+	}
+}
@@ -0,0 +1,304 @@
+use crossbeam_channel::{unbounded, Receiver};
+use std::{
+	error::Error,
+	fs::File,
+	io::{BufRead, BufReader},
+	time::{Duration, Instant},
+};
+
+use autopilot::{Autopilot, BarometerData, ImuData, ImuSource, Input, InputController, NavioAdcData, RcChannels};
+use nalgebra::Vector3;
+
+use crate::ahrs_config;
+use crate::filter_config;
+use crate::input_controllers::imu_input_controller::ImuInputController;
+use crate::mixer::Mixer;
+use crate::quadcopter::QuadcopterCollector;
+use crate::quadcopter_autopilot::QuadcopterAutopilot;
+use crate::quadcopter_config::QuadcopterConfig;
+
+/// Input record parsed from a black box text log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogRecord {
+	/// Calibrated accelerometer and gyroscope samples, before filtering. Magnetometer samples are
+	/// null, and temperature is 25 °C, in logs that do not record them.
+	Imu {
+		acc: Vector3<f64>,
+		gyr: Vector3<f64>,
+		mag: Vector3<f64>,
+		temperature: f64,
+	},
+	RcChannels(RcChannels<f64>),
+	Adc {
+		external_voltage: f64,
+		external_current: f64,
+		board_voltage: f64,
+	},
+	Barometer {
+		pressure: f64,
+		temperature: f64,
+	},
+}
+
+/// Record with its time since the start of the logged flight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+	pub time: Duration,
+	pub record: LogRecord,
+}
+
+/// Parses a line formatted as `[time][target] message`. Lines of targets that are not inputs
+/// of the autopilot, as well as malformed lines, are ignored.
+pub fn parse_line(line: &str) -> Option<LogEntry> {
+	let line = line.strip_prefix('[')?;
+	let (time, line) = line.split_at(line.find("][")?);
+	let line = &line[2..];
+	let (target, message) = line.split_at(line.find(']')?);
+	let message = message[1..].trim();
+
+	let time = Duration::from_secs_f64(time.parse().ok()?);
+
+	let record = match target {
+		"lsm9ds1" => {
+			let values = parse_values(message)?;
+
+			if values.len() < 6 {
+				return None;
+			}
+
+			LogRecord::Imu {
+				acc: Vector3::new(values[0], values[1], values[2]),
+				gyr: Vector3::new(values[3], values[4], values[5]),
+				mag: if values.len() >= 15 {
+					Vector3::new(values[12], values[13], values[14])
+				} else {
+					Vector3::zeros()
+				},
+				temperature: values.get(15).copied().unwrap_or(25.),
+			}
+		}
+		"navio_rc" => {
+			let values = parse_values(message)?;
+
+			if values.len() != 17 {
+				return None;
+			}
+
+			if values[0] == 1. {
+				let mut channels = [0.; 16];
+				channels.copy_from_slice(&values[1..]);
+				LogRecord::RcChannels(Some(channels))
+			} else {
+				LogRecord::RcChannels(None)
+			}
+		}
+		"adc" => {
+			// Formatted by `NavioAdcData` as "Ext. voltage: 0.0 V, Ext. current: 0.0 A, Board..."
+			let values = message
+				.split(',')
+				.map(|field| field.split(':').nth(1)?.split_whitespace().next()?.parse().ok())
+				.collect::<Option<Vec<f64>>>()?;
+
+			if values.len() != 3 {
+				return None;
+			}
+
+			LogRecord::Adc {
+				external_voltage: values[0],
+				external_current: values[1],
+				board_voltage: values[2],
+			}
+		}
+		"barometer" => {
+			let values = parse_values(message)?;
+
+			if values.len() != 2 {
+				return None;
+			}
+
+			LogRecord::Barometer {
+				pressure: values[0],
+				temperature: values[1],
+			}
+		}
+		_ => return None,
+	};
+
+	Some(LogEntry { time, record })
+}
+
+fn parse_values(message: &str) -> Option<Vec<f64>> {
+	message
+		.split_whitespace()
+		.map(|value| value.parse().ok())
+		.collect()
+}
+
+/// IMU replaying samples pushed by `replay`, one sample per read.
+struct ReplayImu {
+	samples: Receiver<ImuData<f64>>,
+}
+
+impl ImuSource for ReplayImu {
+	fn read_imu(&mut self) -> Result<ImuData<f64>, Box<dyn Error>> {
+		Ok(self.samples.try_recv()?)
+	}
+}
+
+/// Feeds the inputs recorded in a black box log to a collector and an autopilot built from
+/// `config`, and prints the recomputed ESC commands as `time esc0 esc1 esc2 esc3` lines. Recorded
+/// times are converted back to instants, so that filters, AHRS and PIDs see the original timing.
+pub fn replay(path: &str, config: &QuadcopterConfig) -> anyhow::Result<()> {
+	let (sample_sender, sample_receiver) = unbounded::<ImuData<f64>>();
+
+	// IMU samples are logged after calibration, therefore no calibration is applied
+	let mut imu_input_controller = ImuInputController::new(
+		Box::new(ReplayImu { samples: sample_receiver }),
+		ahrs_config::ahrs(&config.ahrs),
+		filter_config::imu_filter_chain(&config.filter_acc),
+		filter_config::imu_filter_chain(&config.filter_gyr),
+	).with_magnetometer(config.ahrs_use_magnetometer);
+
+	let mut collector = QuadcopterCollector::new(config.battery);
+
+	let mut autopilot = QuadcopterAutopilot::new(config.pid_values,
+												 &config.filter_d_term,
+												 config.rates,
+												 config.limits,
+												 config.acro_rates,
+												 Mixer {
+													 min_output: config.output_esc_min_value
+												 },
+												 config.altitude_hold,
+												 config.heading_hold,
+												 config.position_control,
+												 config.failsafe,
+												 config.arming,
+												 config.watchdog);
+
+	let start_instant = Instant::now();
+
+	collector.collect_at(Input::SoftArmed(true), start_instant);
+
+	for line in BufReader::new(File::open(path)?).lines() {
+		let entry = match parse_line(&line?) {
+			Some(entry) => entry,
+			None => continue,
+		};
+
+		let instant = start_instant + entry.time;
+
+		let input = match entry.record {
+			LogRecord::Imu { acc, gyr, mag, temperature } => {
+				sample_sender.send(ImuData { acc, gyr, mag, temperature, instant })?;
+
+				imu_input_controller
+					.read_input()
+					.map_err(|e| anyhow!("Failed to replay IMU sample at {:.3} s: {}",
+										 entry.time.as_secs_f64(), e))?
+			}
+			LogRecord::RcChannels(rc_channels) => Input::RcChannels(rc_channels),
+			LogRecord::Adc { external_voltage, external_current, board_voltage } => {
+				Input::NavioAdc(NavioAdcData {
+					external_voltage,
+					external_current,
+					board_voltage,
+					instant,
+					..NavioAdcData::default()
+				})
+			}
+			LogRecord::Barometer { pressure, temperature } => {
+				Input::Barometer(BarometerData { pressure, temperature, instant })
+			}
+		};
+
+		let output_frame = autopilot.output_frame(collector.collect_at(input, instant));
+
+		let esc_channels = output_frame.esc_channels;
+		println!("{:.3} {} {} {} {}",
+				 entry.time.as_secs_f64(),
+				 esc_channels[0], esc_channels[1], esc_channels[2], esc_channels[3]);
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::replay::{parse_line, LogEntry, LogRecord};
+	use nalgebra::Vector3;
+	use std::time::Duration;
+
+	#[test]
+	fn parse_imu_test() {
+		assert_eq!(
+			parse_line("[1.250][lsm9ds1] 0.1 0.2 9.8 0.01 0.02 0.03 0 0 0 0 0 0"),
+			Some(LogEntry {
+				time: Duration::from_millis(1250),
+				record: LogRecord::Imu {
+					acc: Vector3::new(0.1, 0.2, 9.8),
+					gyr: Vector3::new(0.01, 0.02, 0.03),
+					mag: Vector3::zeros(),
+					temperature: 25.,
+				},
+			})
+		);
+
+		let line = "[1.250][lsm9ds1] 0.1 0.2 9.8 0.01 0.02 0.03 0 0 0 0 0 0 0.2 0 -0.4";
+		assert_eq!(parse_line(line).unwrap().record, LogRecord::Imu {
+			acc: Vector3::new(0.1, 0.2, 9.8),
+			gyr: Vector3::new(0.01, 0.02, 0.03),
+			mag: Vector3::new(0.2, 0., -0.4),
+			temperature: 25.,
+		});
+
+		let line = "[1.250][lsm9ds1] 0.1 0.2 9.8 0.01 0.02 0.03 0 0 0 0 0 0 0.2 0 -0.4 41.5";
+		assert_eq!(parse_line(line).unwrap().record, LogRecord::Imu {
+			acc: Vector3::new(0.1, 0.2, 9.8),
+			gyr: Vector3::new(0.01, 0.02, 0.03),
+			mag: Vector3::new(0.2, 0., -0.4),
+			temperature: 41.5,
+		});
+	}
+
+	#[test]
+	fn parse_rc_test() {
+		let line = "[2.000][navio_rc] 1 0.5 0.5 0 0.5 1 0 0 0 0 0 0 0 0 0 0 0";
+		let mut channels = [0.; 16];
+		channels[0] = 0.5;
+		channels[1] = 0.5;
+		channels[3] = 0.5;
+		channels[4] = 1.;
+
+		assert_eq!(parse_line(line).unwrap().record, LogRecord::RcChannels(Some(channels)));
+
+		let line = "[2.000][navio_rc] 0 0.5 0.5 0 0.5 1 0 0 0 0 0 0 0 0 0 0 0";
+		assert_eq!(parse_line(line).unwrap().record, LogRecord::RcChannels(None));
+	}
+
+	#[test]
+	fn parse_adc_test() {
+		let line = "[3.500][adc] Ext. voltage: 12.1 V, Ext. current: 3.4 A, Board voltage: 5.0 V";
+
+		assert_eq!(parse_line(line).unwrap().record, LogRecord::Adc {
+			external_voltage: 12.1,
+			external_current: 3.4,
+			board_voltage: 5.0,
+		});
+	}
+
+	#[test]
+	fn parse_barometer_test() {
+		assert_eq!(parse_line("[4.000][barometer] 100009 20.07").unwrap().record, LogRecord::Barometer {
+			pressure: 100009.,
+			temperature: 20.07,
+		});
+	}
+
+	#[test]
+	fn ignored_lines_test() {
+		assert_eq!(parse_line("[0.000][quadcopter] Autopilot 0.1.0"), None);
+		assert_eq!(parse_line("[0.000][lsm9ds1] 0.1 0.2"), None);
+		assert_eq!(parse_line("not a log line"), None);
+	}
+}
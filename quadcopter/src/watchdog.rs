@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::quadcopter::QuadcopterInputFrame;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct WatchdogConfig {
+	/// Ages (s) above which inputs are stale.
+	pub max_orientation_age: f64,
+	pub max_rc_channels_age: f64,
+	pub max_navio_adc_age: f64,
+	pub max_barometer_age: f64,
+	pub max_gnss_age: f64,
+}
+
+impl Default for WatchdogConfig {
+	fn default() -> Self {
+		WatchdogConfig {
+			max_orientation_age: 0.1,
+			max_rc_channels_age: 0.5,
+			max_navio_adc_age: 1.,
+			max_barometer_age: 0.5,
+			max_gnss_age: 2.,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StaleInput {
+	Orientation,
+	RcChannels,
+	NavioAdc,
+	Barometer,
+	Gnss,
+}
+
+/// Detects inputs that are no longer collected, e.g. once the thread of an input controller died
+/// or its sensor keeps failing, and removes their data from input frames so that the autopilot
+/// falls back on degraded actions:
+/// - stale RC channels are handled by the failsafe as a lost RC signal,
+/// - stale barometer samples disable altitude hold,
+/// - stale GNSS samples disable navigation,
+/// - stale power module samples disable the battery estimate,
+/// - a stale orientation stops motors, as attitude can no longer be controlled.
+pub struct InputWatchdog {
+	config: WatchdogConfig,
+	stale: Vec<StaleInput>,
+}
+
+impl InputWatchdog {
+	pub fn new(config: WatchdogConfig) -> Self {
+		Self {
+			config,
+			stale: Vec::new(),
+		}
+	}
+
+	/// Stale inputs of a frame at the current instant. Inputs never collected are not stale, as
+	/// their data is not available anyway.
+	pub fn stale_inputs(&self, input_frame: &QuadcopterInputFrame, instant: Instant) -> Vec<StaleInput> {
+		let config = &self.config;
+		let timestamps = &input_frame.timestamps;
+
+		let stale = |timestamp: Option<Instant>, max_age: f64| timestamp
+			.map_or(false, |timestamp| instant.saturating_duration_since(timestamp) > Duration::from_secs_f64(max_age));
+
+		[(StaleInput::Orientation, stale(timestamps.orientation, config.max_orientation_age)),
+			(StaleInput::RcChannels, stale(timestamps.rc_channels, config.max_rc_channels_age)),
+			(StaleInput::NavioAdc, stale(timestamps.navio_adc, config.max_navio_adc_age)),
+			(StaleInput::Barometer, stale(timestamps.barometer, config.max_barometer_age)),
+			(StaleInput::Gnss, stale(timestamps.gnss, config.max_gnss_age))]
+			.iter()
+			.filter(|(_, stale)| *stale)
+			.map(|&(input, _)| input)
+			.collect()
+	}
+
+	/// Removes stale data from an input frame, logging inputs going stale or fresh again. Returns
+	/// whether the orientation is stale.
+	pub fn apply(&mut self, input_frame: &mut QuadcopterInputFrame, instant: Instant) -> bool {
+		let stale = self.stale_inputs(input_frame, instant);
+
+		for input in stale.iter().filter(|input| !self.stale.contains(input)) {
+			error!("{:?} input is stale", input);
+		}
+
+		for input in self.stale.iter().filter(|input| !stale.contains(input)) {
+			info!("{:?} input is fresh again", input);
+		}
+
+		for input in &stale {
+			match input {
+				// The placeholder orientation is ignored by the autopilot
+				StaleInput::Orientation => {}
+				StaleInput::RcChannels => input_frame.rc_channels = None,
+				StaleInput::NavioAdc => input_frame.battery = None,
+				StaleInput::Barometer => input_frame.altitude = None,
+				StaleInput::Gnss => input_frame.navigation = None,
+			}
+		}
+
+		let orientation_stale = stale.contains(&StaleInput::Orientation);
+		self.stale = stale;
+
+		orientation_stale
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::battery::BatteryConfig;
+	use crate::quadcopter::QuadcopterCollector;
+	use crate::watchdog::{InputWatchdog, StaleInput, WatchdogConfig};
+	use autopilot::{BarometerData, Collector, Input};
+	use std::time::{Duration, Instant};
+
+	#[test]
+	fn stale_test() {
+		let mut watchdog = InputWatchdog::new(WatchdogConfig::default());
+		let mut collector = QuadcopterCollector::new(BatteryConfig::default());
+		let instant = Instant::now();
+
+		// Inputs never collected are not stale
+		let mut frame = collector.collect(Input::RcChannels(Some([0.5; 16])));
+		frame.timestamps.rc_channels = Some(instant);
+		assert_eq!(watchdog.stale_inputs(&frame, instant + Duration::from_secs(10)), vec![StaleInput::RcChannels]);
+		assert_eq!(watchdog.stale_inputs(&frame, instant + Duration::from_millis(100)), vec![]);
+
+		frame.timestamps.orientation = Some(instant);
+		frame.timestamps.gnss = Some(instant);
+
+		let later = instant + Duration::from_secs(1);
+		assert_eq!(watchdog.stale_inputs(&frame, later), vec![StaleInput::Orientation, StaleInput::RcChannels]);
+
+		// Stale RC channels are removed, as if the signal was lost
+		assert!(watchdog.apply(&mut frame, later));
+		assert_eq!(frame.rc_channels, None);
+
+		frame.timestamps.orientation = Some(later);
+		assert!(!watchdog.apply(&mut frame, later));
+	}
+
+	#[test]
+	fn input_instants_test() {
+		let mut watchdog = InputWatchdog::new(WatchdogConfig::default());
+		let mut collector = QuadcopterCollector::new(BatteryConfig::default());
+		let instant = Instant::now() + Duration::from_secs(60);
+
+		// Inputs are stamped with their own instants, e.g. when replayed faster than real time
+		let barometer = BarometerData { pressure: 101_325., temperature: 20., instant };
+		let frame = collector.collect_at(Input::Barometer(barometer), Instant::now());
+		assert_eq!(frame.timestamps.barometer, Some(instant));
+		assert_eq!(frame.instant, instant);
+
+		let later = instant + Duration::from_secs(1);
+		let mut frame = collector.collect_at(Input::RcChannels(Some([0.5; 16])), later);
+		assert_eq!(frame.instant, later);
+
+		// The barometer goes stale by input instants, even if no time elapsed meanwhile
+		assert_eq!(watchdog.stale_inputs(&frame, frame.instant), vec![StaleInput::Barometer]);
+		assert!(!watchdog.apply(&mut frame, frame.instant));
+		assert_eq!(frame.altitude, None);
+	}
+}
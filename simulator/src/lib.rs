@@ -0,0 +1,24 @@
+#[cfg(test)]
+#[macro_use]
+extern crate assert_approx_eq;
+
+#[macro_use]
+extern crate log;
+
+mod quadcopter_model;
+mod simulated_barometer;
+mod simulated_escs;
+mod simulated_gnss;
+mod simulated_imu;
+mod simulated_power_module;
+mod simulated_rc;
+mod simulator;
+
+pub use quadcopter_model::*;
+pub use simulated_barometer::SimulatedBarometer;
+pub use simulated_escs::SimulatedEscs;
+pub use simulated_gnss::SimulatedGnss;
+pub use simulated_imu::SimulatedImu;
+pub use simulated_power_module::SimulatedPowerModule;
+pub use simulated_rc::SimulatedRc;
+pub use simulator::Simulator;
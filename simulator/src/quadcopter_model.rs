@@ -0,0 +1,278 @@
+use nalgebra::{UnitQuaternion, Vector3, zero};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+pub const G: f64 = 9.80665;
+pub const MOTORS: usize = 4;
+
+/// Motor positions `(x, y)` in arm lengths and spin direction, in the same order as `Mixer`
+/// outputs. The body frame is x forward, y left, z up. A spin direction of `1.` means that the
+/// reaction torque of the motor is positive around the z axis.
+const MOTOR_LAYOUT: [(f64, f64, f64); MOTORS] = [
+	(-FRAC_1_SQRT_2, -FRAC_1_SQRT_2, 1.),
+	(FRAC_1_SQRT_2, -FRAC_1_SQRT_2, -1.),
+	(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 1.),
+	(-FRAC_1_SQRT_2, FRAC_1_SQRT_2, -1.),
+];
+
+/// Physical parameters of the simulated airframe, in international system units.
+#[derive(Debug, Clone)]
+pub struct QuadcopterParameters {
+	pub mass: f64,
+	/// Diagonal of the inertia matrix (kg.m²).
+	pub inertia: Vector3<f64>,
+	pub arm_length: f64,
+	/// Thrust of a single motor at full command (N).
+	pub max_motor_thrust: f64,
+	/// Reaction torque around z axis per newton of thrust (m).
+	pub motor_torque_coefficient: f64,
+	/// Time constant of the first order response of a motor to a command change (s).
+	pub motor_time_constant: f64,
+	/// Current drawn by a single motor at full command (A).
+	pub max_motor_current: f64,
+	pub linear_drag: f64,
+	pub angular_drag: f64,
+	pub battery_voltage: f64,
+	pub battery_internal_resistance: f64,
+	/// Earth magnetic field in world frame (gauss).
+	pub magnetic_field: Vector3<f64>,
+}
+
+impl Default for QuadcopterParameters {
+	fn default() -> Self {
+		QuadcopterParameters {
+			mass: 1.2,
+			inertia: Vector3::new(0.011, 0.011, 0.021),
+			arm_length: 0.225,
+			max_motor_thrust: 8.0,
+			motor_torque_coefficient: 0.016,
+			motor_time_constant: 0.02,
+			max_motor_current: 12.0,
+			linear_drag: 0.25,
+			angular_drag: 0.01,
+			battery_voltage: 12.6,
+			battery_internal_resistance: 0.02,
+			magnetic_field: Vector3::new(0.22, 0., -0.42),
+		}
+	}
+}
+
+/// Kinematic state of the simulated airframe. World frame is x north, y west, z up.
+#[derive(Debug, Clone)]
+pub struct QuadcopterState {
+	pub position: Vector3<f64>,
+	pub velocity: Vector3<f64>,
+	/// Linear acceleration in world frame, as computed during the latest step.
+	pub acceleration: Vector3<f64>,
+	/// Rotation from body frame to world frame.
+	pub attitude: UnitQuaternion<f64>,
+	/// Angular velocity in body frame.
+	pub angular_velocity: Vector3<f64>,
+	/// Normalized motor speeds within (0., 1.).
+	pub motor_speeds: [f64; MOTORS],
+}
+
+impl Default for QuadcopterState {
+	fn default() -> Self {
+		QuadcopterState {
+			position: zero(),
+			velocity: zero(),
+			acceleration: zero(),
+			attitude: UnitQuaternion::identity(),
+			angular_velocity: zero(),
+			motor_speeds: [0.; MOTORS],
+		}
+	}
+}
+
+/// Rigid body model of a quadcopter in X configuration, with first order motor dynamics,
+/// quadratic thrust, linear drag and a flat ground at zero altitude.
+pub struct QuadcopterModel {
+	pub parameters: QuadcopterParameters,
+	pub state: QuadcopterState,
+	motor_commands: [f64; MOTORS],
+}
+
+impl QuadcopterModel {
+	pub fn new(parameters: QuadcopterParameters) -> Self {
+		Self {
+			parameters,
+			state: QuadcopterState::default(),
+			motor_commands: [0.; MOTORS],
+		}
+	}
+
+	/// Sets motor commands, expected to be within (0., 1.) as sent to ESCs.
+	pub fn set_motor_commands(&mut self, commands: [f64; MOTORS]) {
+		for (command, &value) in self.motor_commands.iter_mut().zip(commands.iter()) {
+			*command = value.max(0.).min(1.);
+		}
+	}
+
+	/// Motor command for which total thrust balances weight.
+	pub fn hover_command(&self) -> f64 {
+		(self.parameters.mass * G / (MOTORS as f64 * self.parameters.max_motor_thrust)).sqrt()
+	}
+
+	pub fn step(&mut self, dt: f64) {
+		let parameters = &self.parameters;
+		let state = &mut self.state;
+
+		// Motors
+		let motor_filter_gain = dt / (parameters.motor_time_constant + dt);
+		let mut thrusts = [0.; MOTORS];
+
+		for i in 0..MOTORS {
+			state.motor_speeds[i] += (self.motor_commands[i] - state.motor_speeds[i]) * motor_filter_gain;
+			thrusts[i] = parameters.max_motor_thrust * state.motor_speeds[i] * state.motor_speeds[i];
+		}
+
+		// Rotation
+		let mut torque: Vector3<f64> = -state.angular_velocity * parameters.angular_drag;
+
+		for (&(x, y, spin), &thrust) in MOTOR_LAYOUT.iter().zip(thrusts.iter()) {
+			torque += Vector3::new(y * parameters.arm_length * thrust,
+								   -x * parameters.arm_length * thrust,
+								   spin * parameters.motor_torque_coefficient * thrust);
+		}
+
+		let angular_momentum = parameters.inertia.component_mul(&state.angular_velocity);
+		let angular_acceleration = (torque - state.angular_velocity.cross(&angular_momentum))
+			.component_div(&parameters.inertia);
+
+		state.angular_velocity += angular_acceleration * dt;
+		state.attitude = state.attitude * UnitQuaternion::from_scaled_axis(state.angular_velocity * dt);
+
+		// Translation
+		let total_thrust: f64 = thrusts.iter().sum();
+		let force = state.attitude.transform_vector(&Vector3::new(0., 0., total_thrust))
+			- state.velocity * parameters.linear_drag
+			- Vector3::new(0., 0., parameters.mass * G);
+
+		state.acceleration = force / parameters.mass;
+		state.velocity += state.acceleration * dt;
+		state.position += state.velocity * dt;
+
+		// Ground reaction
+		if state.position.z <= 0. {
+			state.position.z = 0.;
+
+			if state.velocity.z <= 0. {
+				state.velocity = zero();
+				state.acceleration = zero();
+				state.angular_velocity = zero();
+			}
+		}
+	}
+
+	/// Specific force in body frame, as measured by an ideal accelerometer.
+	pub fn accelerometer(&self) -> Vector3<f64> {
+		self.state.attitude.inverse_transform_vector(&(self.state.acceleration + Vector3::new(0., 0., G)))
+	}
+
+	/// Angular velocity in body frame, as measured by an ideal gyroscope.
+	pub fn gyroscope(&self) -> Vector3<f64> {
+		self.state.angular_velocity
+	}
+
+	/// Earth magnetic field in body frame, as measured by an ideal magnetometer.
+	pub fn magnetometer(&self) -> Vector3<f64> {
+		self.state.attitude.inverse_transform_vector(&self.parameters.magnetic_field)
+	}
+
+	/// Total current drawn by motors, assuming electrical power grows with the cube of speed.
+	pub fn current(&self) -> f64 {
+		self.state.motor_speeds
+			.iter()
+			.map(|speed| self.parameters.max_motor_current * speed.powi(3))
+			.sum()
+	}
+
+	pub fn battery_voltage(&self) -> f64 {
+		self.parameters.battery_voltage - self.current() * self.parameters.battery_internal_resistance
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::quadcopter_model::{QuadcopterModel, QuadcopterParameters, G, MOTORS};
+
+	const DT: f64 = 0.001;
+
+	fn run(model: &mut QuadcopterModel, duration: f64) {
+		for _ in 0..(duration / DT) as usize {
+			model.step(DT);
+		}
+	}
+
+	#[test]
+	fn free_fall_test() {
+		let mut model = QuadcopterModel::new(QuadcopterParameters {
+			linear_drag: 0.,
+			..QuadcopterParameters::default()
+		});
+		model.state.position.z = 10.;
+
+		run(&mut model, 1.);
+
+		assert_approx_eq!(model.state.position.z, 10. - G / 2., 0.02);
+		assert_approx_eq!(model.state.velocity.z, -G, 0.02);
+		assert_approx_eq!(model.accelerometer().norm(), 0.);
+	}
+
+	#[test]
+	fn resting_test() {
+		let mut model = QuadcopterModel::new(QuadcopterParameters::default());
+
+		run(&mut model, 0.5);
+
+		assert_approx_eq!(model.state.position.z, 0.);
+		assert_approx_eq!(model.accelerometer().z, G);
+		assert_approx_eq!((model.magnetometer() - model.parameters.magnetic_field).norm(), 0.);
+	}
+
+	#[test]
+	fn hover_test() {
+		let mut model = QuadcopterModel::new(QuadcopterParameters::default());
+		model.state.position.z = 10.;
+		let hover_command = model.hover_command();
+		model.state.motor_speeds = [hover_command; MOTORS];
+		model.set_motor_commands([hover_command; MOTORS]);
+
+		run(&mut model, 1.);
+
+		assert_approx_eq!(model.state.position.z, 10.);
+		assert_approx_eq!(model.state.angular_velocity.norm(), 0.);
+		assert_approx_eq!(model.accelerometer().z, G);
+	}
+
+	#[test]
+	fn torque_direction_test() {
+		let airborne_model = || {
+			let mut model = QuadcopterModel::new(QuadcopterParameters::default());
+			model.state.position.z = 10.;
+			model
+		};
+
+		let mut model = airborne_model();
+		let h = model.hover_command();
+
+		// Motors 2 and 3 roll right side up
+		model.set_motor_commands([h, h, h + 0.05, h + 0.05]);
+		run(&mut model, 0.1);
+		assert!(model.gyroscope().x > 0.);
+
+		// Motors 0 and 3 pitch nose down
+		let mut model = airborne_model();
+		model.set_motor_commands([h + 0.05, h, h, h + 0.05]);
+		run(&mut model, 0.1);
+		assert!(model.gyroscope().y > 0.);
+
+		// Motors 0 and 2 yaw left
+		let mut model = airborne_model();
+		model.set_motor_commands([h + 0.05, h, h + 0.05, h]);
+		run(&mut model, 0.1);
+		assert!(model.gyroscope().z > 0.);
+		assert_approx_eq!(model.gyroscope().x, 0.);
+		assert_approx_eq!(model.gyroscope().y, 0.);
+	}
+}
@@ -0,0 +1,61 @@
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use std::{
+	error::Error,
+	sync::{Arc, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+
+use autopilot::{BarometerData, BarometerSource};
+
+use crate::quadcopter_model::QuadcopterModel;
+
+/// Simulated barometer, converting the model altitude to pressure with the international standard
+/// atmosphere, with gaussian noise.
+pub struct SimulatedBarometer {
+	model: Arc<Mutex<QuadcopterModel>>,
+	rng: StdRng,
+	pressure_noise: Normal<f64>,
+}
+
+impl SimulatedBarometer {
+	/// Pressure at zero altitude (Pa).
+	const GROUND_PRESSURE: f64 = 101_325.;
+	const TEMPERATURE: f64 = 25.;
+	/// Standard deviation of pressure noise (Pa), about 10 cm.
+	const PRESSURE_NOISE: f64 = 1.2;
+	/// Time taken by a pressure and temperature conversion.
+	const CONVERSION_DELAY: Duration = Duration::from_millis(20);
+
+	pub fn new(model: Arc<Mutex<QuadcopterModel>>) -> Self {
+		Self {
+			model,
+			rng: StdRng::from_entropy(),
+			pressure_noise: Normal::new(0., Self::PRESSURE_NOISE).unwrap(),
+		}
+	}
+
+	pub fn pressure(altitude: f64) -> f64 {
+		Self::GROUND_PRESSURE * (1. - altitude / 44_330.).powf(5.255)
+	}
+}
+
+impl BarometerSource for SimulatedBarometer {
+	fn read_barometer(&mut self) -> Result<BarometerData<f64>, Box<dyn Error>> {
+		thread::sleep(Self::CONVERSION_DELAY);
+
+		let altitude = self.model
+			.lock()
+			.map_err(|_| "Simulated model is poisoned")?
+			.state
+			.position
+			.z;
+
+		Ok(BarometerData {
+			pressure: Self::pressure(altitude) + self.pressure_noise.sample(&mut self.rng),
+			temperature: Self::TEMPERATURE,
+			instant: Instant::now(),
+		})
+	}
+}
@@ -0,0 +1,37 @@
+use std::{
+	error::Error,
+	sync::{Arc, Mutex},
+};
+
+use autopilot::MotorSink;
+
+use crate::quadcopter_model::{QuadcopterModel, MOTORS};
+
+/// Forwards ESC commands to the motors of the simulated model.
+pub struct SimulatedEscs {
+	model: Arc<Mutex<QuadcopterModel>>,
+}
+
+impl SimulatedEscs {
+	pub fn new(model: Arc<Mutex<QuadcopterModel>>) -> Self {
+		Self { model }
+	}
+}
+
+impl MotorSink for SimulatedEscs {
+	fn write_motors(&mut self, outputs: &[f64]) -> Result<(), Box<dyn Error>> {
+		if outputs.len() != MOTORS {
+			return Err(format!("Expected {} motor outputs, got {}", MOTORS, outputs.len()).into());
+		}
+
+		// Outputs are expected to be between (0., +1.)
+		debug!(target: "esc", "{} {} {} {}", outputs[0], outputs[1], outputs[2], outputs[3]);
+
+		self.model
+			.lock()
+			.map_err(|_| "Simulated model is poisoned")?
+			.set_motor_commands([outputs[0], outputs[1], outputs[2], outputs[3]]);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,89 @@
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use std::{
+	error::Error,
+	sync::{Arc, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+
+use autopilot::{GnssData, GnssFix, GnssSource};
+use nalgebra::Vector3;
+use navigation::{GeodeticPosition, LocalFrame};
+
+use crate::quadcopter_model::QuadcopterModel;
+
+/// Simulated GNSS receiver, converting the model position to geodetic coordinates around a fixed
+/// origin, with gaussian noise.
+pub struct SimulatedGnss {
+	model: Arc<Mutex<QuadcopterModel>>,
+	frame: LocalFrame,
+	rng: StdRng,
+	horizontal_noise: Normal<f64>,
+	vertical_noise: Normal<f64>,
+	speed_noise: Normal<f64>,
+}
+
+impl SimulatedGnss {
+	/// Geodetic position of the model origin.
+	pub const ORIGIN: GeodeticPosition = GeodeticPosition {
+		latitude: 48.713202,
+		longitude: 2.200641,
+		altitude: 160.,
+	};
+	/// Period between two navigation solutions.
+	const NAVIGATION_PERIOD: Duration = Duration::from_millis(200);
+	/// Standard deviations of position (m) and velocity (m/s) noise.
+	const HORIZONTAL_NOISE: f64 = 1.;
+	const VERTICAL_NOISE: f64 = 2.;
+	const SPEED_NOISE: f64 = 0.1;
+	const SATELLITES: u8 = 12;
+
+	pub fn new(model: Arc<Mutex<QuadcopterModel>>) -> Self {
+		Self {
+			model,
+			frame: LocalFrame::new(Self::ORIGIN),
+			rng: StdRng::from_entropy(),
+			horizontal_noise: Normal::new(0., Self::HORIZONTAL_NOISE).unwrap(),
+			vertical_noise: Normal::new(0., Self::VERTICAL_NOISE).unwrap(),
+			speed_noise: Normal::new(0., Self::SPEED_NOISE).unwrap(),
+		}
+	}
+}
+
+impl GnssSource for SimulatedGnss {
+	fn read_gnss(&mut self) -> Result<GnssData<f64>, Box<dyn Error>> {
+		thread::sleep(Self::NAVIGATION_PERIOD);
+
+		let (position, velocity) = {
+			let model = self.model
+				.lock()
+				.map_err(|_| "Simulated model is poisoned")?;
+
+			(model.state.position, model.state.velocity)
+		};
+
+		// Model world frame is north-west-up
+		let ned = [
+			position.x + self.horizontal_noise.sample(&mut self.rng),
+			-position.y + self.horizontal_noise.sample(&mut self.rng),
+			-position.z + self.vertical_noise.sample(&mut self.rng),
+		];
+		let geodetic = self.frame.to_geodetic(ned);
+
+		Ok(GnssData {
+			fix: GnssFix::Fix3D,
+			latitude: geodetic.latitude,
+			longitude: geodetic.longitude,
+			altitude: geodetic.altitude,
+			velocity: Vector3::new(velocity.x + self.speed_noise.sample(&mut self.rng),
+								   -velocity.y + self.speed_noise.sample(&mut self.rng),
+								   -velocity.z + self.speed_noise.sample(&mut self.rng)),
+			horizontal_accuracy: Self::HORIZONTAL_NOISE,
+			vertical_accuracy: Self::VERTICAL_NOISE,
+			speed_accuracy: Self::SPEED_NOISE,
+			satellites: Self::SATELLITES,
+			instant: Instant::now(),
+		})
+	}
+}
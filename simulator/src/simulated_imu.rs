@@ -0,0 +1,74 @@
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use std::{
+	error::Error,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use autopilot::{ImuData, ImuSource};
+use nalgebra::Vector3;
+
+use crate::quadcopter_model::QuadcopterModel;
+
+/// Simulated IMU, sampling the model with gaussian noise.
+pub struct SimulatedImu {
+	model: Arc<Mutex<QuadcopterModel>>,
+	rng: StdRng,
+	acc_noise: Normal<f64>,
+	gyr_noise: Normal<f64>,
+	mag_noise: Normal<f64>,
+}
+
+impl SimulatedImu {
+	/// Standard deviations of sensor noises, in sensor units.
+	const ACC_NOISE: f64 = 0.05;
+	const GYR_NOISE: f64 = 0.005;
+	const MAG_NOISE: f64 = 0.002;
+	/// Constant die temperature (°C).
+	const TEMPERATURE: f64 = 25.;
+	/// Period at which samples are produced by `read_imu_batch`, i.e. 500 Hz.
+	const SAMPLE_PERIOD: Duration = Duration::from_millis(2);
+
+	pub fn new(model: Arc<Mutex<QuadcopterModel>>) -> Self {
+		Self {
+			model,
+			rng: StdRng::from_entropy(),
+			acc_noise: Normal::new(0., Self::ACC_NOISE).unwrap(),
+			gyr_noise: Normal::new(0., Self::GYR_NOISE).unwrap(),
+			mag_noise: Normal::new(0., Self::MAG_NOISE).unwrap(),
+		}
+	}
+
+	fn noise(rng: &mut StdRng, distribution: &Normal<f64>) -> Vector3<f64> {
+		Vector3::new(distribution.sample(rng),
+					 distribution.sample(rng),
+					 distribution.sample(rng))
+	}
+}
+
+impl ImuSource for SimulatedImu {
+	fn read_imu(&mut self) -> Result<ImuData<f64>, Box<dyn Error>> {
+		let (acc, gyr, mag) = {
+			let model = self.model
+				.lock()
+				.map_err(|_| "Simulated model is poisoned")?;
+
+			(model.accelerometer(), model.gyroscope(), model.magnetometer())
+		};
+
+		Ok(ImuData {
+			acc: acc + Self::noise(&mut self.rng, &self.acc_noise),
+			gyr: gyr + Self::noise(&mut self.rng, &self.gyr_noise),
+			mag: mag + Self::noise(&mut self.rng, &self.mag_noise),
+			temperature: Self::TEMPERATURE,
+			instant: Instant::now(),
+		})
+	}
+
+	fn read_imu_batch(&mut self) -> Result<Vec<ImuData<f64>>, Box<dyn Error>> {
+		std::thread::sleep(Self::SAMPLE_PERIOD);
+
+		Ok(vec![self.read_imu()?])
+	}
+}
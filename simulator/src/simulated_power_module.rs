@@ -0,0 +1,40 @@
+use std::{
+	error::Error,
+	sync::{Arc, Mutex},
+	time::Instant,
+};
+
+use autopilot::{NavioAdcData, PowerSource};
+
+use crate::quadcopter_model::QuadcopterModel;
+
+/// Simulated power module, with a battery whose voltage sags with the current drawn by motors.
+pub struct SimulatedPowerModule {
+	model: Arc<Mutex<QuadcopterModel>>,
+}
+
+impl SimulatedPowerModule {
+	const BOARD_VOLTAGE: f64 = 5.0;
+
+	pub fn new(model: Arc<Mutex<QuadcopterModel>>) -> Self {
+		Self { model }
+	}
+}
+
+impl PowerSource for SimulatedPowerModule {
+	fn read_power(&mut self) -> Result<NavioAdcData<f64>, Box<dyn Error>> {
+		let model = self.model
+			.lock()
+			.map_err(|_| "Simulated model is poisoned")?;
+
+		Ok(NavioAdcData {
+			board_voltage: Self::BOARD_VOLTAGE,
+			servo_voltage: Self::BOARD_VOLTAGE,
+			external_voltage: model.battery_voltage(),
+			external_current: model.current(),
+			adc_port_2: 0.,
+			adc_port_3: 0.,
+			instant: Instant::now(),
+		})
+	}
+}
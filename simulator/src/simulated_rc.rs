@@ -0,0 +1,38 @@
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::error::Error;
+
+use autopilot::{RcChannels, RcSource};
+
+/// Simulated RC receiver. Channels are kept constant until new values are sent through the
+/// channel returned by `sender`.
+pub struct SimulatedRc {
+	rc_channels: RcChannels<f64>,
+	sender: Sender<RcChannels<f64>>,
+	receiver: Receiver<RcChannels<f64>>,
+}
+
+impl SimulatedRc {
+	pub fn new(rc_channels: RcChannels<f64>) -> Self {
+		let (sender, receiver) = unbounded::<RcChannels<f64>>();
+
+		Self {
+			rc_channels,
+			sender,
+			receiver,
+		}
+	}
+
+	pub fn sender(&self) -> Sender<RcChannels<f64>> {
+		self.sender.clone()
+	}
+}
+
+impl RcSource for SimulatedRc {
+	fn read_rc(&mut self) -> Result<RcChannels<f64>, Box<dyn Error>> {
+		if let Some(rc_channels) = self.receiver.try_iter().last() {
+			self.rc_channels = rc_channels;
+		}
+
+		Ok(self.rc_channels)
+	}
+}
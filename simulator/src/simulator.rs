@@ -0,0 +1,96 @@
+use std::{
+	sync::{Arc, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+
+use autopilot::RcChannels;
+
+use crate::quadcopter_model::{QuadcopterModel, QuadcopterParameters};
+use crate::simulated_barometer::SimulatedBarometer;
+use crate::simulated_escs::SimulatedEscs;
+use crate::simulated_gnss::SimulatedGnss;
+use crate::simulated_imu::SimulatedImu;
+use crate::simulated_power_module::SimulatedPowerModule;
+use crate::simulated_rc::SimulatedRc;
+
+/// Shared handle on a simulated quadcopter. Simulated devices created from the same handle
+/// read from and write to the same model, which is integrated in real time by `spawn`.
+#[derive(Clone)]
+pub struct Simulator {
+	model: Arc<Mutex<QuadcopterModel>>,
+}
+
+impl Simulator {
+	/// Integration period of the physics loop.
+	pub const STEP_PERIOD: Duration = Duration::from_millis(1);
+
+	/// Period between two logs of the simulated state.
+	const LOG_PERIOD: Duration = Duration::from_millis(20);
+
+	pub fn new(parameters: QuadcopterParameters) -> Self {
+		Self {
+			model: Arc::new(Mutex::new(QuadcopterModel::new(parameters))),
+		}
+	}
+
+	pub fn model(&self) -> Arc<Mutex<QuadcopterModel>> {
+		self.model.clone()
+	}
+
+	pub fn imu(&self) -> SimulatedImu {
+		SimulatedImu::new(self.model.clone())
+	}
+
+	pub fn barometer(&self) -> SimulatedBarometer {
+		SimulatedBarometer::new(self.model.clone())
+	}
+
+	pub fn gnss(&self) -> SimulatedGnss {
+		SimulatedGnss::new(self.model.clone())
+	}
+
+	pub fn power_module(&self) -> SimulatedPowerModule {
+		SimulatedPowerModule::new(self.model.clone())
+	}
+
+	pub fn rc(&self, rc_channels: RcChannels<f64>) -> SimulatedRc {
+		SimulatedRc::new(rc_channels)
+	}
+
+	pub fn escs(&self) -> SimulatedEscs {
+		SimulatedEscs::new(self.model.clone())
+	}
+
+	fn step_loop(&self) -> ! {
+		let mut last_step_instant = Instant::now();
+		let mut last_log_instant = last_step_instant;
+
+		loop {
+			thread::sleep(Self::STEP_PERIOD);
+
+			let now = Instant::now();
+			let mut model = self.model.lock().unwrap();
+			model.step((now - last_step_instant).as_secs_f64());
+			last_step_instant = now;
+
+			if now - last_log_instant >= Self::LOG_PERIOD {
+				let state = &model.state;
+				let (roll, pitch, yaw) = state.attitude.euler_angles();
+
+				debug!(target: "simulator", "{} {} {} {} {} {} {} {} {}",
+					   state.position.x, state.position.y, state.position.z,
+					   state.velocity.x, state.velocity.y, state.velocity.z,
+					   roll, pitch, yaw);
+
+				last_log_instant = now;
+			}
+		}
+	}
+
+	/// Spawns a thread integrating the model in real time.
+	pub fn spawn(&self) -> thread::JoinHandle<()> {
+		let simulator = self.clone();
+		thread::spawn(move || simulator.step_loop())
+	}
+}
@@ -0,0 +1,7 @@
+mod messages;
+mod parser;
+mod ublox;
+
+pub use messages::*;
+pub use parser::*;
+pub use ublox::*;
@@ -0,0 +1,305 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use crate::parser::Packet;
+
+/// Message class and identifier.
+pub type MessageId = (u8, u8);
+
+pub const NAV_STATUS: MessageId = (0x01, 0x03);
+pub const NAV_PVT: MessageId = (0x01, 0x07);
+pub const ACK_NAK: MessageId = (0x05, 0x00);
+pub const ACK_ACK: MessageId = (0x05, 0x01);
+pub const CFG_PRT: MessageId = (0x06, 0x00);
+pub const CFG_MSG: MessageId = (0x06, 0x01);
+pub const CFG_RATE: MessageId = (0x06, 0x08);
+
+const NAV_PVT_LENGTH: usize = 92;
+const NAV_STATUS_LENGTH: usize = 16;
+const ACK_LENGTH: usize = 2;
+
+/// Identifier of the SPI port, as used by CFG-PRT.
+const SPI_PORT: u8 = 4;
+/// UBX protocol bit of CFG-PRT protocol masks.
+const PROTOCOL_UBX: u16 = 0x0001;
+
+/// GNSS fix type.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FixType {
+	NoFix,
+	DeadReckoning,
+	Fix2D,
+	Fix3D,
+	GnssDeadReckoning,
+	TimeOnly,
+}
+
+impl FixType {
+	fn from_byte(byte: u8) -> Self {
+		match byte {
+			1 => FixType::DeadReckoning,
+			2 => FixType::Fix2D,
+			3 => FixType::Fix3D,
+			4 => FixType::GnssDeadReckoning,
+			5 => FixType::TimeOnly,
+			_ => FixType::NoFix,
+		}
+	}
+}
+
+/// Navigation position, velocity and time solution (NAV-PVT), in degrees and international system
+/// units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavPvt {
+	/// GPS time of week of the navigation epoch (ms).
+	pub time_of_week: u32,
+	pub fix_type: FixType,
+	/// Whether the fix is valid, i.e. within the configured accuracy masks.
+	pub fix_ok: bool,
+	pub satellites: u8,
+	/// Degrees, positive northwards.
+	pub latitude: f64,
+	/// Degrees, positive eastwards.
+	pub longitude: f64,
+	/// Height above the WGS84 ellipsoid (m).
+	pub height: f64,
+	/// Height above mean sea level (m).
+	pub height_msl: f64,
+	/// Estimated horizontal and vertical accuracies (m).
+	pub horizontal_accuracy: f64,
+	pub vertical_accuracy: f64,
+	/// North, east and down velocity (m/s).
+	pub velocity: [f64; 3],
+	/// Ground speed (m/s) and heading of motion (degrees).
+	pub ground_speed: f64,
+	pub heading_of_motion: f64,
+	/// Estimated speed (m/s) and heading (degrees) accuracies.
+	pub speed_accuracy: f64,
+	pub heading_accuracy: f64,
+	/// Position dilution of precision.
+	pub position_dop: f64,
+}
+
+/// Receiver navigation status (NAV-STATUS).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavStatus {
+	/// GPS time of week of the navigation epoch (ms).
+	pub time_of_week: u32,
+	pub fix_type: FixType,
+	/// Whether the fix is valid, i.e. within the configured accuracy masks.
+	pub fix_ok: bool,
+	/// Whether differential corrections are applied.
+	pub differential: bool,
+	pub time_to_first_fix: Duration,
+	/// Time since startup or reset.
+	pub uptime: Duration,
+}
+
+/// Decoded UBX message. Messages this crate does not decode are kept as raw packets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+	NavPvt(NavPvt),
+	NavStatus(NavStatus),
+	/// Acknowledged configuration message.
+	AckAck(MessageId),
+	/// Rejected configuration message.
+	AckNak(MessageId),
+	Other(Packet),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageError {
+	/// The payload is shorter than expected for this message.
+	InvalidLength { id: MessageId, length: usize },
+}
+
+impl fmt::Display for MessageError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MessageError::InvalidLength { id, length } =>
+				write!(f, "Invalid length {} of UBX message {:#04x} {:#04x}", length, id.0, id.1),
+		}
+	}
+}
+
+impl Error for MessageError {}
+
+fn u16_at(payload: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes([payload[offset], payload[offset + 1]])
+}
+
+fn u32_at(payload: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes([payload[offset], payload[offset + 1], payload[offset + 2], payload[offset + 3]])
+}
+
+fn i32_at(payload: &[u8], offset: usize) -> i32 {
+	u32_at(payload, offset) as i32
+}
+
+impl Message {
+	/// Decodes a packet, checking its payload length (protocol specification, section 32).
+	pub fn decode(packet: &Packet) -> Result<Self, MessageError> {
+		let id = (packet.class, packet.id);
+		let payload = &packet.payload;
+
+		let expected_length = match id {
+			NAV_PVT => NAV_PVT_LENGTH,
+			NAV_STATUS => NAV_STATUS_LENGTH,
+			ACK_ACK | ACK_NAK => ACK_LENGTH,
+			_ => return Ok(Message::Other(packet.clone())),
+		};
+
+		if payload.len() < expected_length {
+			return Err(MessageError::InvalidLength { id, length: payload.len() });
+		}
+
+		Ok(match id {
+			NAV_PVT => Message::NavPvt(NavPvt {
+				time_of_week: u32_at(payload, 0),
+				fix_type: FixType::from_byte(payload[20]),
+				fix_ok: payload[21] & 0x01 != 0,
+				satellites: payload[23],
+				longitude: i32_at(payload, 24) as f64 * 1e-7,
+				latitude: i32_at(payload, 28) as f64 * 1e-7,
+				height: i32_at(payload, 32) as f64 * 1e-3,
+				height_msl: i32_at(payload, 36) as f64 * 1e-3,
+				horizontal_accuracy: u32_at(payload, 40) as f64 * 1e-3,
+				vertical_accuracy: u32_at(payload, 44) as f64 * 1e-3,
+				velocity: [
+					i32_at(payload, 48) as f64 * 1e-3,
+					i32_at(payload, 52) as f64 * 1e-3,
+					i32_at(payload, 56) as f64 * 1e-3,
+				],
+				ground_speed: i32_at(payload, 60) as f64 * 1e-3,
+				heading_of_motion: i32_at(payload, 64) as f64 * 1e-5,
+				speed_accuracy: u32_at(payload, 68) as f64 * 1e-3,
+				heading_accuracy: u32_at(payload, 72) as f64 * 1e-5,
+				position_dop: u16_at(payload, 76) as f64 * 1e-2,
+			}),
+			NAV_STATUS => Message::NavStatus(NavStatus {
+				time_of_week: u32_at(payload, 0),
+				fix_type: FixType::from_byte(payload[4]),
+				fix_ok: payload[5] & 0x01 != 0,
+				differential: payload[5] & 0x02 != 0,
+				time_to_first_fix: Duration::from_millis(u32_at(payload, 8) as u64),
+				uptime: Duration::from_millis(u32_at(payload, 12) as u64),
+			}),
+			ACK_ACK => Message::AckAck((payload[0], payload[1])),
+			_ => Message::AckNak((payload[0], payload[1])),
+		})
+	}
+}
+
+/// Configures the SPI port to use the UBX protocol only, disabling NMEA output (CFG-PRT).
+pub fn cfg_prt_spi() -> Packet {
+	let mut payload = vec![0u8; 20];
+	payload[0] = SPI_PORT;
+	payload[12..14].copy_from_slice(&PROTOCOL_UBX.to_le_bytes());
+	payload[14..16].copy_from_slice(&PROTOCOL_UBX.to_le_bytes());
+
+	Packet::new(CFG_PRT.0, CFG_PRT.1, payload)
+}
+
+/// Sets the period between two navigation solutions, aligned on GPS time (CFG-RATE).
+pub fn cfg_rate(period: Duration) -> Packet {
+	let mut payload = Vec::with_capacity(6);
+	payload.extend_from_slice(&(period.as_millis() as u16).to_le_bytes());
+	// One measurement per navigation solution, GPS time reference
+	payload.extend_from_slice(&1u16.to_le_bytes());
+	payload.extend_from_slice(&1u16.to_le_bytes());
+
+	Packet::new(CFG_RATE.0, CFG_RATE.1, payload)
+}
+
+/// Sets the output rate of a message on the current port, as a number of navigation solutions
+/// between two messages, 0 disabling the message (CFG-MSG).
+pub fn cfg_msg(message: MessageId, rate: u8) -> Packet {
+	Packet::new(CFG_MSG.0, CFG_MSG.1, vec![message.0, message.1, rate])
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::messages::{cfg_msg, cfg_rate, FixType, Message, MessageError, NAV_PVT, CFG_RATE};
+	use crate::parser::{Packet, Parser};
+	use std::time::Duration;
+
+	/// Byte stream of NAV-PVT, NAV-STATUS and ACK-ACK messages, in u-blox 8 format.
+	const STREAM: [u8; 134] = [
+		0xb5, 0x62, 0x01, 0x07, 0x5c, 0x00, 0x00, 0x70, 0x99, 0x14, 0xe4, 0x07, 0x0b, 0x03, 0x0e, 0x19,
+		0x24, 0x37, 0x19, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x0b, 0x8a, 0xca,
+		0x4f, 0x01, 0x74, 0x0b, 0x09, 0x1d, 0xec, 0x29, 0x03, 0x00, 0x78, 0x71, 0x02, 0x00, 0xf0, 0x05,
+		0x00, 0x00, 0x6a, 0x09, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00, 0xac, 0xfe, 0xff, 0xff, 0x0f, 0x00,
+		0x00, 0x00, 0x68, 0x01, 0x00, 0x00, 0x68, 0xaa, 0xb9, 0x01, 0xd2, 0x00, 0x00, 0x00, 0x20, 0x27,
+		0x45, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x1e, 0x66,
+		0xb5, 0x62, 0x01, 0x03, 0x10, 0x00, 0x00, 0x70, 0x99, 0x14, 0x03, 0x0d, 0x00, 0x02, 0x8c, 0x6e,
+		0x00, 0x00, 0x18, 0x73, 0x01, 0x00, 0xc9, 0x41,
+		0xb5, 0x62, 0x05, 0x01, 0x02, 0x00, 0x06, 0x08, 0x16, 0x3f,
+	];
+
+	fn approx_eq(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-9
+	}
+
+	#[test]
+	fn decode_test() {
+		let messages: Vec<Message> = Parser::new()
+			.parse(&STREAM)
+			.iter()
+			.map(|packet| Message::decode(packet).unwrap())
+			.collect();
+
+		assert_eq!(messages.len(), 3);
+
+		match &messages[0] {
+			Message::NavPvt(pvt) => {
+				assert_eq!(pvt.time_of_week, 345_600_000);
+				assert_eq!(pvt.fix_type, FixType::Fix3D);
+				assert!(pvt.fix_ok);
+				assert_eq!(pvt.satellites, 11);
+				assert!(approx_eq(pvt.latitude, 48.713202));
+				assert!(approx_eq(pvt.longitude, 2.200641));
+				assert!(approx_eq(pvt.height, 207.34));
+				assert!(approx_eq(pvt.height_msl, 160.12));
+				assert!(approx_eq(pvt.horizontal_accuracy, 1.52));
+				assert!(approx_eq(pvt.vertical_accuracy, 2.41));
+				assert!(approx_eq(pvt.velocity[0], 0.12));
+				assert!(approx_eq(pvt.velocity[1], -0.34));
+				assert!(approx_eq(pvt.velocity[2], 0.015));
+				assert!(approx_eq(pvt.heading_of_motion, 289.45));
+				assert!(approx_eq(pvt.speed_accuracy, 0.21));
+				assert!(approx_eq(pvt.position_dop, 1.32));
+			}
+			message => panic!("Unexpected message {:?}", message),
+		}
+
+		match &messages[1] {
+			Message::NavStatus(status) => {
+				assert_eq!(status.fix_type, FixType::Fix3D);
+				assert!(status.fix_ok);
+				assert!(!status.differential);
+				assert_eq!(status.time_to_first_fix, Duration::from_millis(28_300));
+				assert_eq!(status.uptime, Duration::from_millis(95_000));
+			}
+			message => panic!("Unexpected message {:?}", message),
+		}
+
+		assert_eq!(messages[2], Message::AckAck(CFG_RATE));
+	}
+
+	#[test]
+	fn invalid_length_test() {
+		let packet = Packet::new(NAV_PVT.0, NAV_PVT.1, vec![0; 84]);
+
+		assert_eq!(Message::decode(&packet), Err(MessageError::InvalidLength { id: NAV_PVT, length: 84 }));
+	}
+
+	#[test]
+	fn configuration_test() {
+		assert_eq!(cfg_rate(Duration::from_millis(200)).to_bytes(),
+				   vec![0xb5, 0x62, 0x06, 0x08, 0x06, 0x00, 0xc8, 0x00, 0x01, 0x00, 0x01, 0x00, 0xde, 0x6a]);
+		assert_eq!(cfg_msg(NAV_PVT, 1).to_bytes(),
+				   vec![0xb5, 0x62, 0x06, 0x01, 0x03, 0x00, 0x01, 0x07, 0x01, 0x13, 0x51]);
+	}
+}
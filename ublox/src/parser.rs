@@ -0,0 +1,219 @@
+/// Synchronization characters starting every UBX packet.
+pub const SYNC_CHARS: [u8; 2] = [0xb5, 0x62];
+
+/// Longest payload accepted by the parser, longer ones being considered corrupted.
+pub const MAX_PAYLOAD_LENGTH: usize = 1024;
+
+/// UBX packet: message class and identifier, and little-endian payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+	pub class: u8,
+	pub id: u8,
+	pub payload: Vec<u8>,
+}
+
+impl Packet {
+	pub fn new(class: u8, id: u8, payload: Vec<u8>) -> Self {
+		Self { class, id, payload }
+	}
+
+	/// Frames the packet with synchronization characters, length and checksum.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(self.payload.len() + 8);
+
+		bytes.extend_from_slice(&SYNC_CHARS);
+		bytes.push(self.class);
+		bytes.push(self.id);
+		bytes.extend_from_slice(&(self.payload.len() as u16).to_le_bytes());
+		bytes.extend_from_slice(&self.payload);
+
+		let (checksum_a, checksum_b) = checksum(&bytes[2..]);
+		bytes.push(checksum_a);
+		bytes.push(checksum_b);
+
+		bytes
+	}
+}
+
+/// 8-bit Fletcher checksum of class, identifier, length and payload bytes (protocol
+/// specification, section 32.4).
+pub fn checksum(bytes: &[u8]) -> (u8, u8) {
+	bytes.iter().fold((0u8, 0u8), |(a, b), &byte| {
+		let a = a.wrapping_add(byte);
+		(a, b.wrapping_add(a))
+	})
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+	Sync1,
+	Sync2,
+	Class,
+	Id,
+	Length1,
+	Length2,
+	Payload,
+	ChecksumA,
+	ChecksumB,
+}
+
+/// Incremental UBX parser, fed with raw bytes as they are received. Bytes between packets, such
+/// as NMEA sentences or SPI idle bytes, are skipped.
+pub struct Parser {
+	state: State,
+	class: u8,
+	id: u8,
+	length: usize,
+	payload: Vec<u8>,
+	checksum: (u8, u8),
+	/// Received checksum A, checked along with checksum B.
+	checksum_a: u8,
+	errors: usize,
+}
+
+impl Parser {
+	pub fn new() -> Self {
+		Self {
+			state: State::Sync1,
+			class: 0,
+			id: 0,
+			length: 0,
+			payload: Vec::new(),
+			checksum: (0, 0),
+			checksum_a: 0,
+			errors: 0,
+		}
+	}
+
+	/// Number of packets discarded so far because of an invalid length or checksum.
+	pub fn errors(&self) -> usize {
+		self.errors
+	}
+
+	/// Parses a chunk of bytes, returning the packets completed by this chunk.
+	pub fn parse(&mut self, bytes: &[u8]) -> Vec<Packet> {
+		bytes.iter().filter_map(|&byte| self.parse_byte(byte)).collect()
+	}
+
+	/// Parses a single byte, returning a packet if this byte completes it.
+	pub fn parse_byte(&mut self, byte: u8) -> Option<Packet> {
+		match self.state {
+			State::Sync1 => {
+				if byte == SYNC_CHARS[0] {
+					self.state = State::Sync2;
+				}
+			}
+			State::Sync2 => {
+				self.state = if byte == SYNC_CHARS[1] {
+					State::Class
+				} else if byte == SYNC_CHARS[0] {
+					State::Sync2
+				} else {
+					State::Sync1
+				};
+			}
+			State::Class => {
+				self.checksum = (0, 0);
+				self.update_checksum(byte);
+				self.class = byte;
+				self.state = State::Id;
+			}
+			State::Id => {
+				self.update_checksum(byte);
+				self.id = byte;
+				self.state = State::Length1;
+			}
+			State::Length1 => {
+				self.update_checksum(byte);
+				self.length = byte as usize;
+				self.state = State::Length2;
+			}
+			State::Length2 => {
+				self.update_checksum(byte);
+				self.length |= (byte as usize) << 8;
+				self.payload.clear();
+
+				self.state = if self.length > MAX_PAYLOAD_LENGTH {
+					self.errors += 1;
+					State::Sync1
+				} else if self.length == 0 {
+					State::ChecksumA
+				} else {
+					State::Payload
+				};
+			}
+			State::Payload => {
+				self.update_checksum(byte);
+				self.payload.push(byte);
+
+				if self.payload.len() == self.length {
+					self.state = State::ChecksumA;
+				}
+			}
+			State::ChecksumA => {
+				self.checksum_a = byte;
+				self.state = State::ChecksumB;
+			}
+			State::ChecksumB => {
+				self.state = State::Sync1;
+
+				if (self.checksum_a, byte) == self.checksum {
+					return Some(Packet::new(self.class, self.id, self.payload.clone()));
+				}
+
+				self.errors += 1;
+			}
+		}
+
+		None
+	}
+
+	fn update_checksum(&mut self, byte: u8) {
+		let (a, b) = self.checksum;
+		let a = a.wrapping_add(byte);
+		self.checksum = (a, b.wrapping_add(a));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parser::{Packet, Parser};
+
+	/// CFG-RATE setting a 200 ms measurement period.
+	const CFG_RATE: [u8; 14] = [0xb5, 0x62, 0x06, 0x08, 0x06, 0x00, 0xc8, 0x00, 0x01, 0x00, 0x01, 0x00, 0xde, 0x6a];
+
+	#[test]
+	fn to_bytes_test() {
+		let packet = Packet::new(0x06, 0x08, vec![0xc8, 0x00, 0x01, 0x00, 0x01, 0x00]);
+		assert_eq!(packet.to_bytes(), CFG_RATE.to_vec());
+	}
+
+	#[test]
+	fn parse_test() {
+		// NMEA output and SPI idle bytes around the packet, which is split across two chunks
+		let mut stream = b"$GNGSA,A,3,,,,*2E\r\n".to_vec();
+		stream.extend_from_slice(&[0xff, 0xff, 0xb5]);
+		stream.extend_from_slice(&CFG_RATE);
+		stream.extend_from_slice(&[0xff, 0xff]);
+
+		let mut parser = Parser::new();
+		let mut packets = parser.parse(&stream[..25]);
+		packets.extend(parser.parse(&stream[25..]));
+
+		assert_eq!(packets, vec![Packet::new(0x06, 0x08, vec![0xc8, 0x00, 0x01, 0x00, 0x01, 0x00])]);
+		assert_eq!(parser.errors(), 0);
+	}
+
+	#[test]
+	fn corrupted_checksum_test() {
+		let mut corrupted = CFG_RATE;
+		corrupted[7] ^= 0x01;
+
+		let mut stream = corrupted.to_vec();
+		stream.extend_from_slice(&CFG_RATE);
+
+		let mut parser = Parser::new();
+		assert_eq!(parser.parse(&stream).len(), 1);
+		assert_eq!(parser.errors(), 1);
+	}
+}
@@ -0,0 +1,134 @@
+use spidev::{Spidev, SpidevOptions, SpiModeFlags, SpidevTransfer};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use std::{io, thread};
+
+use crate::messages::{cfg_msg, cfg_prt_spi, cfg_rate, Message, NAV_PVT, NAV_STATUS};
+use crate::parser::{Packet, Parser};
+
+/// u-blox receiver driver using Linux `Spidev`. On Navio2, the receiver is wired on
+/// `/dev/spidev0.0`.
+pub struct Ublox {
+	spi: Spidev,
+	parser: Parser,
+	/// Packets received while sending, not returned yet.
+	pending: VecDeque<Packet>,
+	/// Packets discarded because their payload could not be decoded.
+	decode_errors: usize,
+}
+
+/// Number of bytes read by each SPI transfer. The receiver outputs `0xff` when it has nothing to
+/// send.
+const READ_LENGTH: usize = 256;
+
+/// Time allowed to the receiver to acknowledge a configuration message.
+const ACK_TIMEOUT: Duration = Duration::from_secs(1);
+const ACK_POLL_PERIOD: Duration = Duration::from_millis(10);
+
+impl Ublox {
+	pub fn new(spi_path: &str) -> Result<Self, io::Error> {
+		let options = SpidevOptions::new()
+			.bits_per_word(8)
+			.max_speed_hz(1_000_000)
+			.mode(SpiModeFlags::SPI_MODE_0)
+			.build();
+
+		let mut spi = Spidev::open(spi_path)?;
+		spi.configure(&options)?;
+
+		Ok(Self {
+			spi,
+			parser: Parser::new(),
+			pending: VecDeque::new(),
+			decode_errors: 0,
+		})
+	}
+
+	/// Switches the SPI port to UBX only, sets the period between two navigation solutions, and
+	/// enables NAV-PVT and NAV-STATUS output for each solution.
+	pub fn configure(&mut self, navigation_period: Duration) -> Result<(), io::Error> {
+		self.send_acknowledged(&cfg_prt_spi())?;
+		self.send_acknowledged(&cfg_rate(navigation_period))?;
+		self.send_acknowledged(&cfg_msg(NAV_PVT, 1))?;
+		self.send_acknowledged(&cfg_msg(NAV_STATUS, 1))?;
+
+		Ok(())
+	}
+
+	/// Number of packets discarded so far because of an invalid length or checksum.
+	pub fn errors(&self) -> usize {
+		self.parser.errors() + self.decode_errors
+	}
+
+	fn transfer(&mut self, tx_buffer: &[u8]) -> Result<Vec<Packet>, io::Error> {
+		let mut rx_buffer = vec![0u8; tx_buffer.len()];
+		self.spi.transfer(&mut SpidevTransfer::read_write(tx_buffer, &mut rx_buffer))?;
+
+		Ok(self.parser.parse(&rx_buffer))
+	}
+
+	/// Sends a packet. SPI being full-duplex, packets received meanwhile are kept for the next
+	/// read.
+	pub fn send(&mut self, packet: &Packet) -> Result<(), io::Error> {
+		let packets = self.transfer(&packet.to_bytes())?;
+		self.pending.extend(packets);
+
+		Ok(())
+	}
+
+	/// Sends a configuration packet and waits for the receiver to acknowledge it. Other messages
+	/// received meanwhile are discarded.
+	pub fn send_acknowledged(&mut self, packet: &Packet) -> Result<(), io::Error> {
+		let id = (packet.class, packet.id);
+		let start = Instant::now();
+
+		self.send(packet)?;
+
+		loop {
+			for message in self.read_messages()? {
+				match message {
+					Message::AckAck(ack_id) if ack_id == id => return Ok(()),
+					Message::AckNak(nak_id) if nak_id == id =>
+						return Err(io::Error::new(
+							ErrorKind::InvalidInput,
+							format!("Receiver rejected UBX message {:#04x} {:#04x}", id.0, id.1))),
+					_ => {}
+				}
+			}
+
+			if start.elapsed() > ACK_TIMEOUT {
+				return Err(io::Error::new(
+					ErrorKind::TimedOut,
+					format!("Receiver did not acknowledge UBX message {:#04x} {:#04x}", id.0, id.1)));
+			}
+
+			thread::sleep(ACK_POLL_PERIOD);
+		}
+	}
+
+	/// Reads the packets output since the previous read, oldest first.
+	pub fn read(&mut self) -> Result<Vec<Packet>, io::Error> {
+		let packets = self.transfer(&[0xff; READ_LENGTH])?;
+
+		let mut all_packets: Vec<Packet> = self.pending.drain(..).collect();
+		all_packets.extend(packets);
+
+		Ok(all_packets)
+	}
+
+	/// Reads and decodes the messages output since the previous read, oldest first. Messages
+	/// that cannot be decoded are discarded.
+	pub fn read_messages(&mut self) -> Result<Vec<Message>, io::Error> {
+		let mut messages = Vec::new();
+
+		for packet in self.read()? {
+			match Message::decode(&packet) {
+				Ok(message) => messages.push(message),
+				Err(_) => self.decode_errors += 1,
+			}
+		}
+
+		Ok(messages)
+	}
+}